@@ -2,7 +2,6 @@ use std::convert::TryInto;
 use std::fmt;
 use std::fs::File;
 use std::time::Instant;
-use std::usize::MAX;
 use criterion::measurement::WallTime;
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkGroup, Criterion};
 use rand::distributions::{Distribution, Standard};
@@ -12,6 +11,7 @@ use reed_solomon_erasure::galois_8::ReedSolomon;
 
 type Shards = Vec<Vec<u8>>;
 
+#[allow(dead_code)]
 const FILE_SIZE: usize = 1024 * 1024 * 1024;
 const MB: usize = 1024 * 1024;
 // 性能结果结构体
@@ -68,7 +68,7 @@ impl PerformanceLogger {
         let file = File::create("reed_solomon_benchmark_results.csv").expect("无法创建CSV文件");
         let mut writer = csv::Writer::from_writer(file);
 
-        writer.write_record(&[
+        writer.write_record([
             "encoding_symbol_length_kb",
             "max_source_block_length",
             "max_number_of_parity_symbols",
@@ -122,11 +122,7 @@ fn create_shards(block_size: usize, data: usize, parity: usize) -> Shards {
     });
 
     // Create empty parity shards
-    shards.resize_with(data + parity, || {
-        let mut vec = Vec::with_capacity(block_size);
-        vec.resize(block_size, 0);
-        vec
-    });
+    shards.resize_with(data + parity, || vec![0; block_size]);
 
     shards
 }
@@ -498,7 +494,7 @@ fn speed_optimized_benchmarks(c: &mut Criterion) {
     let transfer_length: usize = 1024 * MB;
     let max_source_block_number = u8::MAX as usize;
     const MAX_TRANSFER_LENGTH: usize = 0xFFFFFFFFFFFF; // 48 bits max
-    let K = 1024;
+    const K: usize = 1024;
     // 定义独立的参数候选项
     let encoding_symbol_length_options = [10 * K, 20 * K, 30 * K, 40 * K, 50 * K]; // u16
     let max_source_block_length_options = [8, 16, 32, 64, 128]; // u32
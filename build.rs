@@ -1,3 +1,5 @@
+#![allow(clippy::needless_range_loop)]
+
 use std::env;
 use std::fs::File;
 use std::io::Write;
@@ -17,7 +19,7 @@ fn gen_log_table(polynomial: usize) -> [u8; FIELD_SIZE] {
     for log in 0..FIELD_SIZE - 1 {
         result[b] = log as u8;
 
-        b = b << 1;
+        b <<= 1;
 
         if FIELD_SIZE <= b {
             b = (b - FIELD_SIZE) ^ polynomial;
@@ -164,14 +166,13 @@ fn compile_simd_c() {
     match env::var("RUST_REED_SOLOMON_ERASURE_ARCH") {
         Ok(arch) => {
             // Use explicitly specified environment variable as architecture.
-            build.flag(&format!("-march={}", arch));
+            build.flag(format!("-march={}", arch));
         }
         Err(_error) => {
             // On x86-64 enabling Haswell architecture unlocks useful instructions and improves performance
             // dramatically while allowing it to run ony modern CPU.
-            match env::var("CARGO_CFG_TARGET_ARCH").unwrap().as_str(){
-                "x86_64"  => { build.flag(&"-march=haswell"); },
-                _         => ()
+            if env::var("CARGO_CFG_TARGET_ARCH").unwrap() == "x86_64" {
+                build.flag("-march=haswell");
             }
         }
     }
@@ -0,0 +1,34 @@
+/*
+ * Encodes data shards with the RS codec of Hadoop HDFS, for the interop
+ * tests of src/profile.rs.
+ *
+ * Usage: java -cp "$(hadoop classpath)" ProfileHdfs.java <data shards> <parity shards> <shard length>
+ *
+ * Reads the data shards one after the other from stdin and writes the
+ * parity shards to stdout.
+ */
+import java.io.DataInputStream;
+
+import org.apache.hadoop.io.erasurecode.ErasureCoderOptions;
+import org.apache.hadoop.io.erasurecode.rawcoder.RSRawEncoder;
+
+public class ProfileHdfs {
+    public static void main(String[] args) throws Exception {
+        int k = Integer.parseInt(args[0]);
+        int m = Integer.parseInt(args[1]);
+        int len = Integer.parseInt(args[2]);
+
+        byte[][] data = new byte[k][len];
+        byte[][] parity = new byte[m][len];
+        DataInputStream in = new DataInputStream(System.in);
+        for (byte[] shard : data) {
+            in.readFully(shard);
+        }
+
+        new RSRawEncoder(new ErasureCoderOptions(k, m)).encode(data, parity);
+        for (byte[] shard : parity) {
+            System.out.write(shard);
+        }
+        System.out.flush();
+    }
+}
@@ -0,0 +1,46 @@
+/*
+ * Encodes data shards with ISA-L, for the interop tests of src/profile.rs.
+ *
+ * Usage: profile_isal rs|cauchy <data shards> <parity shards> <shard length>
+ *
+ * Reads the data shards one after the other from stdin and writes the
+ * parity shards to stdout.
+ *
+ * Build: cc -O2 -o profile_isal profile_isal.c -lisal
+ */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include <isa-l/erasure_code.h>
+
+int main(int argc, char **argv)
+{
+	if (argc != 5) {
+		fprintf(stderr, "usage: %s rs|cauchy <k> <m> <len>\n", argv[0]);
+		return 2;
+	}
+	int k = atoi(argv[2]);
+	int m = atoi(argv[3]);
+	int len = atoi(argv[4]);
+
+	unsigned char *matrix = malloc((k + m) * k);
+	unsigned char *tables = malloc(32 * k * m);
+	unsigned char *buf = malloc((k + m) * len);
+	unsigned char *shards[256];
+	for (int i = 0; i < k + m; i++)
+		shards[i] = buf + i * len;
+
+	if (strcmp(argv[1], "rs") == 0)
+		gf_gen_rs_matrix(matrix, k + m, k);
+	else
+		gf_gen_cauchy1_matrix(matrix, k + m, k);
+
+	if (fread(buf, len, k, stdin) != (size_t)k)
+		return 1;
+	ec_init_tables(k, m, matrix + k * k, tables);
+	ec_encode_data(len, k, m, tables, shards, shards + k);
+	if (fwrite(buf + k * len, len, m, stdout) != (size_t)m)
+		return 1;
+	return 0;
+}
@@ -0,0 +1,42 @@
+/*
+ * Encodes data shards with Jerasure, for the interop tests of src/profile.rs.
+ *
+ * Usage: profile_jerasure <data shards> <parity shards> <shard length>
+ *
+ * Reads the data shards one after the other from stdin and writes the
+ * parity shards to stdout. The shard length must be a multiple of
+ * sizeof(long).
+ *
+ * Build: cc -O2 -I/usr/include/jerasure -o profile_jerasure profile_jerasure.c \
+ *            -lJerasure -lgf_complete
+ */
+#include <stdio.h>
+#include <stdlib.h>
+
+#include <jerasure.h>
+#include <reed_sol.h>
+
+int main(int argc, char **argv)
+{
+	if (argc != 4) {
+		fprintf(stderr, "usage: %s <k> <m> <len>\n", argv[0]);
+		return 2;
+	}
+	int k = atoi(argv[1]);
+	int m = atoi(argv[2]);
+	int len = atoi(argv[3]);
+
+	char *buf = malloc((k + m) * len);
+	char *shards[256];
+	for (int i = 0; i < k + m; i++)
+		shards[i] = buf + i * len;
+
+	int *matrix = reed_sol_vandermonde_coding_matrix(k, m, 8);
+
+	if (fread(buf, len, k, stdin) != (size_t)k)
+		return 1;
+	jerasure_matrix_encode(k, m, 8, matrix, shards, shards + k, len);
+	if (fwrite(buf + k * len, len, m, stdout) != (size_t)m)
+		return 1;
+	return 0;
+}
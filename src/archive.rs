@@ -0,0 +1,734 @@
+//! Erasure-coded archives of several files.
+//!
+//! An archive is a single file storing the concatenation of its member files
+//! as a sequence of stripes. Every stripe holds `data_shards * shard_size`
+//! bytes, the last one padded with zeros, and is stored as `total_shard_count`
+//! shard frames (see `shard_format`) of equal length, so the position of every
+//! frame is known in advance. Reading a member file only reads the stripes
+//! overlapping it, and only reads parity frames of a stripe if one of its data
+//! frames is damaged.
+//!
+//! The manifest lists the member files with their offset and length in the
+//! concatenation, and the CRC-32C of every frame of every stripe. It is stored
+//! after the stripes as a stripe of its own, encoded with `MANIFEST_DATA_SHARDS`
+//! data shards and `MANIFEST_PARITY_SHARDS` parity shards, so that it survives
+//! damage that makes stripes of the member files unreadable.
+//!
+//! # Layout
+//!
+//! | Part | Size |
+//! | --- | --- |
+//! | header | `HEADER_LEN` |
+//! | stripes of the member files | `stripe count * total_shard_count * frame length` |
+//! | manifest | `(MANIFEST_DATA_SHARDS + MANIFEST_PARITY_SHARDS) * manifest frame length` |
+//! | footer, a copy of the header | `HEADER_LEN` |
+//!
+//! The header records the codec parameters, the shard size, the stripe count,
+//! and the position and frame length of the manifest. All integers are
+//! little-endian:
+//!
+//! | Offset | Size | Field |
+//! | --- | --- | --- |
+//! | 0 | 4 | magic bytes `RSEA` |
+//! | 4 | 1 | format version, currently `1` |
+//! | 5 | 3 | reserved, zero |
+//! | 8 | 4 | data shard count |
+//! | 12 | 4 | parity shard count |
+//! | 16 | 8 | shard size in bytes |
+//! | 24 | 8 | stripe count |
+//! | 32 | 8 | offset of the manifest |
+//! | 40 | 8 | length of a manifest frame |
+//! | 48 | 4 | manifest data shard count |
+//! | 52 | 4 | manifest parity shard count |
+//! | 56 | 4 | reserved, zero |
+//! | 60 | 4 | CRC-32C of the previous 60 bytes |
+//!
+//! The manifest stripe has ID `u64::MAX`, and holds:
+//!
+//! | Size | Field |
+//! | --- | --- |
+//! | 8 | total length of the member files |
+//! | 4 | member file count |
+//! | per member file: 4, name length, 8, 8 | name length, UTF-8 name, offset, length |
+//! | per frame of every stripe: 4 | CRC-32C of the frame |
+//!
+//! This module is only available with the `std` feature enabled.
+//!
+//! # Example
+//!
+//! ```
+//! # use std::io::Cursor;
+//! # use reed_solomon_erasure::archive::{ArchiveReader, ArchiveWriter};
+//! let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()), 4, 2, 1024).unwrap();
+//! writer.add_file("a.txt", &b"first file"[..]).unwrap();
+//! writer.add_file("b.txt", &b"second file"[..]).unwrap();
+//! let archive = writer.finish().unwrap().into_inner();
+//!
+//! let mut reader = ArchiveReader::open(Cursor::new(archive)).unwrap();
+//! assert_eq!(2, reader.entries().len());
+//! assert_eq!(&b"second file"[..], &reader.read_file("b.txt").unwrap()[..]);
+//! ```
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::errors::Error;
+use crate::galois_8::ReedSolomon;
+use crate::shard_format::{self, decode_stripe, encode_stripe, Checksum, Crc32c};
+
+/// Magic bytes at the start of the header and the footer.
+pub const MAGIC: [u8; 4] = *b"RSEA";
+
+/// Current version of the archive format.
+pub const VERSION: u8 = 1;
+
+/// Length of the header and the footer.
+pub const HEADER_LEN: usize = 64;
+
+/// Number of data shards of the manifest stripe.
+pub const MANIFEST_DATA_SHARDS: usize = 2;
+
+/// Number of parity shards of the manifest stripe.
+pub const MANIFEST_PARITY_SHARDS: usize = 6;
+
+const MANIFEST_STRIPE_ID: u64 = u64::MAX;
+
+fn invalid_input(e: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e)
+}
+
+fn invalid_data<E>(e: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn crc32c(bytes: &[u8]) -> u32 {
+    let mut checksum = Crc32c::new();
+    checksum.update(bytes);
+    let mut out = [0u8; 4];
+    checksum.finish(&mut out);
+    u32::from_le_bytes(out)
+}
+
+/// A member file of an archive.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ArchiveEntry {
+    /// Name of the file.
+    pub name: String,
+    /// Offset of the file in the concatenation of all member files.
+    pub offset: u64,
+    /// Length of the file in bytes.
+    pub len: u64,
+}
+
+// Contents of the header and the footer.
+#[derive(PartialEq, Debug, Clone, Copy)]
+struct Geometry {
+    data_shards: usize,
+    parity_shards: usize,
+    shard_size: usize,
+    stripes: u64,
+    manifest_offset: u64,
+    manifest_frame_len: usize,
+    manifest_data_shards: usize,
+    manifest_parity_shards: usize,
+}
+
+impl Geometry {
+    fn frame_len(&self) -> usize {
+        shard_format::frame_len::<Crc32c>(self.shard_size)
+    }
+
+    fn stripe_len(&self) -> u64 {
+        (self.data_shards * self.shard_size) as u64
+    }
+
+    fn frame_offset(&self, stripe: u64, index: usize) -> u64 {
+        let stored_stripe_len = ((self.data_shards + self.parity_shards) * self.frame_len()) as u64;
+        HEADER_LEN as u64 + stripe * stored_stripe_len + (index * self.frame_len()) as u64
+    }
+
+    // Checks that the stripes and the manifest lie within an input of
+    // `input_len` bytes, as laid out by `ArchiveWriter`.
+    fn fits(&self, input_len: u64) -> bool {
+        let stripes_end = (self.shard_size as u64)
+            .checked_add((shard_format::HEADER_LEN + Crc32c::LEN) as u64)
+            .and_then(|frame_len| {
+                frame_len.checked_mul((self.data_shards + self.parity_shards) as u64)
+            })
+            .and_then(|stored_stripe_len| stored_stripe_len.checked_mul(self.stripes))
+            .and_then(|stored_len| stored_len.checked_add(HEADER_LEN as u64));
+
+        stripes_end == Some(self.manifest_offset)
+            && self.manifest_offset <= input_len
+            && self.manifest_frame_len as u64 <= input_len
+    }
+
+    fn to_bytes(self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4] = VERSION;
+        bytes[8..12].copy_from_slice(&(self.data_shards as u32).to_le_bytes());
+        bytes[12..16].copy_from_slice(&(self.parity_shards as u32).to_le_bytes());
+        bytes[16..24].copy_from_slice(&(self.shard_size as u64).to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.stripes.to_le_bytes());
+        bytes[32..40].copy_from_slice(&self.manifest_offset.to_le_bytes());
+        bytes[40..48].copy_from_slice(&(self.manifest_frame_len as u64).to_le_bytes());
+        bytes[48..52].copy_from_slice(&(self.manifest_data_shards as u32).to_le_bytes());
+        bytes[52..56].copy_from_slice(&(self.manifest_parity_shards as u32).to_le_bytes());
+        let crc = crc32c(&bytes[..60]);
+        bytes[60..64].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; HEADER_LEN]) -> Option<Geometry> {
+        let u32_at = |i: usize| {
+            let mut buf = [0; 4];
+            buf.copy_from_slice(&bytes[i..i + 4]);
+            u32::from_le_bytes(buf)
+        };
+        let u64_at = |i: usize| {
+            let mut buf = [0; 8];
+            buf.copy_from_slice(&bytes[i..i + 8]);
+            u64::from_le_bytes(buf)
+        };
+
+        if bytes[0..4] != MAGIC || bytes[4] != VERSION || crc32c(&bytes[..60]) != u32_at(60) {
+            return None;
+        }
+
+        Some(Geometry {
+            data_shards: u32_at(8) as usize,
+            parity_shards: u32_at(12) as usize,
+            shard_size: u64_at(16) as usize,
+            stripes: u64_at(24),
+            manifest_offset: u64_at(32),
+            manifest_frame_len: u64_at(40) as usize,
+            manifest_data_shards: u32_at(48) as usize,
+            manifest_parity_shards: u32_at(52) as usize,
+        })
+    }
+}
+
+/// Writer of an archive.
+///
+/// The header is only complete once `finish` is called, so the output
+/// needs to be seekable.
+pub struct ArchiveWriter<W: Write + Seek> {
+    out: W,
+    codec: ReedSolomon,
+    geometry: Geometry,
+    entries: Vec<ArchiveEntry>,
+    frame_crcs: Vec<u32>,
+    stripe: Vec<u8>,
+    total_len: u64,
+}
+
+impl<W: Write + Seek> ArchiveWriter<W> {
+    /// Creates a new archive, writing it into `out`.
+    ///
+    /// Returns an error of kind `InvalidInput` wrapping the errors of
+    /// `ReedSolomon::new` for invalid shard counts, or `Error::EmptyShard`
+    /// if `shard_size == 0`.
+    pub fn new(
+        mut out: W,
+        data_shards: usize,
+        parity_shards: usize,
+        shard_size: usize,
+    ) -> io::Result<ArchiveWriter<W>> {
+        let codec = ReedSolomon::new(data_shards, parity_shards).map_err(invalid_input)?;
+        if shard_size == 0 {
+            return Err(invalid_input(Error::EmptyShard));
+        }
+
+        // placeholder, written once the manifest is in place
+        out.write_all(&[0u8; HEADER_LEN])?;
+
+        Ok(ArchiveWriter {
+            out,
+            codec,
+            geometry: Geometry {
+                data_shards,
+                parity_shards,
+                shard_size,
+                stripes: 0,
+                manifest_offset: 0,
+                manifest_frame_len: 0,
+                manifest_data_shards: MANIFEST_DATA_SHARDS,
+                manifest_parity_shards: MANIFEST_PARITY_SHARDS,
+            },
+            entries: Vec::new(),
+            frame_crcs: Vec::new(),
+            stripe: Vec::with_capacity(data_shards * shard_size),
+            total_len: 0,
+        })
+    }
+
+    fn write_stripe(&mut self) -> io::Result<()> {
+        self.stripe.resize(self.geometry.stripe_len() as usize, 0);
+
+        let frames = encode_stripe::<Crc32c>(&self.codec, self.geometry.stripes, 0, &self.stripe)
+            .map_err(invalid_input)?;
+        for frame in frames.iter() {
+            self.frame_crcs.push(crc32c(frame));
+            self.out.write_all(frame)?;
+        }
+
+        self.geometry.stripes += 1;
+        self.stripe.clear();
+        Ok(())
+    }
+
+    /// Appends a file named `name` with the contents read from `input`.
+    ///
+    /// Returns the length of the file.
+    pub fn add_file<R: Read>(&mut self, name: &str, mut input: R) -> io::Result<u64> {
+        let offset = self.total_len;
+        let stripe_len = self.geometry.stripe_len();
+
+        loop {
+            let wanted = stripe_len - self.stripe.len() as u64;
+            let n = (&mut input).take(wanted).read_to_end(&mut self.stripe)?;
+            self.total_len += n as u64;
+
+            if self.stripe.len() as u64 == stripe_len {
+                self.write_stripe()?;
+            }
+            if n == 0 {
+                break;
+            }
+        }
+
+        let len = self.total_len - offset;
+        self.entries.push(ArchiveEntry {
+            name: name.to_string(),
+            offset,
+            len,
+        });
+        Ok(len)
+    }
+
+    /// Writes the last stripe, the manifest, the header and the footer.
+    ///
+    /// Returns the output.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.stripe.is_empty() {
+            self.write_stripe()?;
+        }
+
+        let mut manifest = Vec::new();
+        manifest.extend_from_slice(&self.total_len.to_le_bytes());
+        manifest.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for entry in self.entries.iter() {
+            manifest.extend_from_slice(&(entry.name.len() as u32).to_le_bytes());
+            manifest.extend_from_slice(entry.name.as_bytes());
+            manifest.extend_from_slice(&entry.offset.to_le_bytes());
+            manifest.extend_from_slice(&entry.len.to_le_bytes());
+        }
+        for crc in self.frame_crcs.iter() {
+            manifest.extend_from_slice(&crc.to_le_bytes());
+        }
+
+        let manifest_codec = ReedSolomon::new(MANIFEST_DATA_SHARDS, MANIFEST_PARITY_SHARDS)
+            .expect("manifest shard counts are valid; qed");
+        let frames = encode_stripe::<Crc32c>(&manifest_codec, MANIFEST_STRIPE_ID, 0, &manifest)
+            .expect("manifest is never empty; qed");
+
+        self.geometry.manifest_offset = self.geometry.frame_offset(self.geometry.stripes, 0);
+        self.geometry.manifest_frame_len = frames[0].len();
+        for frame in frames.iter() {
+            self.out.write_all(frame)?;
+        }
+
+        let header = self.geometry.to_bytes();
+        self.out.write_all(&header)?;
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(&header)?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
+
+/// Reader of an archive.
+pub struct ArchiveReader<R: Read + Seek> {
+    input: R,
+    codec: ReedSolomon,
+    geometry: Geometry,
+    entries: Vec<ArchiveEntry>,
+    frame_crcs: Vec<u32>,
+}
+
+// Parses the manifest, returning the entries and the frame checksums.
+fn parse_manifest(manifest: &[u8], geometry: &Geometry) -> Option<(Vec<ArchiveEntry>, Vec<u32>)> {
+    let mut rest = manifest;
+    let mut take = |len: usize| {
+        if rest.len() < len {
+            return None;
+        }
+        let (head, tail) = rest.split_at(len);
+        rest = tail;
+        Some(head)
+    };
+
+    let mut u64_buf = [0u8; 8];
+    let mut u32_buf = [0u8; 4];
+
+    u64_buf.copy_from_slice(take(8)?);
+    let total_len = u64::from_le_bytes(u64_buf);
+    u32_buf.copy_from_slice(take(4)?);
+    let entry_count = u32::from_le_bytes(u32_buf);
+
+    let mut entries = Vec::new();
+    for _ in 0..entry_count {
+        u32_buf.copy_from_slice(take(4)?);
+        let name = String::from_utf8(take(u32::from_le_bytes(u32_buf) as usize)?.to_vec()).ok()?;
+        u64_buf.copy_from_slice(take(8)?);
+        let offset = u64::from_le_bytes(u64_buf);
+        u64_buf.copy_from_slice(take(8)?);
+        let len = u64::from_le_bytes(u64_buf);
+
+        if offset.checked_add(len)? > total_len {
+            return None;
+        }
+        entries.push(ArchiveEntry { name, offset, len });
+    }
+
+    let frame_count = usize::try_from(geometry.stripes)
+        .ok()?
+        .checked_mul(geometry.data_shards + geometry.parity_shards)?;
+    if frame_count.checked_mul(4)? > manifest.len() {
+        return None;
+    }
+    let mut frame_crcs = Vec::with_capacity(frame_count);
+    for _ in 0..frame_count {
+        u32_buf.copy_from_slice(take(4)?);
+        frame_crcs.push(u32::from_le_bytes(u32_buf));
+    }
+
+    if total_len > geometry.stripes.checked_mul(geometry.stripe_len())? {
+        return None;
+    }
+
+    Some((entries, frame_crcs))
+}
+
+impl<R: Read + Seek> ArchiveReader<R> {
+    /// Opens an archive.
+    ///
+    /// Uses the footer if the header is damaged or describes an archive
+    /// larger than the input, and reconstructs the manifest if some of its
+    /// frames are damaged.
+    ///
+    /// Returns an error of kind `InvalidData` if neither the header nor the
+    /// footer is valid, or the manifest cannot be recovered.
+    pub fn open(mut input: R) -> io::Result<ArchiveReader<R>> {
+        let input_len = input.seek(SeekFrom::End(0))?;
+
+        let mut geometry = None;
+        for &offset in [0, input_len.saturating_sub(HEADER_LEN as u64)].iter() {
+            let mut header = [0u8; HEADER_LEN];
+            input.seek(SeekFrom::Start(offset))?;
+            if input.read_exact(&mut header).is_ok() {
+                geometry = Geometry::from_bytes(&header).filter(|g| g.fits(input_len));
+            }
+            if geometry.is_some() {
+                break;
+            }
+        }
+        let geometry = geometry.ok_or_else(|| invalid_data("no valid archive header or footer"))?;
+
+        let codec =
+            ReedSolomon::new(geometry.data_shards, geometry.parity_shards).map_err(invalid_data)?;
+        let manifest_codec = ReedSolomon::new(
+            geometry.manifest_data_shards,
+            geometry.manifest_parity_shards,
+        )
+        .map_err(invalid_data)?;
+        if geometry.shard_size == 0 {
+            return Err(invalid_data(Error::EmptyShard));
+        }
+
+        let mut frames = Vec::with_capacity(manifest_codec.total_shard_count());
+        for index in 0..manifest_codec.total_shard_count() {
+            let offset = match (index as u64)
+                .checked_mul(geometry.manifest_frame_len as u64)
+                .and_then(|offset| offset.checked_add(geometry.manifest_offset))
+            {
+                Some(offset) => offset,
+                None => break,
+            };
+            let mut frame = vec![0u8; geometry.manifest_frame_len];
+            input.seek(SeekFrom::Start(offset))?;
+            if input.read_exact(&mut frame).is_ok() {
+                frames.push(frame);
+            }
+        }
+        let manifest = decode_stripe::<Crc32c, _>(&manifest_codec, MANIFEST_STRIPE_ID, &frames)
+            .map_err(invalid_data)?;
+        let (entries, frame_crcs) = parse_manifest(&manifest, &geometry)
+            .ok_or_else(|| invalid_data("malformed manifest"))?;
+
+        Ok(ArchiveReader {
+            input,
+            codec,
+            geometry,
+            entries,
+            frame_crcs,
+        })
+    }
+
+    /// The member files of the archive.
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    // Reads the frame at `index` of a stripe, if it matches its checksum.
+    fn read_frame(&mut self, stripe: u64, index: usize) -> io::Result<Option<Vec<u8>>> {
+        let mut frame = vec![0u8; self.geometry.frame_len()];
+        self.input
+            .seek(SeekFrom::Start(self.geometry.frame_offset(stripe, index)))?;
+        if self.input.read_exact(&mut frame).is_err() {
+            return Ok(None);
+        }
+
+        let i_crc = stripe as usize * self.codec.total_shard_count() + index;
+        if crc32c(&frame) != self.frame_crcs[i_crc] {
+            return Ok(None);
+        }
+        Ok(Some(frame))
+    }
+
+    // Reads the data of a stripe, only reading parity frames if needed.
+    fn read_stripe(&mut self, stripe: u64) -> io::Result<Vec<u8>> {
+        let data_shards = self.codec.data_shard_count();
+
+        let mut frames = Vec::with_capacity(self.codec.total_shard_count());
+        for index in 0..data_shards {
+            frames.extend(self.read_frame(stripe, index)?);
+        }
+        if frames.len() < data_shards {
+            for index in data_shards..self.codec.total_shard_count() {
+                frames.extend(self.read_frame(stripe, index)?);
+            }
+        }
+
+        decode_stripe::<Crc32c, _>(&self.codec, stripe, &frames).map_err(invalid_data)
+    }
+
+    /// Reads the member file at `index` of `entries`, reading only the
+    /// stripes overlapping it.
+    ///
+    /// Returns an error of kind `InvalidInput` wrapping `Error::InvalidIndex`
+    /// if `index` is out of range, and an error of kind `InvalidData` if a
+    /// stripe cannot be recovered. Other I/O errors are passed through.
+    pub fn read_entry(&mut self, index: usize) -> io::Result<Vec<u8>> {
+        let entry = self
+            .entries
+            .get(index)
+            .cloned()
+            .ok_or_else(|| invalid_input(Error::InvalidIndex))?;
+
+        let mut out = Vec::with_capacity(entry.len as usize);
+        if entry.len == 0 {
+            return Ok(out);
+        }
+
+        let stripe_len = self.geometry.stripe_len();
+        let end = entry.offset + entry.len;
+        for stripe in entry.offset / stripe_len..=(end - 1) / stripe_len {
+            let data = self.read_stripe(stripe)?;
+
+            let stripe_start = stripe * stripe_len;
+            let from = entry.offset.max(stripe_start) - stripe_start;
+            let to = end.min(stripe_start + stripe_len) - stripe_start;
+            out.extend_from_slice(&data[from as usize..to as usize]);
+        }
+
+        Ok(out)
+    }
+
+    /// Reads the first member file named `name`.
+    ///
+    /// Returns an error of kind `NotFound` if there is no such file,
+    /// otherwise behaves like `read_entry`.
+    pub fn read_file(&mut self, name: &str) -> io::Result<Vec<u8>> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.name == name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such file in archive"))?;
+        self.read_entry(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fill_random;
+    use std::io::Cursor;
+
+    // Input recording the offsets of all reads.
+    struct Tracking {
+        inner: Cursor<Vec<u8>>,
+        reads: Vec<u64>,
+    }
+
+    impl Read for Tracking {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads.push(self.inner.position());
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for Tracking {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    fn geometry_of(archive: &[u8]) -> Geometry {
+        let mut header = [0u8; HEADER_LEN];
+        header.copy_from_slice(&archive[..HEADER_LEN]);
+        Geometry::from_bytes(&header).unwrap()
+    }
+
+    fn make_archive(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()), 4, 2, 100).unwrap();
+        for (name, contents) in files.iter() {
+            assert_eq!(
+                contents.len() as u64,
+                writer.add_file(name, &contents[..]).unwrap()
+            );
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn random_files() -> Vec<(&'static str, Vec<u8>)> {
+        [("a", 1000), ("empty", 0), ("b", 1), ("c", 399), ("d", 2500)]
+            .iter()
+            .map(|&(name, len)| {
+                let mut contents = vec![0u8; len];
+                fill_random(&mut contents);
+                (name, contents)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let files = random_files();
+        let archive = make_archive(&files);
+
+        let mut reader = ArchiveReader::open(Cursor::new(archive)).unwrap();
+        assert_eq!(files.len(), reader.entries().len());
+        for (name, contents) in files.iter() {
+            assert_eq!(contents, &reader.read_file(name).unwrap());
+        }
+
+        assert_eq!(
+            io::ErrorKind::NotFound,
+            reader.read_file("missing").unwrap_err().kind()
+        );
+        assert_eq!(
+            io::ErrorKind::InvalidInput,
+            reader.read_entry(5).unwrap_err().kind()
+        );
+
+        let empty = ArchiveWriter::new(Cursor::new(Vec::new()), 4, 2, 100)
+            .unwrap()
+            .finish()
+            .unwrap()
+            .into_inner();
+        let reader = ArchiveReader::open(Cursor::new(empty)).unwrap();
+        assert!(reader.entries().is_empty());
+    }
+
+    #[test]
+    fn test_archive_reads_only_overlapping_stripes() {
+        let files = random_files();
+        let archive = make_archive(&files);
+        let geometry = geometry_of(&archive);
+
+        let mut reader = ArchiveReader::open(Tracking {
+            inner: Cursor::new(archive),
+            reads: Vec::new(),
+        })
+        .unwrap();
+
+        // "b" is at 1000..1001, within the third stripe, and only data frames are read
+        reader.input.reads.clear();
+        assert_eq!(files[2].1, reader.read_file("b").unwrap());
+        for &pos in reader.input.reads.iter() {
+            assert!(pos >= geometry.frame_offset(2, 0));
+            assert!(pos < geometry.frame_offset(2, 4));
+        }
+    }
+
+    #[test]
+    fn test_archive_survives_damage() {
+        let files = random_files();
+        let mut archive = make_archive(&files);
+        let geometry = geometry_of(&archive);
+
+        // damage the header, two frames of every stripe, and most of the manifest
+        archive[10] ^= 1;
+        for stripe in 0..geometry.stripes {
+            for &index in [0, 5].iter() {
+                archive[geometry.frame_offset(stripe, index) as usize + 100] ^= 1;
+            }
+        }
+        for index in 0..6 {
+            let offset = geometry.manifest_offset as usize + index * geometry.manifest_frame_len;
+            archive[offset + 70] ^= 1;
+        }
+
+        let mut reader = ArchiveReader::open(Cursor::new(archive.clone())).unwrap();
+        for (name, contents) in files.iter() {
+            assert_eq!(contents, &reader.read_file(name).unwrap());
+        }
+
+        // too much damage in a stripe
+        archive[geometry.frame_offset(0, 1) as usize + 100] ^= 1;
+        let mut reader = ArchiveReader::open(Cursor::new(archive.clone())).unwrap();
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            reader.read_file("a").unwrap_err().kind()
+        );
+        assert_eq!(files[4].1, reader.read_file("d").unwrap());
+
+        // header and footer describing an archive larger than the input
+        let mut oversized = make_archive(&files);
+        let end = oversized.len();
+        let huge = Geometry {
+            stripes: u64::MAX / 2,
+            manifest_frame_len: usize::MAX,
+            ..geometry
+        };
+        oversized[..HEADER_LEN].copy_from_slice(&huge.to_bytes());
+        let mut reader = ArchiveReader::open(Cursor::new(oversized.clone())).unwrap();
+        assert_eq!(files[4].1, reader.read_file("d").unwrap());
+        oversized[end - HEADER_LEN..].copy_from_slice(&huge.to_bytes());
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            ArchiveReader::open(Cursor::new(oversized))
+                .err()
+                .unwrap()
+                .kind()
+        );
+
+        // damaged header and footer
+        let end = archive.len();
+        archive[end - 10] ^= 1;
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            ArchiveReader::open(Cursor::new(archive))
+                .err()
+                .unwrap()
+                .kind()
+        );
+    }
+}
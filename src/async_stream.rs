@@ -0,0 +1,356 @@
+//! Asynchronous streaming encoding and decoding.
+//!
+//! `AsyncStreamEncoder` and `AsyncStreamDecoder` are the counterparts of
+//! `stream::StreamEncoder` and `stream::StreamDecoder` over
+//! `futures::io::{AsyncRead, AsyncWrite}`, and read and write the same shard
+//! streams. They do not depend on any particular runtime.
+//!
+//! Encoding and reconstruction of every stripe runs on the thread pool of the
+//! `blocking` crate, so that coding large stripes does not stall the executor.
+//! Reads and writes of the shard streams are driven concurrently.
+//!
+//! This module is only available with the `async` feature enabled.
+
+use std::io;
+use std::sync::Arc;
+
+use blocking::unblock;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_util::future::{join_all, try_join_all};
+use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::errors::Error;
+use crate::galois_8::ReedSolomon;
+use crate::stream::{
+    invalid_data, invalid_input, make_trailer, vote_total_len, Chunk, HeldStripe, Lookahead,
+};
+
+// Reads until `buf` is full or the end of the stream is reached,
+// returns the number of bytes read.
+async fn read_full<R: AsyncRead + Unpin>(input: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]).await {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Asynchronous encoder from a single `AsyncRead` source into one
+/// `AsyncWrite` sink per shard.
+///
+/// Memory use is bounded by a single stripe, i.e.
+/// `total_shard_count * shard_size` bytes.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use reed_solomon_erasure::galois_8::ReedSolomon;
+/// # use reed_solomon_erasure::async_stream::{AsyncStreamDecoder, AsyncStreamEncoder};
+/// # futures_executor::block_on(async {
+/// let r = Arc::new(ReedSolomon::new(3, 2).unwrap());
+///
+/// let input = b"a stream of bytes";
+/// let mut outputs = vec![Vec::new(); 5];
+/// let encoder = AsyncStreamEncoder::new(r.clone(), 4).unwrap();
+/// encoder.encode(&input[..], &mut outputs).await.unwrap();
+///
+/// let mut inputs: Vec<_> = outputs.iter().map(|o| Some(&o[..])).collect();
+/// inputs[1] = None;
+///
+/// let mut decoded = Vec::new();
+/// let decoder = AsyncStreamDecoder::new(r, 4).unwrap();
+/// decoder.decode(&mut inputs, &mut decoded).await.unwrap();
+/// assert_eq!(&input[..], &decoded[..]);
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct AsyncStreamEncoder {
+    codec: Arc<ReedSolomon>,
+    shard_size: usize,
+}
+
+impl AsyncStreamEncoder {
+    /// Creates a new stream encoder cutting the input into shards of `shard_size` bytes.
+    ///
+    /// Returns `Error::EmptyShard` if `shard_size == 0`.
+    pub fn new(codec: Arc<ReedSolomon>, shard_size: usize) -> Result<AsyncStreamEncoder, Error> {
+        if shard_size == 0 {
+            return Err(Error::EmptyShard);
+        }
+
+        Ok(AsyncStreamEncoder { codec, shard_size })
+    }
+
+    /// The size of a shard of a stripe in bytes.
+    pub fn shard_size(&self) -> usize {
+        self.shard_size
+    }
+
+    /// Encodes all of `input`, writing the shard streams into `outputs`.
+    ///
+    /// Behaves like `StreamEncoder::encode`.
+    pub async fn encode<R, W>(&self, mut input: R, outputs: &mut [W]) -> io::Result<u64>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        if outputs.len() < self.codec.total_shard_count() {
+            return Err(invalid_input(Error::TooFewShards));
+        }
+        if outputs.len() > self.codec.total_shard_count() {
+            return Err(invalid_input(Error::TooManyShards));
+        }
+
+        let data_shards = self.codec.data_shard_count();
+
+        let mut shards = vec![vec![0u8; self.shard_size]; self.codec.total_shard_count()];
+        let mut total_len: u64 = 0;
+
+        loop {
+            let mut stripe_len = 0;
+            for shard in shards[..data_shards].iter_mut() {
+                let n = read_full(&mut input, shard).await?;
+                // pad the rest of the stripe
+                for b in shard[n..].iter_mut() {
+                    *b = 0;
+                }
+                stripe_len += n;
+            }
+
+            if stripe_len == 0 {
+                break;
+            }
+            total_len += stripe_len as u64;
+
+            let codec = self.codec.clone();
+            shards = unblock(move || {
+                let (data, parity) = shards.split_at_mut(data_shards);
+                codec.encode_sep(data, parity).map(|_| shards)
+            })
+            .await
+            .map_err(invalid_input)?;
+
+            try_join_all(
+                shards
+                    .iter()
+                    .zip(outputs.iter_mut())
+                    .map(|(shard, output)| output.write_all(shard)),
+            )
+            .await?;
+
+            if stripe_len < data_shards * self.shard_size {
+                break;
+            }
+        }
+
+        let trailer = make_trailer(total_len);
+        try_join_all(outputs.iter_mut().map(|output| async move {
+            output.write_all(&trailer).await?;
+            output.flush().await
+        }))
+        .await?;
+
+        Ok(total_len)
+    }
+}
+
+/// Asynchronous decoder from the shard streams written by `AsyncStreamEncoder`
+/// or `stream::StreamEncoder` back into the original bytes.
+///
+/// Memory use is bounded by about two stripes.
+#[derive(Debug)]
+pub struct AsyncStreamDecoder {
+    codec: Arc<ReedSolomon>,
+    shard_size: usize,
+}
+
+impl AsyncStreamDecoder {
+    /// Creates a new stream decoder for shard streams written with
+    /// shards of `shard_size` bytes.
+    ///
+    /// Returns `Error::EmptyShard` if `shard_size == 0`.
+    pub fn new(codec: Arc<ReedSolomon>, shard_size: usize) -> Result<AsyncStreamDecoder, Error> {
+        if shard_size == 0 {
+            return Err(Error::EmptyShard);
+        }
+
+        Ok(AsyncStreamDecoder { codec, shard_size })
+    }
+
+    /// The size of a shard of a stripe in bytes.
+    pub fn shard_size(&self) -> usize {
+        self.shard_size
+    }
+
+    /// Decodes the shard streams in `inputs`, writing the original bytes into `output`.
+    ///
+    /// Behaves like `StreamDecoder::decode`, including setting the streams
+    /// which failed to `None`.
+    pub async fn decode<R, W>(&self, inputs: &mut [Option<R>], mut output: W) -> io::Result<u64>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        if inputs.len() < self.codec.total_shard_count() {
+            return Err(invalid_input(Error::TooFewShards));
+        }
+        if inputs.len() > self.codec.total_shard_count() {
+            return Err(invalid_input(Error::TooManyShards));
+        }
+
+        let data_shards = self.codec.data_shard_count();
+
+        let mut lookaheads: Vec<Lookahead> = inputs
+            .iter()
+            .map(|_| Lookahead::new(self.shard_size))
+            .collect();
+        let mut shards = vec![(vec![0u8; self.shard_size], false); inputs.len()];
+        let mut held = HeldStripe::new(data_shards * self.shard_size);
+
+        loop {
+            let results = join_all(
+                inputs
+                    .iter_mut()
+                    .zip(lookaheads.iter_mut())
+                    .zip(shards.iter_mut())
+                    .map(|((input, lookahead), (shard, present))| async move {
+                        *present = false;
+
+                        let reader = input.as_mut()?;
+                        let result = match read_full(reader, lookahead.unfilled()).await {
+                            Ok(n_read) => lookahead.take_chunk(n_read, shard),
+                            Err(e) => Err(e),
+                        };
+                        Some(result)
+                    }),
+            )
+            .await;
+
+            let mut trailers = Vec::new();
+            for (i_input, result) in results.into_iter().enumerate() {
+                match result {
+                    Some(Ok(Chunk::Shard)) => shards[i_input].1 = true,
+                    Some(Ok(Chunk::Trailer(total_len))) => trailers.push((i_input, total_len)),
+                    Some(Err(_)) => inputs[i_input] = None,
+                    None => {}
+                }
+            }
+
+            if shards.iter().any(|&(_, present)| present) {
+                // streams which ended before the others are missing
+                for &(i_input, _) in trailers.iter() {
+                    inputs[i_input] = None;
+                }
+
+                let codec = self.codec.clone();
+                let mut moved = shards;
+                shards = unblock(move || codec.reconstruct_data(&mut moved).map(|_| moved))
+                    .await
+                    .map_err(invalid_data)?;
+
+                output.write_all(held.data()).await?;
+                held.replace(&shards[..data_shards]);
+
+                continue;
+            }
+
+            // all remaining streams are at their trailer
+            let total_len = vote_total_len(inputs, &trailers)?;
+
+            output.write_all(held.last(total_len)?).await?;
+            output.flush().await?;
+
+            return Ok(total_len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::{StreamDecoder, StreamEncoder};
+    use crate::tests::fill_random;
+    use futures_executor::block_on;
+
+    #[test]
+    fn test_async_stream_matches_sync_stream() {
+        let r = Arc::new(ReedSolomon::new(4, 2).unwrap());
+        let encoder = AsyncStreamEncoder::new(r.clone(), 100).unwrap();
+        let decoder = AsyncStreamDecoder::new(r.clone(), 100).unwrap();
+
+        for &len in [0, 1, 399, 400, 401, 1234].iter() {
+            let mut input = vec![0u8; len];
+            fill_random(&mut input);
+
+            let mut outputs = vec![Vec::new(); 6];
+            assert_eq!(
+                len as u64,
+                block_on(encoder.encode(&input[..], &mut outputs)).unwrap()
+            );
+
+            let mut expect = vec![Vec::new(); 6];
+            StreamEncoder::new(&r, 100)
+                .unwrap()
+                .encode(&input[..], &mut expect)
+                .unwrap();
+            assert_eq!(expect, outputs);
+
+            let mut inputs: Vec<_> = outputs.iter().map(|o| Some(&o[..])).collect();
+            inputs[0] = None;
+            inputs[5] = Some(&outputs[5][..outputs[5].len() / 2]);
+
+            let mut decoded = Vec::new();
+            assert_eq!(
+                len as u64,
+                block_on(decoder.decode(&mut inputs, &mut decoded)).unwrap()
+            );
+            assert_eq!(input, decoded);
+
+            let mut inputs: Vec<_> = outputs.iter().map(|o| Some(&o[..])).collect();
+            let mut expect = Vec::new();
+            StreamDecoder::new(&r, 100)
+                .unwrap()
+                .decode(&mut inputs, &mut expect)
+                .unwrap();
+            assert_eq!(expect, decoded);
+        }
+    }
+
+    #[test]
+    fn test_async_stream_error_handling() {
+        let r = Arc::new(ReedSolomon::new(4, 2).unwrap());
+        assert_eq!(
+            Error::EmptyShard,
+            AsyncStreamEncoder::new(r.clone(), 0).unwrap_err()
+        );
+        assert_eq!(
+            Error::EmptyShard,
+            AsyncStreamDecoder::new(r.clone(), 0).unwrap_err()
+        );
+
+        let encoder = AsyncStreamEncoder::new(r.clone(), 100).unwrap();
+        let mut outputs = vec![Vec::new(); 5];
+        let e = block_on(encoder.encode(&[0u8; 10][..], &mut outputs)).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+
+        let decoder = AsyncStreamDecoder::new(r, 100).unwrap();
+        let mut inputs: Vec<Option<&[u8]>> = vec![None; 7];
+        let e = block_on(decoder.decode(&mut inputs, &mut Vec::new())).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+
+        let mut outputs = vec![Vec::new(); 6];
+        block_on(encoder.encode(&[0u8; 1000][..], &mut outputs)).unwrap();
+        let mut inputs: Vec<_> = outputs.iter().map(|o| Some(&o[..])).collect();
+        inputs[0] = None;
+        inputs[1] = None;
+        inputs[2] = None;
+        let e = block_on(decoder.decode(&mut inputs, &mut Vec::new())).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+    }
+}
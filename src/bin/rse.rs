@@ -0,0 +1,733 @@
+//! `rse`, a command-line tool for encoding, verifying and repairing files.
+//!
+//! `rse encode FILE` splits a file into stripes of `data_shards * shard_size`
+//! bytes, encodes every stripe and writes one shard file per shard index, plus
+//! a manifest `FILE.rse`. Every shard file is a sequence of shard frames (see
+//! `reed_solomon_erasure::shard_format`), one per stripe, each protected by a
+//! CRC-32C. The manifest records the codec parameters and a CRC-32C of every
+//! shard file as a whole.
+//!
+//! The other subcommands take the manifest as argument, and expect the shard
+//! files next to it.
+//!
+//! This binary is only built with the `cli` feature enabled.
+
+use std::error;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use reed_solomon_erasure::shard_format::{
+    decode_stripe, encode_stripe, matrix_fingerprint, parse_frame, peek_frame_len, Checksum,
+    Crc32c, HEADER_LEN,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Parser)]
+#[command(name = "rse", version, about = "Reed-Solomon erasure coding of files")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Encode a file into data and parity shard files plus a manifest
+    Encode {
+        /// File to encode
+        file: PathBuf,
+        /// Number of data shards
+        #[arg(short = 'k', long, default_value_t = 10)]
+        data_shards: usize,
+        /// Number of parity shards
+        #[arg(short = 'm', long, default_value_t = 4)]
+        parity_shards: usize,
+        /// Size in bytes of a shard of a stripe
+        #[arg(short, long, default_value_t = 1 << 20)]
+        shard_size: usize,
+        /// Directory to write the shard files and the manifest into,
+        /// defaults to the directory of the file
+        #[arg(short, long)]
+        out_dir: Option<PathBuf>,
+    },
+    /// Decode shard files back into the original file
+    Decode {
+        /// Manifest written by `encode`
+        manifest: PathBuf,
+        /// Path of the decoded file, defaults to the original file name
+        /// next to the manifest
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Overwrite the output file if it exists
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Check the shard checksums and the parity consistency of every stripe
+    Verify {
+        /// Manifest written by `encode`
+        manifest: PathBuf,
+    },
+    /// Regenerate missing or corrupt shard files
+    Repair {
+        /// Manifest written by `encode`
+        manifest: PathBuf,
+    },
+    /// Show the parameters of a shard set and the state of its shard files
+    Info {
+        /// Manifest written by `encode`
+        manifest: PathBuf,
+    },
+}
+
+/// Contents of a manifest file.
+///
+/// The manifest is a text file with one `key value` pair per line:
+///
+/// ```text
+/// rse-manifest 1
+/// file <original file name>
+/// length <length of the original file>
+/// data-shards <k>
+/// parity-shards <m>
+/// shard-size <shard size>
+/// stripes <stripe count>
+/// shard <index> <CRC-32C of the shard file in hex> <shard file name>
+/// ```
+#[derive(PartialEq, Debug)]
+struct Manifest {
+    file_name: String,
+    length: u64,
+    data_shards: usize,
+    parity_shards: usize,
+    shard_size: usize,
+    stripes: u64,
+    shards: Vec<(u32, String)>,
+}
+
+impl Manifest {
+    fn parse(text: &str) -> Result<Manifest> {
+        let mut lines = text.lines();
+        match lines.next() {
+            Some(line) if line == format!("rse-manifest {}", MANIFEST_VERSION) => {}
+            _ => return Err("not an rse manifest, or an unsupported version".into()),
+        }
+
+        let mut file_name = None;
+        let mut length = None;
+        let mut data_shards = None;
+        let mut parity_shards = None;
+        let mut shard_size = None;
+        let mut stripes = None;
+        let mut shards = Vec::new();
+
+        for line in lines.filter(|line| !line.is_empty()) {
+            let (key, value) = line.split_once(' ').ok_or("malformed manifest line")?;
+            match key {
+                "file" => file_name = Some(value.to_string()),
+                "length" => length = Some(value.parse()?),
+                "data-shards" => data_shards = Some(value.parse()?),
+                "parity-shards" => parity_shards = Some(value.parse()?),
+                "shard-size" => shard_size = Some(value.parse()?),
+                "stripes" => stripes = Some(value.parse()?),
+                "shard" => {
+                    let mut fields = value.splitn(3, ' ');
+                    let index: usize = fields.next().ok_or("malformed shard line")?.parse()?;
+                    let crc =
+                        u32::from_str_radix(fields.next().ok_or("malformed shard line")?, 16)?;
+                    let name = fields.next().ok_or("malformed shard line")?;
+                    if index != shards.len() {
+                        return Err("shards in the manifest are out of order".into());
+                    }
+                    shards.push((crc, name.to_string()));
+                }
+                _ => return Err(format!("unknown manifest key `{}`", key).into()),
+            }
+        }
+
+        let manifest = Manifest {
+            file_name: file_name.ok_or("manifest is missing `file`")?,
+            length: length.ok_or("manifest is missing `length`")?,
+            data_shards: data_shards.ok_or("manifest is missing `data-shards`")?,
+            parity_shards: parity_shards.ok_or("manifest is missing `parity-shards`")?,
+            shard_size: shard_size.ok_or("manifest is missing `shard-size`")?,
+            stripes: stripes.ok_or("manifest is missing `stripes`")?,
+            shards,
+        };
+        if manifest.shards.len() != manifest.data_shards + manifest.parity_shards {
+            return Err("number of shards in the manifest does not match the codec".into());
+        }
+
+        Ok(manifest)
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        writeln!(text, "rse-manifest {}", MANIFEST_VERSION).unwrap();
+        writeln!(text, "file {}", self.file_name).unwrap();
+        writeln!(text, "length {}", self.length).unwrap();
+        writeln!(text, "data-shards {}", self.data_shards).unwrap();
+        writeln!(text, "parity-shards {}", self.parity_shards).unwrap();
+        writeln!(text, "shard-size {}", self.shard_size).unwrap();
+        writeln!(text, "stripes {}", self.stripes).unwrap();
+        for (index, (crc, name)) in self.shards.iter().enumerate() {
+            writeln!(text, "shard {} {:08x} {}", index, crc, name).unwrap();
+        }
+        text
+    }
+
+    fn load(path: &Path) -> Result<Manifest> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read manifest {}: {}", path.display(), e))?;
+        Manifest::parse(&text)
+    }
+
+    fn codec(&self) -> Result<ReedSolomon> {
+        Ok(ReedSolomon::new(self.data_shards, self.parity_shards)?)
+    }
+
+    fn shard_path(&self, manifest_path: &Path, index: usize) -> PathBuf {
+        manifest_dir(manifest_path).join(&self.shards[index].1)
+    }
+}
+
+fn manifest_dir(manifest_path: &Path) -> &Path {
+    match manifest_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+fn crc32c_of_file(path: &Path) -> io::Result<u32> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut checksum = Crc32c::new();
+    let mut buf = vec![0u8; 1 << 16];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        checksum.update(&buf[..n]);
+    }
+
+    let mut out = [0u8; 4];
+    checksum.finish(&mut out);
+    Ok(u32::from_le_bytes(out))
+}
+
+/// State of a shard file compared to the manifest.
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum ShardState {
+    Ok,
+    Missing,
+    Corrupt,
+}
+
+fn shard_states(manifest: &Manifest, manifest_path: &Path) -> Vec<ShardState> {
+    (0..manifest.shards.len())
+        .map(
+            |index| match crc32c_of_file(&manifest.shard_path(manifest_path, index)) {
+                Ok(crc) if crc == manifest.shards[index].0 => ShardState::Ok,
+                Ok(_) => ShardState::Corrupt,
+                Err(_) => ShardState::Missing,
+            },
+        )
+        .collect()
+}
+
+/// Reads the frames of a shard file one after another.
+///
+/// Every frame but the last one of a shard file is `max_len` bytes long, so
+/// a frame with a corrupt header is skipped without losing the following
+/// ones. A shard file which cannot be opened or read produces no further
+/// frames.
+struct FrameReader {
+    reader: Option<BufReader<File>>,
+    max_len: usize,
+}
+
+impl FrameReader {
+    fn open(path: &Path, shard_size: usize) -> FrameReader {
+        FrameReader {
+            reader: File::open(path).ok().map(BufReader::new),
+            max_len: HEADER_LEN + Crc32c::LEN + shard_size,
+        }
+    }
+
+    // Returns the frame of the next stripe, or `None` if it is unreadable.
+    fn next_frame(&mut self) -> Option<Vec<u8>> {
+        let reader = self.reader.as_mut()?;
+
+        let mut frame = Vec::with_capacity(self.max_len);
+        match reader.take(self.max_len as u64).read_to_end(&mut frame) {
+            Ok(0) | Err(_) => {
+                self.reader = None;
+                return None;
+            }
+            Ok(_) => {}
+        }
+
+        let len = peek_frame_len(&frame)
+            .ok()
+            .filter(|&len| len <= frame.len())?;
+        frame.truncate(len);
+        Some(frame)
+    }
+}
+
+/// Reads the frames of every stripe from all shard files.
+struct StripeReader {
+    readers: Vec<FrameReader>,
+    fingerprint: u64,
+}
+
+impl StripeReader {
+    fn open(manifest: &Manifest, manifest_path: &Path, codec: &ReedSolomon) -> StripeReader {
+        StripeReader {
+            readers: (0..manifest.shards.len())
+                .map(|index| {
+                    FrameReader::open(
+                        &manifest.shard_path(manifest_path, index),
+                        manifest.shard_size,
+                    )
+                })
+                .collect(),
+            fingerprint: matrix_fingerprint(codec),
+        }
+    }
+
+    // Returns the valid payload of every shard of the next stripe.
+    fn next_stripe(
+        &mut self,
+        codec: &ReedSolomon,
+        stripe_id: u64,
+    ) -> (Vec<Vec<u8>>, Vec<Option<Vec<u8>>>) {
+        let frames: Vec<Vec<u8>> = self
+            .readers
+            .iter_mut()
+            .filter_map(|reader| reader.next_frame())
+            .collect();
+
+        let mut payloads = vec![None; codec.total_shard_count()];
+        for frame in frames.iter() {
+            if let Ok((header, payload)) = parse_frame::<Crc32c>(frame) {
+                if header.stripe_id == stripe_id
+                    && header.matches_fingerprint(codec, self.fingerprint)
+                {
+                    payloads[header.index as usize] = Some(payload.to_vec());
+                }
+            }
+        }
+
+        (frames, payloads)
+    }
+}
+
+fn encode(
+    file: &Path,
+    data_shards: usize,
+    parity_shards: usize,
+    shard_size: usize,
+    out_dir: Option<&Path>,
+) -> Result<()> {
+    let codec = ReedSolomon::new(data_shards, parity_shards)?;
+    if shard_size == 0 {
+        return Err("shard size must be greater than zero".into());
+    }
+
+    let file_name = file
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or("cannot determine the name of the file")?
+        .to_string();
+    let out_dir = match out_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => manifest_dir(file).to_path_buf(),
+    };
+
+    let shard_names: Vec<String> = (0..codec.total_shard_count())
+        .map(|index| format!("{}.{:03}", file_name, index))
+        .collect();
+    let mut writers = shard_names
+        .iter()
+        .map(|name| Ok(BufWriter::new(File::create(out_dir.join(name))?)))
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut checksums: Vec<Crc32c> = shard_names.iter().map(|_| Crc32c::new()).collect();
+
+    let mut input = BufReader::new(File::open(file)?);
+    let stripe_len = (data_shards * shard_size) as u64;
+    let mut length = 0;
+    let mut stripes = 0;
+    loop {
+        let mut chunk = Vec::with_capacity(stripe_len as usize);
+        (&mut input).take(stripe_len).read_to_end(&mut chunk)?;
+        if chunk.is_empty() {
+            break;
+        }
+
+        let frames = encode_stripe::<Crc32c>(&codec, stripes, 0, &chunk)?;
+        for ((frame, writer), checksum) in frames
+            .iter()
+            .zip(writers.iter_mut())
+            .zip(checksums.iter_mut())
+        {
+            writer.write_all(frame)?;
+            checksum.update(frame);
+        }
+
+        length += chunk.len() as u64;
+        stripes += 1;
+    }
+
+    for writer in writers.iter_mut() {
+        writer.flush()?;
+    }
+
+    let manifest = Manifest {
+        file_name: file_name.clone(),
+        length,
+        data_shards,
+        parity_shards,
+        shard_size,
+        stripes,
+        shards: checksums
+            .into_iter()
+            .zip(shard_names)
+            .map(|(checksum, name)| {
+                let mut out = [0u8; 4];
+                checksum.finish(&mut out);
+                (u32::from_le_bytes(out), name)
+            })
+            .collect(),
+    };
+    let manifest_path = out_dir.join(format!("{}.rse", file_name));
+    fs::write(&manifest_path, manifest.to_text())?;
+
+    println!(
+        "encoded {} bytes into {} stripes of {}+{} shards, manifest {}",
+        length,
+        stripes,
+        data_shards,
+        parity_shards,
+        manifest_path.display()
+    );
+    Ok(())
+}
+
+fn decode(manifest_path: &Path, output: Option<&Path>, force: bool) -> Result<()> {
+    let manifest = Manifest::load(manifest_path)?;
+    let codec = manifest.codec()?;
+
+    let output = match output {
+        Some(output) => output.to_path_buf(),
+        None => manifest_dir(manifest_path).join(&manifest.file_name),
+    };
+    if output.exists() && !force {
+        return Err(format!(
+            "{} already exists, use --force to overwrite it",
+            output.display()
+        )
+        .into());
+    }
+
+    let mut reader = StripeReader::open(&manifest, manifest_path, &codec);
+    let mut writer = BufWriter::new(File::create(&output)?);
+    let mut length = 0;
+    for stripe_id in 0..manifest.stripes {
+        let (frames, _) = reader.next_stripe(&codec, stripe_id);
+        let data = decode_stripe::<Crc32c, _>(&codec, stripe_id, &frames)
+            .map_err(|e| format!("cannot decode stripe {}: {}", stripe_id, e))?;
+        writer.write_all(&data)?;
+        length += data.len() as u64;
+    }
+    writer.flush()?;
+
+    if length != manifest.length {
+        return Err(format!(
+            "decoded {} bytes, but the manifest records {}",
+            length, manifest.length
+        )
+        .into());
+    }
+
+    println!("decoded {} bytes into {}", length, output.display());
+    Ok(())
+}
+
+// Returns whether the shard set is intact.
+fn verify(manifest_path: &Path) -> Result<bool> {
+    let manifest = Manifest::load(manifest_path)?;
+    let codec = manifest.codec()?;
+
+    let mut intact = true;
+    for (index, state) in shard_states(&manifest, manifest_path).iter().enumerate() {
+        match state {
+            ShardState::Ok => {}
+            ShardState::Missing => {
+                println!("shard {}: missing", index);
+                intact = false;
+            }
+            ShardState::Corrupt => {
+                println!("shard {}: checksum mismatch", index);
+                intact = false;
+            }
+        }
+    }
+
+    let mut reader = StripeReader::open(&manifest, manifest_path, &codec);
+    for stripe_id in 0..manifest.stripes {
+        let (_, payloads) = reader.next_stripe(&codec, stripe_id);
+
+        let present: Vec<&Vec<u8>> = payloads.iter().flatten().collect();
+        if present.len() < payloads.len() {
+            let missing = payloads.len() - present.len();
+            println!(
+                "stripe {}: {} invalid or missing shards",
+                stripe_id, missing
+            );
+            intact = false;
+        } else if !codec.verify(&present).unwrap_or(false) {
+            println!("stripe {}: parity mismatch", stripe_id);
+            intact = false;
+        }
+    }
+
+    if intact {
+        println!("all {} shards are intact", manifest.shards.len());
+    }
+    Ok(intact)
+}
+
+fn repair(manifest_path: &Path) -> Result<()> {
+    let manifest = Manifest::load(manifest_path)?;
+    let codec = manifest.codec()?;
+
+    let states = shard_states(&manifest, manifest_path);
+    let damaged: Vec<usize> = (0..states.len())
+        .filter(|&index| states[index] != ShardState::Ok)
+        .collect();
+    if damaged.is_empty() {
+        println!("all {} shards are intact", manifest.shards.len());
+        return Ok(());
+    }
+
+    // regenerated shard files are written next to the damaged ones and
+    // only replace them once all stripes are repaired
+    let temp_path = |index: usize| {
+        let mut path = manifest.shard_path(manifest_path, index).into_os_string();
+        path.push(".repair");
+        PathBuf::from(path)
+    };
+    let mut writers = damaged
+        .iter()
+        .map(|&index| Ok(BufWriter::new(File::create(temp_path(index))?)))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut reader = StripeReader::open(&manifest, manifest_path, &codec);
+    for stripe_id in 0..manifest.stripes {
+        let (frames, _) = reader.next_stripe(&codec, stripe_id);
+        let data = decode_stripe::<Crc32c, _>(&codec, stripe_id, &frames)
+            .map_err(|e| format!("cannot repair stripe {}: {}", stripe_id, e))?;
+
+        // encoding is deterministic, so re-encoding yields the original frames
+        let frames = encode_stripe::<Crc32c>(&codec, stripe_id, 0, &data)?;
+        for (&index, writer) in damaged.iter().zip(writers.iter_mut()) {
+            writer.write_all(&frames[index])?;
+        }
+    }
+
+    for writer in writers.iter_mut() {
+        writer.flush()?;
+    }
+    drop(writers);
+
+    for &index in damaged.iter() {
+        let path = temp_path(index);
+        if crc32c_of_file(&path)? != manifest.shards[index].0 {
+            return Err(format!(
+                "regenerated shard {} does not match the manifest, left at {}",
+                index,
+                path.display()
+            )
+            .into());
+        }
+        fs::rename(&path, manifest.shard_path(manifest_path, index))?;
+        println!("shard {}: repaired", index);
+    }
+
+    Ok(())
+}
+
+fn info(manifest_path: &Path) -> Result<()> {
+    let manifest = Manifest::load(manifest_path)?;
+
+    println!("file:          {}", manifest.file_name);
+    println!("length:        {}", manifest.length);
+    println!("data shards:   {}", manifest.data_shards);
+    println!("parity shards: {}", manifest.parity_shards);
+    println!("shard size:    {}", manifest.shard_size);
+    println!("stripes:       {}", manifest.stripes);
+
+    let states = shard_states(&manifest, manifest_path);
+    for (index, (crc, name)) in manifest.shards.iter().enumerate() {
+        let state = match states[index] {
+            ShardState::Ok => "ok",
+            ShardState::Missing => "missing",
+            ShardState::Corrupt => "corrupt",
+        };
+        println!("shard {:3}: {:08x} {:8} {}", index, crc, state, name);
+    }
+
+    let damaged = states.iter().filter(|&&s| s != ShardState::Ok).count();
+    if damaged > manifest.parity_shards {
+        println!(
+            "{} shards are damaged, the file cannot be recovered",
+            damaged
+        );
+    }
+
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<bool> {
+    match cli.command {
+        Command::Encode {
+            file,
+            data_shards,
+            parity_shards,
+            shard_size,
+            out_dir,
+        } => encode(
+            &file,
+            data_shards,
+            parity_shards,
+            shard_size,
+            out_dir.as_deref(),
+        )
+        .map(|_| true),
+        Command::Decode {
+            manifest,
+            output,
+            force,
+        } => decode(&manifest, output.as_deref(), force).map(|_| true),
+        Command::Verify { manifest } => verify(&manifest),
+        Command::Repair { manifest } => repair(&manifest).map(|_| true),
+        Command::Info { manifest } => info(&manifest).map(|_| true),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("rse: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Scratch directory removed on drop.
+    struct ScratchDir(PathBuf);
+
+    impl ScratchDir {
+        fn new() -> ScratchDir {
+            let dir =
+                std::env::temp_dir().join(format!("rse-cli-test-{:016x}", rand::random::<u64>()));
+            fs::create_dir(&dir).unwrap();
+            ScratchDir(dir)
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let manifest = Manifest {
+            file_name: "disk image.img".to_string(),
+            length: 12345,
+            data_shards: 2,
+            parity_shards: 1,
+            shard_size: 4096,
+            stripes: 2,
+            shards: vec![
+                (0xDEAD_BEEF, "disk image.img.000".to_string()),
+                (0, "disk image.img.001".to_string()),
+                (1, "disk image.img.002".to_string()),
+            ],
+        };
+        assert_eq!(manifest, Manifest::parse(&manifest.to_text()).unwrap());
+
+        assert!(Manifest::parse("").is_err());
+        assert!(Manifest::parse("rse-manifest 2\n").is_err());
+        let truncated = manifest.to_text().replace("stripes 2\n", "");
+        assert!(Manifest::parse(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_encode_verify_repair_decode() {
+        let dir = ScratchDir::new();
+        let file = dir.0.join("input");
+        let mut data = vec![0u8; 100_000];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = (i * 7 + i / 251) as u8;
+        }
+        fs::write(&file, &data).unwrap();
+
+        encode(&file, 4, 2, 3000, None).unwrap();
+        let manifest_path = dir.0.join("input.rse");
+        let manifest = Manifest::load(&manifest_path).unwrap();
+        assert_eq!(9, manifest.stripes);
+        assert!(verify(&manifest_path).unwrap());
+
+        // lose a data shard and corrupt a parity shard
+        fs::remove_file(dir.0.join("input.001")).unwrap();
+        let mut parity = fs::read(dir.0.join("input.005")).unwrap();
+        parity[20_000] ^= 1;
+        fs::write(dir.0.join("input.005"), &parity).unwrap();
+        assert!(!verify(&manifest_path).unwrap());
+
+        let output = dir.0.join("output");
+        decode(&manifest_path, Some(&output), false).unwrap();
+        assert_eq!(data, fs::read(&output).unwrap());
+        assert!(decode(&manifest_path, Some(&output), false).is_err());
+
+        repair(&manifest_path).unwrap();
+        assert!(verify(&manifest_path).unwrap());
+
+        // corrupt frame headers only lose their own stripe
+        for &(index, stripe) in [(0, 2), (3, 4), (5, 6)].iter() {
+            let path = dir.0.join(format!("input.{:03}", index));
+            let mut shard = fs::read(&path).unwrap();
+            shard[stripe * (HEADER_LEN + Crc32c::LEN + 3000)] ^= 1;
+            fs::write(&path, &shard).unwrap();
+        }
+        assert!(!verify(&manifest_path).unwrap());
+        decode(&manifest_path, Some(&output), true).unwrap();
+        assert_eq!(data, fs::read(&output).unwrap());
+        repair(&manifest_path).unwrap();
+        assert!(verify(&manifest_path).unwrap());
+
+        // too many damaged shards
+        for index in 0..3 {
+            fs::remove_file(dir.0.join(format!("input.{:03}", index))).unwrap();
+        }
+        assert!(repair(&manifest_path).is_err());
+        assert!(decode(&manifest_path, Some(&output), true).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 extern crate alloc;
 
+use ::core::mem::MaybeUninit;
 use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
@@ -23,6 +24,13 @@ use super::ReconstructShard;
 
 const DATA_DECODE_MATRIX_CACHE_CAPACITY: usize = 254;
 
+/// The bytes of all shards which are coded at a time by default, so that a
+/// block of every input and output stays in the L2 cache.
+const CACHE_BLOCK_BYTES: usize = 512 * 1024;
+
+/// The smallest block of each shard which is coded at a time by default.
+const MIN_BLOCK_BYTES: usize = 4096;
+
 // /// Parameters for parallelism.
 // #[derive(PartialEq, Debug, Clone, Copy)]
 // pub struct ParallelParam {
@@ -46,6 +54,115 @@ const DATA_DECODE_MATRIX_CACHE_CAPACITY: usize = 254;
 //     }
 // }
 
+// Turns uninitialized outputs into slices of elements, zeroing them first
+// unless the coding of `F` writes every element before reading it.
+fn assume_init_outputs<F, U>(outputs: &mut [U]) -> Vec<&mut [F::Elem]>
+where
+    F: Field,
+    U: AsMut<[MaybeUninit<F::Elem>]>,
+{
+    outputs
+        .iter_mut()
+        .map(|output| {
+            let output = output.as_mut();
+            if !F::WRITES_OUTPUTS_FIRST {
+                output.fill(MaybeUninit::new(F::zero()));
+            }
+            // `MaybeUninit<T>` has the layout of `T`, and the elements are
+            // either initialized or written before they are read.
+            unsafe { &mut *(output as *mut [MaybeUninit<F::Elem>] as *mut [F::Elem]) }
+        })
+        .collect()
+}
+
+/// Rows of a matrix, with the tables of their elements written by
+/// `Field::prepare`.
+#[derive(Debug)]
+struct PreparedRows<F: Field> {
+    row_len: usize,
+    elems: Vec<F::Elem>,
+    tables: Vec<F::Elem>,
+    // whether each row is all ones, such as the P parity of RAID-6
+    sums: Vec<bool>,
+}
+
+/// Rows selected from `PreparedRows`, split into the rows of ones, which are
+/// sums of the inputs and need no multiplications, and the others.
+struct SelectedRows<'a, F: Field> {
+    sums: SmallVec<[bool; 32]>,
+    products: SmallVec<[&'a [F::Elem]; 32]>,
+    product_tables: SmallVec<[&'a [F::Elem]; 32]>,
+}
+
+impl<F: Field> PreparedRows<F> {
+    fn new<'a, I>(row_len: usize, rows: I) -> PreparedRows<F>
+    where
+        I: IntoIterator<Item = &'a [F::Elem]>,
+        F::Elem: 'a,
+    {
+        let mut elems = Vec::new();
+        for row in rows {
+            elems.extend_from_slice(row);
+        }
+        let mut tables = vec![F::zero(); elems.len() * F::TABLE_LEN];
+        // `chunks_exact_mut` panics on chunks of 0 elements
+        if F::TABLE_LEN > 0 {
+            for (&elem, table) in elems.iter().zip(tables.chunks_exact_mut(F::TABLE_LEN)) {
+                F::prepare(elem, table);
+            }
+        }
+        let sums = elems
+            .chunks(row_len)
+            .map(|row| row.iter().all(|&c| c == F::one()))
+            .collect();
+        PreparedRows {
+            row_len,
+            elems,
+            tables,
+            sums,
+        }
+    }
+
+    fn row(&self, i: usize) -> &[F::Elem] {
+        &self.elems[i * self.row_len..(i + 1) * self.row_len]
+    }
+
+    fn tables(&self, i: usize) -> &[F::Elem] {
+        let len = self.row_len * F::TABLE_LEN;
+        &self.tables[i * len..(i + 1) * len]
+    }
+
+    fn row_count(&self) -> usize {
+        self.elems.len() / self.row_len
+    }
+
+    // The rows at `indices`, with the tables of the rows which are not sums
+    // as taken by `Field::dot_prod_prepared`.
+    fn select<I: IntoIterator<Item = usize>>(&self, indices: I) -> SelectedRows<'_, F> {
+        let mut selected = SelectedRows {
+            sums: SmallVec::new(),
+            products: SmallVec::new(),
+            product_tables: SmallVec::new(),
+        };
+        for i in indices {
+            selected.sums.push(self.sums[i]);
+            if !self.sums[i] {
+                selected.products.push(self.row(i));
+                selected.product_tables.push(self.tables(i));
+            }
+        }
+        selected
+    }
+}
+
+/// A data decode matrix, with its rows which re-create missing data shards
+/// prepared.
+#[derive(Debug)]
+struct DecodePlan<F: Field> {
+    matrix: Matrix<F>,
+    missing_data_rows: PreparedRows<F>,
+}
+
 /// Bookkeeper for shard by shard encoding.
 ///
 /// This is useful for avoiding incorrect use of
@@ -315,6 +432,13 @@ impl<'a, F: 'a + Field> ShardByShard<'a, F> {
 /// completely. If you are using the methods correctly, then you do not need
 /// to clear the parity shards beforehand.
 ///
+/// ## For `partial_parity`, `merge_parity`
+///
+/// `partial_parity` always overwrites the parity shards with the contribution
+/// of a single data shard, and `merge_parity` adds contributions together.
+/// This allows the data shards to be encoded on different nodes, in any order,
+/// with the contributions merged afterwards.
+///
 /// # Variants of verifying methods
 ///
 /// `verify` allocate sa buffer on the heap of the same size
@@ -346,13 +470,21 @@ pub struct ReedSolomon<F: Field> {
     parity_shard_count: usize,
     total_shard_count: usize,
     matrix: Matrix<F>,
-    data_decode_matrix_cache: Mutex<LruCache<Vec<usize>, Arc<Matrix<F>>>>,
+    parity_rows: PreparedRows<F>,
+    block_len: usize,
+    data_decode_matrix_cache: Mutex<LruCache<Vec<usize>, Arc<DecodePlan<F>>>>,
 }
 
 impl<F: Field> Clone for ReedSolomon<F> {
     fn clone(&self) -> ReedSolomon<F> {
-        ReedSolomon::new(self.data_shard_count, self.parity_shard_count)
-            .expect("basic checks already passed as precondition of existence of self")
+        let mut codec = ReedSolomon::with_matrix(
+            self.data_shard_count,
+            self.parity_shard_count,
+            self.matrix.clone(),
+        )
+        .expect("basic checks already passed as precondition of existence of self");
+        codec.block_len = self.block_len;
+        codec
     }
 }
 
@@ -360,6 +492,7 @@ impl<F: Field> PartialEq for ReedSolomon<F> {
     fn eq(&self, rhs: &ReedSolomon<F>) -> bool {
         self.data_shard_count == rhs.data_shard_count
             && self.parity_shard_count == rhs.parity_shard_count
+            && self.matrix == rhs.matrix
     }
 }
 
@@ -392,6 +525,17 @@ impl<F: Field> ReedSolomon<F> {
     //   - check consistency of length of individual data slices
     //   - check consistency of length of individual parity slices
     //   - check length of first parity slice matches length of first data slice
+    // `partial_parity`:=
+    //   - check index `i_data` within range [0, data shard count)
+    //   - check length of `parity` matches parity shard count exactly
+    //   - check consistency of length of individual parity slices
+    //   - check length of `single_data` matches length of first parity slice
+    // `merge_parity`:=
+    //   - check length of `partial` matches parity shard count exactly
+    //   - check length of `parity` matches parity shard count exactly
+    //   - check consistency of length of individual partial slices
+    //   - check consistency of length of individual parity slices
+    //   - check length of first parity slice matches length of first partial slice
     //
     // Verify methods
     //
@@ -416,18 +560,21 @@ impl<F: Field> ReedSolomon<F> {
     //   - check length of `slices` matches total shard count exactly
     //   - check consistency of length of individual slices
     //   - check length of `slice_present` matches length of `slices`
+    //
+    // Repair coefficient methods
+    //
+    // `repair_coefficients`:=
+    //   - check index `target` within range [0, total shard count)
+    //   - check indices in `available` within range [0, total shard count)
+    //   - check number of distinct indices in `available` is at least data shard count
 
     fn get_parity_rows(&self) -> SmallVec<[&[F::Elem]; 32]> {
-        let mut parity_rows = SmallVec::with_capacity(self.parity_shard_count);
-        let matrix = &self.matrix;
-        for i in self.data_shard_count..self.total_shard_count {
-            parity_rows.push(matrix.get_row(i));
-        }
-
-        parity_rows
+        (0..self.parity_shard_count)
+            .map(|i| self.parity_rows.row(i))
+            .collect()
     }
 
-    fn build_matrix(data_shards: usize, total_shards: usize) -> Matrix<F> {
+    pub(crate) fn build_matrix(data_shards: usize, total_shards: usize) -> Matrix<F> {
         let vandermonde = Matrix::vandermonde(total_shards, data_shards);
 
         let top = vandermonde.sub_matrix(0, 0, data_shards, data_shards);
@@ -443,6 +590,17 @@ impl<F: Field> ReedSolomon<F> {
     ///
     /// Returns `Error::TooManyShards` if `data_shards + parity_shards > F::ORDER`.
     pub fn new(data_shards: usize, parity_shards: usize) -> Result<ReedSolomon<F>, Error> {
+        Self::check_shard_counts(data_shards, parity_shards)?;
+
+        let matrix = Self::build_matrix(data_shards, data_shards + parity_shards);
+
+        Self::with_matrix(data_shards, parity_shards, matrix)
+    }
+
+    pub(crate) fn check_shard_counts(
+        data_shards: usize,
+        parity_shards: usize,
+    ) -> Result<(), Error> {
         if data_shards == 0 {
             return Err(Error::TooFewDataShards);
         }
@@ -452,20 +610,70 @@ impl<F: Field> ReedSolomon<F> {
         if data_shards + parity_shards > F::ORDER {
             return Err(Error::TooManyShards);
         }
+        Ok(())
+    }
+
+    /// Creates a codec with a given encoding matrix, which has `data_shards`
+    /// columns, one row per shard, and the identity matrix as its top rows.
+    /// Every selection of `data_shards` rows must be invertible.
+    ///
+    /// Returns the same errors as `new` for the shard counts.
+    pub(crate) fn with_matrix(
+        data_shards: usize,
+        parity_shards: usize,
+        matrix: Matrix<F>,
+    ) -> Result<ReedSolomon<F>, Error> {
+        Self::check_shard_counts(data_shards, parity_shards)?;
 
         let total_shards = data_shards + parity_shards;
+        debug_assert_eq!(total_shards, matrix.row_count());
+        debug_assert_eq!(data_shards, matrix.col_count());
 
-        let matrix = Self::build_matrix(data_shards, total_shards);
+        let parity_rows = PreparedRows::new(
+            data_shards,
+            (data_shards..total_shards).map(|i| matrix.get_row(i)),
+        );
 
         Ok(ReedSolomon {
             data_shard_count: data_shards,
             parity_shard_count: parity_shards,
             total_shard_count: total_shards,
             matrix,
+            parity_rows,
+            block_len: Self::default_block_len(total_shards),
             data_decode_matrix_cache: Mutex::new(LruCache::new(DATA_DECODE_MATRIX_CACHE_CAPACITY)),
         })
     }
 
+    fn default_block_len(total_shards: usize) -> usize {
+        let elem_size = ::core::mem::size_of::<F::Elem>().max(1);
+        let block_bytes = (CACHE_BLOCK_BYTES / total_shards).max(MIN_BLOCK_BYTES);
+        // a multiple of the widest vectors of the SIMD routines
+        block_bytes / 64 * 64 / elem_size
+    }
+
+    /// Returns the number of elements of each shard which are coded at a time.
+    ///
+    /// See `set_block_len`.
+    pub fn block_len(&self) -> usize {
+        self.block_len
+    }
+
+    /// Sets the number of elements of each shard which are coded at a time
+    /// by `encode`, `verify` and `reconstruct`.
+    ///
+    /// Shards are split into blocks of `block_len` elements, and a block of
+    /// every input and every output is coded before the next, so that they
+    /// stay in the cache. By default, the blocks of all shards take about
+    /// 512 KiB, but no less than 4 KiB per shard.
+    ///
+    /// # Panics
+    /// Panics if `block_len` is zero.
+    pub fn set_block_len(&mut self, block_len: usize) {
+        assert!(block_len > 0, "block length must be positive");
+        self.block_len = block_len;
+    }
+
     pub fn data_shard_count(&self) -> usize {
         self.data_shard_count
     }
@@ -478,14 +686,57 @@ impl<F: Field> ReedSolomon<F> {
         self.total_shard_count
     }
 
+    pub(crate) fn matrix(&self) -> &Matrix<F> {
+        &self.matrix
+    }
+
     fn code_some_slices<T: AsRef<[F::Elem]>, U: AsMut<[F::Elem]>>(
         &self,
-        matrix_rows: &[&[F::Elem]],
+        rows: &SelectedRows<F>,
         inputs: &[T],
         outputs: &mut [U],
     ) {
-        for i_input in 0..self.data_shard_count {
-            self.code_single_slice(matrix_rows, i_input, inputs[i_input].as_ref(), outputs);
+        let inputs: SmallVec<[&[F::Elem]; 32]> = inputs
+            .iter()
+            .take(self.data_shard_count)
+            .map(|input| input.as_ref())
+            .collect();
+
+        let mut sum_outputs: SmallVec<[&mut [F::Elem]; 32]> = SmallVec::new();
+        let mut product_outputs: SmallVec<[&mut [F::Elem]; 32]> = SmallVec::new();
+        for (&sum, output) in rows.sums.iter().zip(outputs.iter_mut()) {
+            if sum {
+                sum_outputs.push(output.as_mut());
+            } else {
+                product_outputs.push(output.as_mut());
+            }
+        }
+
+        let len = inputs.first().map_or(0, |input| input.len());
+        let mut start = 0;
+        while start < len {
+            let end = len.min(start + self.block_len);
+            let block_inputs: SmallVec<[&[F::Elem]; 32]> =
+                inputs.iter().map(|input| &input[start..end]).collect();
+
+            for output in sum_outputs.iter_mut() {
+                F::sum_slices(&block_inputs, &mut output[start..end]);
+            }
+            if !product_outputs.is_empty() {
+                let mut block_outputs: SmallVec<[&mut [F::Elem]; 32]> = product_outputs
+                    .iter_mut()
+                    .map(|output| &mut output[start..end])
+                    .collect();
+
+                F::dot_prod_prepared(
+                    &rows.products,
+                    &rows.product_tables,
+                    &block_inputs,
+                    &mut block_outputs,
+                );
+            }
+
+            start = end;
         }
     }
 
@@ -500,17 +751,19 @@ impl<F: Field> ReedSolomon<F> {
             let matrix_row_to_use = matrix_rows[i_row][i_input];
             let output = output.as_mut();
 
-            if i_input == 0 {
-                F::mul_slice(matrix_row_to_use, input, output);
-            } else {
-                F::mul_slice_add(matrix_row_to_use, input, output);
+            let is_one = matrix_row_to_use == F::one();
+            match (i_input == 0, is_one) {
+                (true, true) => output.copy_from_slice(input),
+                (true, false) => F::mul_slice(matrix_row_to_use, input, output),
+                (false, true) => F::add_slice(input, output),
+                (false, false) => F::mul_slice_add(matrix_row_to_use, input, output),
             }
         })
     }
 
     fn check_some_slices_with_buffer<T, U>(
         &self,
-        matrix_rows: &[&[F::Elem]],
+        rows: &SelectedRows<F>,
         inputs: &[T],
         to_check: &[T],
         buffer: &mut [U],
@@ -519,7 +772,7 @@ impl<F: Field> ReedSolomon<F> {
         T: AsRef<[F::Elem]>,
         U: AsRef<[F::Elem]> + AsMut<[F::Elem]>,
     {
-        self.code_some_slices(matrix_rows, inputs, buffer);
+        self.code_some_slices(rows, inputs, buffer);
 
         let at_least_one_mismatch_present = buffer
             .iter_mut()
@@ -623,7 +876,7 @@ impl<F: Field> ReedSolomon<F> {
         check_piece_count!(parity => self, parity);
         check_slices!(multi => data, multi => parity);
 
-        let parity_rows = self.get_parity_rows();
+        let parity_rows = self.parity_rows.select(0..self.parity_shard_count);
 
         // Do the coding.
         self.code_some_slices(&parity_rows, data, parity);
@@ -631,6 +884,86 @@ impl<F: Field> ReedSolomon<F> {
         Ok(())
     }
 
+    /// Constructs the parity shards into uninitialized buffers, using a
+    /// read-only view into the data shards.
+    ///
+    /// Returns the parity shards, which `parity` now holds. Unlike with
+    /// `encode_sep`, the buffers do not need to be zeroed or otherwise
+    /// initialized beforehand.
+    ///
+    /// Returns the same errors as `encode_sep`.
+    pub fn encode_sep_uninit<'a, T, U>(
+        &self,
+        data: &[T],
+        parity: &'a mut [U],
+    ) -> Result<Vec<&'a mut [F::Elem]>, Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[MaybeUninit<F::Elem>]> + AsMut<[MaybeUninit<F::Elem>]>,
+    {
+        check_piece_count!(data => self, data);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => data, multi => parity);
+
+        let mut parity = assume_init_outputs::<F, U>(parity);
+        let parity_rows = self.parity_rows.select(0..self.parity_shard_count);
+
+        // Do the coding.
+        self.code_some_slices(&parity_rows, data, &mut parity);
+
+        Ok(parity)
+    }
+
+    /// Computes the contribution of the data shard indexed by `i_data`
+    /// to every parity shard.
+    ///
+    /// The slots in `parity` will be overwritten with the contributions,
+    /// so unlike `encode_single_sep`, the result does not depend on
+    /// previous calls or on the order in which data shards are handled.
+    ///
+    /// The parity shards are the sum of the contributions of all data shards,
+    /// see `merge_parity`.
+    pub fn partial_parity<U: AsRef<[F::Elem]> + AsMut<[F::Elem]>>(
+        &self,
+        i_data: usize,
+        single_data: &[F::Elem],
+        parity: &mut [U],
+    ) -> Result<(), Error> {
+        check_slice_index!(data => self, i_data);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => parity, single => single_data);
+
+        let parity_rows = self.get_parity_rows();
+
+        for (parity_row, output) in parity_rows.iter().zip(parity.iter_mut()) {
+            F::mul_slice(parity_row[i_data], single_data, output.as_mut());
+        }
+
+        Ok(())
+    }
+
+    /// Adds partial parity shards, as computed by `partial_parity`,
+    /// or as obtained by previous calls to `merge_parity`, into `parity`.
+    ///
+    /// `parity` should start out zeroed or hold a previous contribution.
+    /// As field addition is commutative and associative, contributions
+    /// can be merged in any order and grouping.
+    pub fn merge_parity<T: AsRef<[F::Elem]>, U: AsRef<[F::Elem]> + AsMut<[F::Elem]>>(
+        &self,
+        partial: &[T],
+        parity: &mut [U],
+    ) -> Result<(), Error> {
+        check_piece_count!(parity => self, partial);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => partial, multi => parity);
+
+        for (input, output) in partial.iter().zip(parity.iter_mut()) {
+            F::add_slice(input.as_ref(), output.as_mut());
+        }
+
+        Ok(())
+    }
+
     /// Checks if the parity shards are correct.
     ///
     /// This is a wrapper of `verify_with_buffer`.
@@ -663,7 +996,7 @@ impl<F: Field> ReedSolomon<F> {
         let data = &slices[0..self.data_shard_count];
         let to_check = &slices[self.data_shard_count..];
 
-        let parity_rows = self.get_parity_rows();
+        let parity_rows = self.parity_rows.select(0..self.parity_shard_count);
 
         Ok(self.check_some_slices_with_buffer(&parity_rows, data, to_check, buffer))
     }
@@ -677,6 +1010,9 @@ impl<F: Field> ReedSolomon<F> {
     ///
     /// `reconstruct`, `reconstruct_data`, `reconstruct_shards`,
     /// `reconstruct_data_shards` share the same core code base.
+    ///
+    /// Missing shards of `Option` are allocated zeroed, see
+    /// `reconstruct_uninit` to reconstruct into uninitialized buffers.
     pub fn reconstruct<T: ReconstructShard<F>>(&self, slices: &mut [T]) -> Result<(), Error> {
         self.reconstruct_internal(slices, false)
     }
@@ -694,15 +1030,104 @@ impl<F: Field> ReedSolomon<F> {
         self.reconstruct_internal(slices, true)
     }
 
-    fn get_data_decode_matrix(
+    /// Reconstructs the missing shards into uninitialized buffers.
+    ///
+    /// `shards` holds every shard, `None` for the missing ones, and `outputs`
+    /// one buffer per missing shard, in the order of the shards. Returns the
+    /// reconstructed shards, which `outputs` now holds. Unlike with
+    /// `reconstruct`, the buffers do not need to be zeroed or otherwise
+    /// initialized beforehand.
+    ///
+    /// Returns `Error::TooFewBufferShards` or `Error::TooManyBufferShards` if
+    /// there is not one buffer per missing shard, `Error::IncorrectShardSize`
+    /// if a buffer does not have the length of the shards, and the same
+    /// errors as `reconstruct` otherwise.
+    pub fn reconstruct_uninit<'a, T, U>(
+        &self,
+        shards: &[Option<T>],
+        outputs: &'a mut [U],
+    ) -> Result<Vec<&'a mut [F::Elem]>, Error>
+    where
+        T: AsRef<[F::Elem]>,
+        U: AsRef<[MaybeUninit<F::Elem>]> + AsMut<[MaybeUninit<F::Elem>]>,
+    {
+        check_piece_count!(all => self, shards);
+
+        let data_shard_count = self.data_shard_count;
+
+        // Like in `reconstruct_internal`, only the first `data_shard_count`
+        // present shards are used.
+        let mut sub_shards: SmallVec<[&[F::Elem]; 32]> = SmallVec::with_capacity(data_shard_count);
+        let mut valid_indices: SmallVec<[usize; 32]> = SmallVec::with_capacity(data_shard_count);
+        let mut invalid_indices: SmallVec<[usize; 32]> = SmallVec::with_capacity(data_shard_count);
+        let mut shard_len = None;
+
+        for (i, shard) in shards.iter().enumerate() {
+            match shard {
+                Some(shard) => {
+                    let shard = shard.as_ref();
+                    if shard.is_empty() {
+                        return Err(Error::EmptyShard);
+                    }
+                    if *shard_len.get_or_insert(shard.len()) != shard.len() {
+                        return Err(Error::IncorrectShardSize);
+                    }
+                    if sub_shards.len() < data_shard_count {
+                        sub_shards.push(shard);
+                        valid_indices.push(i);
+                    }
+                }
+                None => invalid_indices.push(i),
+            }
+        }
+
+        if outputs.len() < invalid_indices.len() {
+            return Err(Error::TooFewBufferShards);
+        }
+        if outputs.len() > invalid_indices.len() {
+            return Err(Error::TooManyBufferShards);
+        }
+        if invalid_indices.is_empty() {
+            return Ok(Vec::new());
+        }
+        if sub_shards.len() < data_shard_count {
+            return Err(Error::TooFewShardsPresent);
+        }
+        if outputs
+            .iter()
+            .any(|output| Some(output.as_ref().len()) != shard_len)
+        {
+            return Err(Error::IncorrectShardSize);
+        }
+
+        let plan = self.get_decode_plan(&valid_indices, &invalid_indices)?;
+
+        let mut outputs = assume_init_outputs::<F, U>(outputs);
+        let missing_data_count = invalid_indices
+            .iter()
+            .take_while(|&&i| i < data_shard_count)
+            .count();
+        let (missing_data_slices, missing_parity_slices) = outputs.split_at_mut(missing_data_count);
+        self.decode_missing(
+            &plan,
+            &invalid_indices,
+            &sub_shards,
+            missing_data_slices,
+            Some(missing_parity_slices),
+        );
+
+        Ok(outputs)
+    }
+
+    fn get_decode_plan(
         &self,
         valid_indices: &[usize],
         invalid_indices: &[usize],
-    ) -> Arc<Matrix<F>> {
+    ) -> Result<Arc<DecodePlan<F>>, Error> {
         {
             let mut cache = self.data_decode_matrix_cache.lock();
             if let Some(entry) = cache.get(invalid_indices) {
-                return entry.clone();
+                return Ok(entry.clone());
             }
         }
         // Pull out the rows of the matrix that correspond to the shards that
@@ -718,16 +1143,106 @@ impl<F: Field> ReedSolomon<F> {
         // original data. Then pull out the row that generates the shard that
         // we want to decode. Note that since this matrix maps back to the
         // original data, it can be used to create a data shard, but not a
-        // parity shard.
-        let data_decode_matrix = Arc::new(sub_matrix.invert().unwrap());
-        // Cache the inverted matrix for future use keyed on the indices of the
-        // invalid rows.
+        // parity shard. The matrix built by `new` is always invertible, but
+        // other matrices passed to `with_matrix` may not be.
+        let data_decode_matrix = sub_matrix.invert().map_err(|_| Error::SingularMatrix)?;
+        let missing_data_rows = PreparedRows::new(
+            self.data_shard_count,
+            invalid_indices
+                .iter()
+                .take_while(|&&i| i < self.data_shard_count)
+                .map(|&i| data_decode_matrix.get_row(i)),
+        );
+        let plan = Arc::new(DecodePlan {
+            matrix: data_decode_matrix,
+            missing_data_rows,
+        });
+        // Cache the plan for future use keyed on the indices of the invalid
+        // rows.
         {
-            let data_decode_matrix = data_decode_matrix.clone();
+            let plan = plan.clone();
             let mut cache = self.data_decode_matrix_cache.lock();
-            cache.put(Vec::from(invalid_indices), data_decode_matrix);
+            cache.put(Vec::from(invalid_indices), plan);
+        }
+        Ok(plan)
+    }
+
+    /// Computes the coefficients needed to regenerate the shard at index
+    /// `target` from the shards listed in `available`.
+    ///
+    /// The result holds `(shard index, coefficient)` pairs, the target
+    /// shard being the sum of every listed shard multiplied by its coefficient.
+    /// This allows the repair work to be split up, e.g. each holder of a shard
+    /// computes `coefficient * shard` locally via `mul_slice` and the partial
+    /// results are added together afterwards.
+    ///
+    /// Only the first `data_shard_count` distinct indices (in ascending order)
+    /// of `available` are used. If `target` itself is available, the result is
+    /// `[(target, one)]`.
+    ///
+    /// Returns `Error::InvalidIndex` if `target` or any index in `available`
+    /// is not smaller than `total_shard_count`.
+    ///
+    /// Returns `Error::TooFewShardsPresent` if `available` holds fewer than
+    /// `data_shard_count` distinct indices.
+    pub fn repair_coefficients(
+        &self,
+        available: &[usize],
+        target: usize,
+    ) -> Result<Vec<(usize, F::Elem)>, Error> {
+        check_slice_index!(all => self, target);
+        for &i in available.iter() {
+            check_slice_index!(all => self, i);
+        }
+
+        if available.contains(&target) {
+            return Ok(vec![(target, F::one())]);
+        }
+
+        let mut present: SmallVec<[bool; 32]> = SmallVec::from_elem(false, self.total_shard_count);
+        for &i in available.iter() {
+            present[i] = true;
+        }
+
+        let mut valid_indices: SmallVec<[usize; 32]> =
+            SmallVec::with_capacity(self.data_shard_count);
+        let mut invalid_indices: SmallVec<[usize; 32]> =
+            SmallVec::with_capacity(self.parity_shard_count);
+        for (i, &is_present) in present.iter().enumerate() {
+            if !is_present {
+                invalid_indices.push(i);
+            } else if valid_indices.len() < self.data_shard_count {
+                valid_indices.push(i);
+            }
+        }
+
+        if valid_indices.len() < self.data_shard_count {
+            return Err(Error::TooFewShardsPresent);
         }
-        data_decode_matrix
+
+        let plan = self.get_decode_plan(&valid_indices, &invalid_indices)?;
+
+        // The target shard is generated from the data shards by its row
+        // in `self.matrix`, and each data shard is in turn generated from
+        // the valid shards by its row in the data decode matrix.
+        let target_row = self.matrix.get_row(target);
+
+        let coefficients = valid_indices
+            .iter()
+            .enumerate()
+            .map(|(c, &valid_index)| {
+                let coefficient = target_row
+                    .iter()
+                    .enumerate()
+                    .fold(F::zero(), |acc, (r, &elem)| {
+                        F::add(acc, F::mul(elem, plan.matrix.get(r, c)))
+                    });
+
+                (valid_index, coefficient)
+            })
+            .collect();
+
+        Ok(coefficients)
     }
 
     fn reconstruct_internal<T: ReconstructShard<F>>(
@@ -797,6 +1312,18 @@ impl<F: Field> ReedSolomon<F> {
         let mut valid_indices: SmallVec<[usize; 32]> = SmallVec::with_capacity(data_shard_count);
         let mut invalid_indices: SmallVec<[usize; 32]> = SmallVec::with_capacity(data_shard_count);
 
+        for (matrix_row, shard) in shards.iter().enumerate() {
+            if shard.len().is_none() {
+                invalid_indices.push(matrix_row);
+            } else if valid_indices.len() < data_shard_count {
+                valid_indices.push(matrix_row);
+            }
+        }
+
+        // Build the data decode matrix before touching any shard, so that
+        // nothing is modified if it turns out to be singular.
+        let plan = self.get_decode_plan(&valid_indices, &invalid_indices)?;
+
         // Separate the shards into groups
         for (matrix_row, shard) in shards.iter_mut().enumerate() {
             // get or initialize the shard so we can reconstruct in-place,
@@ -812,7 +1339,6 @@ impl<F: Field> ReedSolomon<F> {
                 Ok(shard) => {
                     if sub_shards.len() < data_shard_count {
                         sub_shards.push(shard);
-                        valid_indices.push(matrix_row);
                     } else {
                         // Already have enough shards in `sub_shards`
                         // as we only need N shards, where N = `data_shard_count`,
@@ -823,8 +1349,7 @@ impl<F: Field> ReedSolomon<F> {
                 }
                 Err(None) => {
                     // the shard data is not meant to be initialized here,
-                    // but we should still note it missing.
-                    invalid_indices.push(matrix_row);
+                    // it is already noted missing.
                 }
                 Err(Some(x)) => {
                     // initialized missing shard data.
@@ -834,52 +1359,62 @@ impl<F: Field> ReedSolomon<F> {
                     } else {
                         missing_parity_slices.push(shard);
                     }
-
-                    invalid_indices.push(matrix_row);
                 }
             }
         }
 
-        let data_decode_matrix = self.get_data_decode_matrix(&valid_indices, &invalid_indices);
+        let missing_parity_slices = if data_only {
+            None
+        } else {
+            Some(&mut missing_parity_slices[..])
+        };
+        self.decode_missing(
+            &plan,
+            &invalid_indices,
+            &sub_shards,
+            &mut missing_data_slices,
+            missing_parity_slices,
+        );
+
+        Ok(())
+    }
+
+    // Re-creates the missing data shards from `sub_shards`, the first
+    // `data_shard_count` present shards, then the missing parity shards
+    // unless `missing_parity_slices` is `None`.
+    fn decode_missing(
+        &self,
+        plan: &DecodePlan<F>,
+        invalid_indices: &[usize],
+        sub_shards: &[&[F::Elem]],
+        missing_data_slices: &mut [&mut [F::Elem]],
+        missing_parity_slices: Option<&mut [&mut [F::Elem]]>,
+    ) {
+        let data_shard_count = self.data_shard_count;
 
         // Re-create any data shards that were missing.
         //
         // The input to the coding is all of the shards we actually
         // have, and the output is the missing data shards. The computation
         // is done using the special decode matrix we just built.
-        let mut matrix_rows: SmallVec<[&[F::Elem]; 32]> =
-            SmallVec::with_capacity(self.parity_shard_count);
+        let missing_data_rows = &plan.missing_data_rows;
+        let matrix_rows = missing_data_rows.select(0..missing_data_rows.row_count());
 
-        for i_slice in invalid_indices
-            .iter()
-            .cloned()
-            .take_while(|i| i < &data_shard_count)
-        {
-            matrix_rows.push(data_decode_matrix.get_row(i_slice));
-        }
+        self.code_some_slices(&matrix_rows, sub_shards, missing_data_slices);
 
-        self.code_some_slices(&matrix_rows, &sub_shards, &mut missing_data_slices);
-
-        if data_only {
-            Ok(())
-        } else {
+        if let Some(missing_parity_slices) = missing_parity_slices {
             // Now that we have all of the data shards intact, we can
             // compute any of the parity that is missing.
             //
             // The input to the coding is ALL of the data shards, including
             // any that we just calculated.  The output is whichever of the
             // parity shards were missing.
-            let mut matrix_rows: SmallVec<[&[F::Elem]; 32]> =
-                SmallVec::with_capacity(self.parity_shard_count);
-            let parity_rows = self.get_parity_rows();
-
-            for i_slice in invalid_indices
-                .iter()
-                .cloned()
-                .skip_while(|i| i < &data_shard_count)
-            {
-                matrix_rows.push(parity_rows[i_slice - data_shard_count]);
-            }
+            let matrix_rows = self.parity_rows.select(
+                invalid_indices
+                    .iter()
+                    .skip_while(|&&i| i < data_shard_count)
+                    .map(|&i| i - data_shard_count),
+            );
             {
                 // Gather up all the data shards.
                 // old data shards are in `sub_shards`,
@@ -915,10 +1450,8 @@ impl<F: Field> ReedSolomon<F> {
 
                 // Now do the actual computation for the missing
                 // parity shards
-                self.code_some_slices(&matrix_rows, &all_data_slices, &mut missing_parity_slices);
+                self.code_some_slices(&matrix_rows, &all_data_slices, missing_parity_slices);
             }
-
-            Ok(())
         }
     }
 }
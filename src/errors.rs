@@ -15,9 +15,11 @@ pub enum Error {
     EmptyShard,
     InvalidShardFlags,
     InvalidIndex,
+    SingularMatrix,
 }
 
 impl Error {
+    #[allow(clippy::wrong_self_convention)]
     fn to_string(&self) -> &str {
         match *self {
             Error::TooFewShards=> "The number of provided shards is smaller than the one in codec",
@@ -33,6 +35,7 @@ impl Error {
             Error::EmptyShard => "The first shard provided is of zero length",
             Error::InvalidShardFlags => "The number of flags does not match the total number of shards",
             Error::InvalidIndex => "The data shard index provided is greater or equal to the number of data shards in codec",
+            Error::SingularMatrix => "The rows of the codec matrix for the present shards are not invertible, cannot reconstruct missing shards",
         }
     }
 }
@@ -58,6 +61,7 @@ pub enum SBSError {
 }
 
 impl SBSError {
+    #[allow(clippy::wrong_self_convention)]
     fn to_string(&self) -> &str {
         match *self {
             SBSError::TooManyCalls => "Too many calls",
@@ -80,9 +84,103 @@ impl std::error::Error for SBSError {
     }
 }
 
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum FormatError {
+    TooShort,
+    InvalidMagic,
+    UnsupportedVersion,
+    ChecksumKindMismatch,
+    ChecksumMismatch,
+    CodecMismatch,
+    RSError(Error),
+}
+
+impl FormatError {
+    #[allow(clippy::wrong_self_convention)]
+    fn to_string(&self) -> &str {
+        match *self {
+            FormatError::TooShort => "The frame is shorter than its header and payload",
+            FormatError::InvalidMagic => "The frame does not start with the magic bytes",
+            FormatError::UnsupportedVersion => "The frame format version is not supported",
+            FormatError::ChecksumKindMismatch => {
+                "The frame uses a different checksum kind than the one expected"
+            }
+            FormatError::ChecksumMismatch => "The checksum of the frame does not match its content",
+            FormatError::CodecMismatch => {
+                "The codec parameters of the frame do not match the ones of the codec"
+            }
+            FormatError::RSError(ref e) => e.to_string(),
+        }
+    }
+}
+
+impl core::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), core::fmt::Error> {
+        write!(f, "{}", self.to_string())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FormatError {
+    fn description(&self) -> &str {
+        self.to_string()
+    }
+}
+
+#[cfg(feature = "par2")]
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Par2Error {
+    InvalidSliceSize,
+    TooManySlices,
+    MissingPackets,
+    FileCountMismatch,
+    TooFewRecoverySlices,
+    SingularMatrix,
+}
+
+#[cfg(feature = "par2")]
+impl Par2Error {
+    #[allow(clippy::wrong_self_convention)]
+    fn to_string(&self) -> &str {
+        match *self {
+            Par2Error::InvalidSliceSize => "The slice size is zero or not a multiple of 4",
+            Par2Error::TooManySlices => {
+                "The number of input or recovery slices exceeds the limit of the format"
+            }
+            Par2Error::MissingPackets => {
+                "The recovery files lack the main packet or the description of a file"
+            }
+            Par2Error::FileCountMismatch => {
+                "The number of provided files does not match the recovery set"
+            }
+            Par2Error::TooFewRecoverySlices => {
+                "The number of recovery slices is smaller than the number of damaged input slices"
+            }
+            Par2Error::SingularMatrix => {
+                "The available recovery slices cannot recover the damaged input slices"
+            }
+        }
+    }
+}
+
+#[cfg(feature = "par2")]
+impl core::fmt::Display for Par2Error {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), core::fmt::Error> {
+        write!(f, "{}", self.to_string())
+    }
+}
+
+#[cfg(feature = "par2")]
+impl std::error::Error for Par2Error {
+    fn description(&self) -> &str {
+        self.to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::errors::Error;
+    use crate::errors::FormatError;
     use crate::errors::SBSError;
 
     #[test]
@@ -136,6 +234,10 @@ mod tests {
             Error::InvalidIndex.to_string(),
             "The data shard index provided is greater or equal to the number of data shards in codec"
         );
+        assert_eq!(
+            Error::SingularMatrix.to_string(),
+            "The rows of the codec matrix for the present shards are not invertible, cannot reconstruct missing shards"
+        );
     }
 
     #[test]
@@ -144,6 +246,22 @@ mod tests {
         assert_eq!(SBSError::LeftoverShards.to_string(), "Leftover shards");
     }
 
+    #[test]
+    fn test_formaterror_to_string_is_okay() {
+        assert_eq!(
+            FormatError::TooShort.to_string(),
+            "The frame is shorter than its header and payload"
+        );
+        assert_eq!(
+            FormatError::ChecksumMismatch.to_string(),
+            "The checksum of the frame does not match its content"
+        );
+        assert_eq!(
+            FormatError::RSError(Error::TooFewShardsPresent).to_string(),
+            Error::TooFewShardsPresent.to_string()
+        );
+    }
+
     #[cfg(feature = "std")]
     #[test]
     fn test_error_display_does_not_panic() {
@@ -0,0 +1,298 @@
+//! Fetching the first `data_shard_count` of the shards of a stripe.
+//!
+//! The latency of reading an erasure-coded stripe is dominated by the slowest
+//! node it waits for. `fetch_first_k` only waits for the first
+//! `data_shard_count` shards to arrive, and hedges slow requests by asking for
+//! more shards than strictly needed after a deadline.
+//!
+//! Timers are not provided by this module, so that it does not depend on a
+//! particular runtime. The deadline is given as a future instead, e.g.
+//! `tokio::time::sleep(duration)` or `async_io::Timer::after(duration)`.
+//!
+//! This module is only available with the `async` feature enabled.
+
+use std::future::Future;
+use std::iter::FromIterator;
+
+use futures_util::future::poll_fn;
+use futures_util::stream::{FuturesUnordered, StreamExt};
+
+use crate::errors::Error;
+use crate::{Field, ReedSolomon};
+
+/// Fetches the shards of a stripe until `data_shard_count` of them
+/// arrived, and reconstructs the data shards from those.
+///
+/// `fetches` has one future per shard, producing the shard or an error.
+/// The futures are lazy, so a fetch is only issued when its future is
+/// first polled:
+///
+/// - At first, only the first `data_shard_count` fetches are issued, which
+///   are the ones of the data shards.
+///
+/// - Whenever a fetch fails, the next fetch which was not issued yet is
+///   issued in its place.
+///
+/// - Once `hedge` completes, all remaining fetches are issued. Pass
+///   `future::ready(())` to issue all fetches right away, and
+///   `future::pending()` to only issue fetches in place of failed ones.
+///
+/// As soon as `data_shard_count` fetches succeeded, the outstanding
+/// fetches are dropped, which cancels them.
+///
+/// Returns the shards, where all data shards are present and the parity
+/// shards which were not fetched are `None`.
+///
+/// Returns `Error::TooFewShards` or `Error::TooManyShards` if the number
+/// of fetches does not match the total shard count of the codec, and
+/// `Error::TooFewShardsPresent` if too many fetches failed. Errors of
+/// `reconstruct_data` are passed through.
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate reed_solomon_erasure;
+/// # use futures_util::future::{self, Either};
+/// # use reed_solomon_erasure::galois_8::ReedSolomon;
+/// # use reed_solomon_erasure::fetch::fetch_first_k;
+/// # fn main () {
+/// let r = ReedSolomon::new(3, 2).unwrap();
+///
+/// let mut shards = shards!([0u8, 1, 2, 3],
+///                          [4, 5, 6, 7],
+///                          [8, 9, 10, 11],
+///                          [0, 0, 0, 0],
+///                          [0, 0, 0, 0]);
+/// r.encode(&mut shards).unwrap();
+///
+/// // the node holding the second shard never answers
+/// let fetches: Vec<_> = shards
+///     .iter()
+///     .enumerate()
+///     .map(|(i, shard)| {
+///         if i == 1 {
+///             Either::Left(future::pending::<Result<Vec<u8>, ()>>())
+///         } else {
+///             Either::Right(future::ready(Ok(shard.clone())))
+///         }
+///     })
+///     .collect();
+///
+/// let fetched = futures_executor::block_on(
+///     fetch_first_k(&r, fetches, future::ready(()))
+/// ).unwrap();
+/// assert_eq!(Some(&shards[1]), fetched[1].as_ref());
+/// # }
+/// ```
+pub async fn fetch_first_k<F, T, E, Fut, D>(
+    codec: &ReedSolomon<F>,
+    fetches: Vec<Fut>,
+    hedge: D,
+) -> Result<Vec<Option<T>>, Error>
+where
+    F: Field,
+    T: AsRef<[F::Elem]> + AsMut<[F::Elem]> + FromIterator<F::Elem>,
+    Fut: Future<Output = Result<T, E>>,
+    D: Future<Output = ()>,
+{
+    if fetches.len() < codec.total_shard_count() {
+        return Err(Error::TooFewShards);
+    }
+    if fetches.len() > codec.total_shard_count() {
+        return Err(Error::TooManyShards);
+    }
+
+    let data_shards = codec.data_shard_count();
+
+    let mut shards: Vec<Option<T>> = fetches.iter().map(|_| None).collect();
+    let mut present = 0;
+
+    let mut not_issued = fetches
+        .into_iter()
+        .enumerate()
+        .map(|(i, fetch)| async move { (i, fetch.await) });
+    let mut issued = FuturesUnordered::new();
+    issued.extend(not_issued.by_ref().take(data_shards));
+
+    let mut hedge = Some(Box::pin(hedge));
+
+    while present < data_shards {
+        let next = poll_fn(|cx| {
+            if let Some(deadline) = hedge.as_mut() {
+                if deadline.as_mut().poll(cx).is_ready() {
+                    hedge = None;
+                    issued.extend(not_issued.by_ref());
+                }
+            }
+
+            // failed fetches are replaced as long as there are fetches left,
+            // so nothing left to wait for means all fetches were issued
+            issued.poll_next_unpin(cx)
+        })
+        .await;
+
+        match next {
+            Some((i, Ok(shard))) => {
+                shards[i] = Some(shard);
+                present += 1;
+            }
+            Some((_, Err(_))) => issued.extend(not_issued.next()),
+            None => return Err(Error::TooFewShardsPresent),
+        }
+    }
+
+    // cancel the outstanding fetches
+    drop(issued);
+
+    codec.reconstruct_data(&mut shards)?;
+
+    Ok(shards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::galois_8;
+    use crate::tests::fill_random;
+    use futures_executor::block_on;
+    use futures_util::future;
+    use std::cell::Cell;
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Node {
+        Ok,
+        Slow,
+        Failing,
+    }
+
+    fn make_shards(r: &galois_8::ReedSolomon) -> Vec<Vec<u8>> {
+        let mut shards = vec![vec![0u8; 100]; r.total_shard_count()];
+        for shard in shards.iter_mut() {
+            fill_random(shard);
+        }
+        r.encode(&mut shards).unwrap();
+        shards
+    }
+
+    // Fetches from nodes behaving as in `nodes`, recording which fetches were issued.
+    fn fetch<'a, D: Future<Output = ()>>(
+        r: &galois_8::ReedSolomon,
+        shards: &'a [Vec<u8>],
+        nodes: &'a [Node],
+        issued: &'a [Cell<bool>],
+        hedge: D,
+    ) -> Result<Vec<Option<Vec<u8>>>, Error> {
+        let fetches: Vec<_> = (0..shards.len())
+            .map(|i| async move {
+                issued[i].set(true);
+                match nodes[i] {
+                    Node::Ok => Ok(shards[i].clone()),
+                    Node::Slow => future::pending().await,
+                    Node::Failing => Err(()),
+                }
+            })
+            .collect();
+
+        block_on(fetch_first_k(r, fetches, hedge))
+    }
+
+    #[test]
+    fn test_fetch_first_k_only_issues_data_fetches() {
+        let r = galois_8::ReedSolomon::new(4, 2).unwrap();
+        let shards = make_shards(&r);
+        let nodes = [Node::Ok; 6];
+        let issued: Vec<_> = (0..6).map(|_| Cell::new(false)).collect();
+
+        let fetched = fetch(&r, &shards, &nodes, &issued, future::pending()).unwrap();
+        for i in 0..4 {
+            assert_eq!(Some(&shards[i]), fetched[i].as_ref());
+            assert!(issued[i].get());
+        }
+        assert!(fetched[4].is_none() && fetched[5].is_none());
+        assert!(!issued[4].get() && !issued[5].get());
+    }
+
+    #[test]
+    fn test_fetch_first_k_hedges_slow_fetches() {
+        let r = galois_8::ReedSolomon::new(4, 2).unwrap();
+        let shards = make_shards(&r);
+        let nodes = [
+            Node::Ok,
+            Node::Slow,
+            Node::Ok,
+            Node::Slow,
+            Node::Ok,
+            Node::Ok,
+        ];
+        let issued: Vec<_> = (0..6).map(|_| Cell::new(false)).collect();
+
+        let fetched = fetch(&r, &shards, &nodes, &issued, future::ready(())).unwrap();
+        for i in 0..4 {
+            assert_eq!(Some(&shards[i]), fetched[i].as_ref());
+        }
+        assert!(issued.iter().all(|i| i.get()));
+    }
+
+    #[test]
+    fn test_fetch_first_k_replaces_failed_fetches() {
+        let r = galois_8::ReedSolomon::new(4, 2).unwrap();
+        let shards = make_shards(&r);
+        let nodes = [
+            Node::Failing,
+            Node::Ok,
+            Node::Ok,
+            Node::Ok,
+            Node::Ok,
+            Node::Ok,
+        ];
+        let issued: Vec<_> = (0..6).map(|_| Cell::new(false)).collect();
+
+        let fetched = fetch(&r, &shards, &nodes, &issued, future::pending()).unwrap();
+        for i in 0..4 {
+            assert_eq!(Some(&shards[i]), fetched[i].as_ref());
+        }
+        assert!(issued[4].get());
+        assert!(!issued[5].get());
+
+        // too many failures
+        let nodes = [
+            Node::Failing,
+            Node::Ok,
+            Node::Failing,
+            Node::Ok,
+            Node::Failing,
+            Node::Ok,
+        ];
+        for &hedge in [false, true].iter() {
+            let issued: Vec<_> = (0..6).map(|_| Cell::new(false)).collect();
+            let hedge = if hedge {
+                future::Either::Left(future::ready(()))
+            } else {
+                future::Either::Right(future::pending())
+            };
+            assert_eq!(
+                Error::TooFewShardsPresent,
+                fetch(&r, &shards, &nodes, &issued, hedge).unwrap_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_fetch_first_k_error_handling() {
+        let r = galois_8::ReedSolomon::new(4, 2).unwrap();
+
+        let fetches: Vec<future::Ready<Result<Vec<u8>, ()>>> = vec![];
+        assert_eq!(
+            Error::TooFewShards,
+            block_on(fetch_first_k(&r, fetches, future::ready(()))).unwrap_err()
+        );
+
+        let fetches: Vec<_> = (0..7)
+            .map(|_| future::ready(Ok::<_, ()>(vec![0u8; 10])))
+            .collect();
+        assert_eq!(
+            Error::TooManyShards,
+            block_on(fetch_first_k(&r, fetches, future::ready(()))).unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Linear fingerprints of shards.
+//!
+//! A fingerprint maps a shard to a short vector of field elements through a
+//! keyed linear map. As the map is linear over the field, the fingerprints of
+//! an encoded stripe form a codeword of the same code: the fingerprints of
+//! the parity shards are the same combination of the fingerprints of the data
+//! shards as the parity shards are of the data shards.
+//!
+//! This allows checking the consistency of a stripe spread over several nodes
+//! by exchanging only the fingerprints, and running `verify` on those instead
+//! of the shards themselves.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate reed_solomon_erasure;
+//! # use reed_solomon_erasure::galois_8::{Field, ReedSolomon};
+//! # use reed_solomon_erasure::fingerprint::Fingerprinter;
+//! # fn main () {
+//! let r = ReedSolomon::new(3, 2).unwrap();
+//!
+//! let mut shards = shards!([0u8, 1, 2, 3],
+//!                          [4, 5, 6, 7],
+//!                          [8, 9, 10, 11],
+//!                          [0, 0, 0, 0],
+//!                          [0, 0, 0, 0]);
+//! r.encode(&mut shards).unwrap();
+//!
+//! // each node computes the fingerprint of the shard it holds
+//! let fingerprinter = Fingerprinter::<Field>::new(0x5eed, 8);
+//! let fingerprints: Vec<_> = shards.iter().map(|s| fingerprinter.fingerprint(s)).collect();
+//!
+//! // the coordinator checks the fingerprints instead of the shards
+//! assert!(r.verify(&fingerprints).unwrap());
+//! # }
+//! ```
+//!
+//! # Choice of the map
+//!
+//! Evaluating the shard as a polynomial at a secret point is linear as well,
+//! but two different shards of length `n` collide with probability up to
+//! `(n - 1) / ORDER`, which is useless for shards longer than the field order.
+//! Instead, each element of the fingerprint is the dot product of the shard with
+//! a pseudorandom key stream, so two different shards collide with probability
+//! `1 / ORDER` per element of the fingerprint.
+//!
+//! The key stream is derived from a seed that should be chosen at random
+//! for every check, so that a fingerprint cannot be computed before the content
+//! of the shard is fixed. The fingerprints only protect against accidental
+//! corruption, not against nodes which know the seed.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use crate::Field;
+
+/// Computes linear fingerprints of shards.
+///
+/// Fingerprints are only comparable when computed by fingerprinters
+/// with the same seed and width, over shards of the same length.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Fingerprinter<F: Field> {
+    seed: u64,
+    width: usize,
+    _marker: PhantomData<F>,
+}
+
+/// SplitMix64, used to derive the key stream from the seed.
+struct KeyStream(u64);
+
+impl KeyStream {
+    fn new(seed: u64, i_elem: usize) -> KeyStream {
+        KeyStream(seed ^ (i_elem as u64).wrapping_mul(0xD1B5_4A32_D192_ED03))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+impl<F: Field> Fingerprinter<F> {
+    /// Creates a new fingerprinter with the given seed, producing
+    /// fingerprints of `width` field elements.
+    ///
+    /// # Panics
+    /// Panics if `width` is zero.
+    pub fn new(seed: u64, width: usize) -> Fingerprinter<F> {
+        assert!(width > 0, "Fingerprint width must be non-zero");
+
+        Fingerprinter {
+            seed,
+            width,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The number of field elements in a fingerprint.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Computes the fingerprint of a shard.
+    pub fn fingerprint(&self, shard: &[F::Elem]) -> Vec<F::Elem> {
+        let mut out = Vec::with_capacity(self.width);
+        for i_elem in 0..self.width {
+            let mut key_stream = KeyStream::new(self.seed, i_elem);
+
+            let elem = shard.iter().fold(F::zero(), |acc, &x| {
+                let key = F::nth(key_stream.next() as usize % F::ORDER);
+                F::add(acc, F::mul(key, x))
+            });
+
+            out.push(elem);
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::Fingerprinter;
+    use crate::galois_8;
+    use crate::tests::fill_random;
+
+    #[test]
+    fn test_fingerprint_is_linear() {
+        let fingerprinter = Fingerprinter::<galois_8::Field>::new(rand::random(), 4);
+
+        let mut a = vec![0u8; 1000];
+        let mut b = vec![0u8; 1000];
+        fill_random(&mut a);
+        fill_random(&mut b);
+
+        let c = rand::random::<u8>();
+        let combined: Vec<u8> = a
+            .iter()
+            .zip(b.iter())
+            .map(|(&x, &y)| galois_8::add(galois_8::mul(c, x), y))
+            .collect();
+
+        let expect: Vec<u8> = fingerprinter
+            .fingerprint(&a)
+            .iter()
+            .zip(fingerprinter.fingerprint(&b).iter())
+            .map(|(&x, &y)| galois_8::add(galois_8::mul(c, x), y))
+            .collect();
+
+        assert_eq!(expect, fingerprinter.fingerprint(&combined));
+    }
+
+    #[test]
+    fn test_fingerprints_verify_and_reconstruct() {
+        let r = galois_8::ReedSolomon::new(10, 4).unwrap();
+        let fingerprinter = Fingerprinter::<galois_8::Field>::new(rand::random(), 8);
+
+        let mut shards = vec![vec![0u8; 4096]; 14];
+        for shard in shards.iter_mut() {
+            fill_random(shard);
+        }
+        r.encode(&mut shards).unwrap();
+
+        let fingerprints: Vec<_> = shards
+            .iter()
+            .map(|s| fingerprinter.fingerprint(s))
+            .collect();
+        assert!(r.verify(&fingerprints).unwrap());
+
+        // fingerprints of missing shards can be recovered as well
+        let mut partial: Vec<_> = fingerprints.iter().cloned().map(Some).collect();
+        partial[2] = None;
+        partial[12] = None;
+        r.reconstruct(&mut partial).unwrap();
+        assert_eq!(Some(&fingerprints[2]), partial[2].as_ref());
+        assert_eq!(Some(&fingerprints[12]), partial[12].as_ref());
+
+        // corruption in any shard is detected
+        for i in 0..14 {
+            let mut corrupted = shards.clone();
+            corrupted[i][1000] ^= 1;
+            let fingerprints: Vec<_> = corrupted
+                .iter()
+                .map(|s| fingerprinter.fingerprint(s))
+                .collect();
+            assert!(!r.verify(&fingerprints).unwrap());
+        }
+    }
+}
@@ -2,7 +2,10 @@
 //!
 //! More accurately, this is a `GF((2^8)^2)` implementation which builds an extension
 //! field of `GF(2^8)`, as defined in the `galois_8` module.
+//!
+//! See `galois_16_native` for a faster GF(2^16) with log and exp tables.
 
+use crate::galois_16_slice::{self, SplitTables};
 use crate::galois_8;
 use core::ops::{Add, Div, Mul, Sub};
 
@@ -22,6 +25,8 @@ impl crate::Field for Field {
 
     type Elem = [u8; 2];
 
+    const WRITES_OUTPUTS_FIRST: bool = true;
+
     fn add(a: [u8; 2], b: [u8; 2]) -> [u8; 2] {
         (Element(a) + Element(b)).0
     }
@@ -49,6 +54,39 @@ impl crate::Field for Field {
     fn nth_internal(n: usize) -> [u8; 2] {
         [(n >> 8) as u8, n as u8]
     }
+
+    fn mul_slice(c: [u8; 2], input: &[[u8; 2]], out: &mut [[u8; 2]]) {
+        let tables = SplitTables::new(|x| Self::mul(c, x));
+        galois_16_slice::mul_slice(&tables, input, out)
+    }
+
+    fn mul_slice_add(c: [u8; 2], input: &[[u8; 2]], out: &mut [[u8; 2]]) {
+        let tables = SplitTables::new(|x| Self::mul(c, x));
+        galois_16_slice::mul_slice_xor(&tables, input, out)
+    }
+
+    const TABLE_LEN: usize = galois_16_slice::TABLE_LEN;
+
+    fn prepare(c: [u8; 2], table: &mut [[u8; 2]]) {
+        SplitTables::new(|x| Self::mul(c, x)).write_to(table)
+    }
+
+    fn dot_prod_prepared(
+        _matrix_rows: &[&[[u8; 2]]],
+        tables: &[&[[u8; 2]]],
+        inputs: &[&[[u8; 2]]],
+        outputs: &mut [&mut [[u8; 2]]],
+    ) {
+        galois_16_slice::dot_prod(tables, inputs, outputs)
+    }
+
+    fn sum_slices(inputs: &[&[[u8; 2]]], out: &mut [[u8; 2]]) {
+        galois_16_slice::xor_slices(inputs, out)
+    }
+
+    fn add_slice(input: &[[u8; 2]], out: &mut [[u8; 2]]) {
+        galois_16_slice::slice_xor(input, out)
+    }
 }
 
 /// Type alias of ReedSolomon over GF(2^8).
@@ -83,12 +121,18 @@ impl Element {
         } else if self == Element::zero() {
             Element::zero()
         } else {
-            let x = self;
-            for _ in 1..n {
-                self = self * x;
+            // square and multiply
+            let mut result = Element::constant(1);
+            let mut n = n;
+            while n != 0 {
+                if n & 1 == 1 {
+                    result = result * self;
+                }
+                self = self * self;
+                n >>= 1;
             }
 
-            self
+            result
         }
     }
 
@@ -172,6 +216,7 @@ impl Mul<u8> for Element {
 impl Div for Element {
     type Output = Element;
 
+    #[allow(clippy::suspicious_arithmetic_impl)]
     fn div(self, rhs: Self) -> Element {
         self * rhs.inverse()
     }
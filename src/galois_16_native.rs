@@ -0,0 +1,303 @@
+//! GF(2^16) with log and exp tables.
+//!
+//! Unlike `galois_16`, which builds GF(2^16) as an extension field of
+//! `galois_8`, this field is GF(2^16) with the primitive polynomial
+//! `x^16 + x^5 + x^3 + x^2 + 1`, the one of Leopard-RS. Multiplication and
+//! division are table lookups, and slices are multiplied with split tables and
+//! SSSE3 or AVX2 when the CPU supports them.
+//!
+//! Elements are stored as two bytes in little-endian order, so shards of bytes
+//! with an even length are shards of elements. `as_elems` and `as_elems_mut`
+//! convert them without copying:
+//!
+//! ```
+//! # use reed_solomon_erasure::galois_16_native::{as_elems, as_elems_mut, ReedSolomon};
+//! let r = ReedSolomon::new(3, 2).unwrap();
+//!
+//! let mut shards = vec![vec![0u8; 8]; 5];
+//! shards[0].copy_from_slice(b"abcdefgh");
+//! shards[1].copy_from_slice(b"ijklmnop");
+//! shards[2].copy_from_slice(b"qrstuvwx");
+//! {
+//!     let mut elems: Vec<_> = shards.iter_mut().map(|s| as_elems_mut(s)).collect();
+//!     r.encode(&mut elems).unwrap();
+//! }
+//!
+//! let elems: Vec<_> = shards.iter().map(|s| as_elems(s)).collect();
+//! assert!(r.verify(&elems).unwrap());
+//! ```
+//!
+//! Shards of this field have no limit of 256 like those of `galois_8`, and
+//! encoding large numbers of shards is much faster than with `galois_16`.
+//! The parity is different from the one of `galois_16`.
+
+use crate::galois_16_slice::{self, SplitTables};
+
+/// The polynomial of the field, including the `x^16` term.
+pub const POLYNOMIAL: u32 = 0x1002D;
+
+const LIMIT: usize = 65535;
+
+struct Tables {
+    log: [u16; 65536],
+    // twice the period, so the sum of two logarithms needs no reduction
+    exp: [u16; 2 * LIMIT],
+}
+
+const fn log_exp_tables() -> Tables {
+    let mut tables = Tables {
+        log: [0; 65536],
+        exp: [0; 2 * LIMIT],
+    };
+    let mut x: u32 = 1;
+    let mut i = 0;
+    while i < LIMIT {
+        tables.exp[i] = x as u16;
+        tables.exp[i + LIMIT] = x as u16;
+        tables.log[x as usize] = i as u16;
+        x <<= 1;
+        if x & 0x10000 != 0 {
+            x ^= POLYNOMIAL;
+        }
+        i += 1;
+    }
+    tables
+}
+
+static TABLES: Tables = log_exp_tables();
+
+/// The field GF(2^16) with `POLYNOMIAL`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Field;
+
+fn log(a: u16) -> usize {
+    TABLES.log[a as usize] as usize
+}
+
+fn mul(a: u16, b: u16) -> u16 {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        TABLES.exp[log(a) + log(b)]
+    }
+}
+
+fn div(a: u16, b: u16) -> u16 {
+    if a == 0 {
+        0
+    } else if b == 0 {
+        panic!("Divisor is 0")
+    } else {
+        TABLES.exp[log(a) + LIMIT - log(b)]
+    }
+}
+
+fn exp(a: u16, n: usize) -> u16 {
+    if n == 0 {
+        1
+    } else if a == 0 {
+        0
+    } else {
+        let log_result = log(a) as u64 * (n % LIMIT) as u64 % LIMIT as u64;
+        TABLES.exp[log_result as usize]
+    }
+}
+
+impl crate::Field for Field {
+    const ORDER: usize = 65536;
+
+    type Elem = [u8; 2];
+
+    const WRITES_OUTPUTS_FIRST: bool = true;
+
+    fn add(a: [u8; 2], b: [u8; 2]) -> [u8; 2] {
+        [a[0] ^ b[0], a[1] ^ b[1]]
+    }
+
+    fn mul(a: [u8; 2], b: [u8; 2]) -> [u8; 2] {
+        mul(u16::from_le_bytes(a), u16::from_le_bytes(b)).to_le_bytes()
+    }
+
+    fn div(a: [u8; 2], b: [u8; 2]) -> [u8; 2] {
+        div(u16::from_le_bytes(a), u16::from_le_bytes(b)).to_le_bytes()
+    }
+
+    fn exp(elem: [u8; 2], n: usize) -> [u8; 2] {
+        exp(u16::from_le_bytes(elem), n).to_le_bytes()
+    }
+
+    fn zero() -> [u8; 2] {
+        [0; 2]
+    }
+
+    fn one() -> [u8; 2] {
+        [1, 0]
+    }
+
+    fn nth_internal(n: usize) -> [u8; 2] {
+        (n as u16).to_le_bytes()
+    }
+
+    fn mul_slice(c: [u8; 2], input: &[[u8; 2]], out: &mut [[u8; 2]]) {
+        let tables = SplitTables::new(|x| Self::mul(c, x));
+        galois_16_slice::mul_slice(&tables, input, out)
+    }
+
+    fn mul_slice_add(c: [u8; 2], input: &[[u8; 2]], out: &mut [[u8; 2]]) {
+        let tables = SplitTables::new(|x| Self::mul(c, x));
+        galois_16_slice::mul_slice_xor(&tables, input, out)
+    }
+
+    const TABLE_LEN: usize = galois_16_slice::TABLE_LEN;
+
+    fn prepare(c: [u8; 2], table: &mut [[u8; 2]]) {
+        SplitTables::new(|x| Self::mul(c, x)).write_to(table)
+    }
+
+    fn dot_prod_prepared(
+        _matrix_rows: &[&[[u8; 2]]],
+        tables: &[&[[u8; 2]]],
+        inputs: &[&[[u8; 2]]],
+        outputs: &mut [&mut [[u8; 2]]],
+    ) {
+        galois_16_slice::dot_prod(tables, inputs, outputs)
+    }
+
+    fn sum_slices(inputs: &[&[[u8; 2]]], out: &mut [[u8; 2]]) {
+        galois_16_slice::xor_slices(inputs, out)
+    }
+
+    fn add_slice(input: &[[u8; 2]], out: &mut [[u8; 2]]) {
+        galois_16_slice::slice_xor(input, out)
+    }
+}
+
+/// Type alias of ReedSolomon over GF(2^16) with `POLYNOMIAL`.
+pub type ReedSolomon = crate::ReedSolomon<Field>;
+
+/// Type alias of ShardByShard over GF(2^16) with `POLYNOMIAL`.
+pub type ShardByShard<'a> = crate::ShardByShard<'a, Field>;
+
+/// Views a shard of bytes as a shard of elements.
+///
+/// # Panics
+/// Panics if the length of `bytes` is odd.
+pub fn as_elems(bytes: &[u8]) -> &[[u8; 2]] {
+    let (elems, rest) = bytes.as_chunks();
+    assert!(rest.is_empty(), "shard has an odd number of bytes");
+    elems
+}
+
+/// Views a mutable shard of bytes as a shard of elements.
+///
+/// # Panics
+/// Panics if the length of `bytes` is odd.
+pub fn as_elems_mut(bytes: &mut [u8]) -> &mut [[u8; 2]] {
+    let (elems, rest) = bytes.as_chunks_mut();
+    assert!(rest.is_empty(), "shard has an odd number of bytes");
+    elems
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::tests::fill_random;
+    use crate::Field as _;
+
+    quickcheck! {
+        fn qc_mul_div(a: u16, b: u16) -> bool {
+            b == 0 || div(mul(a, b), b) == a
+        }
+
+        fn qc_mul_distributivity(a: u16, b: u16, c: u16) -> bool {
+            mul(a, b ^ c) == mul(a, b) ^ mul(a, c)
+        }
+
+        fn qc_mul_associativity(a: u16, b: u16, c: u16) -> bool {
+            mul(a, mul(b, c)) == mul(mul(a, b), c)
+        }
+
+        fn qc_exp(a: u16, n: usize) -> bool {
+            let n = n % 1000;
+            exp(a, n) == (0..n).fold(1, |acc, _| mul(acc, a))
+        }
+    }
+
+    #[test]
+    fn test_galois() {
+        assert_eq!(4, mul(2, 2));
+        // x^15 * x = x^16 = x^5 + x^3 + x^2 + 1
+        assert_eq!(0x2D, mul(0x8000, 2));
+        assert_eq!(1, exp(2, LIMIT));
+        assert_eq!(2, exp(2, LIMIT + 1));
+        assert_eq!(0, exp(0, 5));
+        assert_eq!([0x2D, 0], Field::mul([0, 0x80], [2, 0]));
+        assert_eq!([0x34, 0x12], Field::nth(0x1234));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_div_b_is_0() {
+        div(1, 0);
+    }
+
+    #[test]
+    fn test_byte_shards() {
+        let r = ReedSolomon::new(10, 4).unwrap();
+        let mut shards = vec![vec![0u8; 200]; 14];
+        for shard in shards[..10].iter_mut() {
+            fill_random(shard);
+        }
+        {
+            let mut elems: Vec<_> = shards.iter_mut().map(|s| as_elems_mut(s)).collect();
+            r.encode(&mut elems).unwrap();
+        }
+
+        let mut damaged = shards.clone();
+        let mut present = [true; 14];
+        for &i in [0, 5, 9, 12].iter() {
+            present[i] = false;
+            damaged[i].iter_mut().for_each(|b| *b = 0);
+        }
+        {
+            let mut elems: Vec<_> = damaged
+                .iter_mut()
+                .zip(present.iter())
+                .map(|(s, p)| (as_elems_mut(s), *p))
+                .collect();
+            r.reconstruct(&mut elems).unwrap();
+        }
+        assert_eq!(shards, damaged);
+    }
+
+    #[test]
+    fn test_many_shards() {
+        let r = ReedSolomon::new(300, 20).unwrap();
+        let mut shards = vec![vec![[0u8; 2]; 16]; 320];
+        for shard in shards[..300].iter_mut() {
+            for elem in shard.iter_mut() {
+                fill_random(elem);
+            }
+        }
+        r.encode(&mut shards).unwrap();
+
+        let mut some: Vec<_> = shards.iter().cloned().map(Some).collect();
+        for i in (0..320).step_by(16) {
+            some[i] = None;
+        }
+        r.reconstruct(&mut some).unwrap();
+        let some: Vec<_> = some.into_iter().map(Option::unwrap).collect();
+        assert_eq!(shards, some);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_odd_shard() {
+        as_elems(&[1, 2, 3]);
+    }
+}
@@ -0,0 +1,616 @@
+//! Slice multiplication for fields with 16-bit elements stored as two bytes.
+//!
+//! Multiplying by a constant is linear over GF(2), so the product of an
+//! element is the sum of the products of its four nibbles. `SplitTables`
+//! holds these 4 x 16 products split into their two bytes, which makes the
+//! routines independent of the field and of its byte order, and lets the
+//! SIMD routines use byte shuffles as table lookups.
+
+use smallvec::SmallVec;
+
+use crate::galois_8;
+
+/// The products of a constant with every nibble of the two bytes of an
+/// element: `bytes[b][k][n]` is byte `b` of the product with the element
+/// having nibble `n` at position `k`, where positions `0` and `1` are the low
+/// and high nibble of byte 0, and positions `2` and `3` those of byte 1.
+pub(crate) struct SplitTables {
+    bytes: [[[u8; 16]; 4]; 2],
+}
+
+impl SplitTables {
+    #[allow(clippy::needless_range_loop)]
+    pub(crate) fn new(mul: impl Fn([u8; 2]) -> [u8; 2]) -> SplitTables {
+        // only the products of single bits are computed, the others are
+        // their sums
+        let mut bytes = [[[0; 16]; 4]; 2];
+        for k in 0..4 {
+            for bit in 0..4 {
+                let single = 1u16 << (4 * k + bit);
+                let product = mul([single as u8, (single >> 8) as u8]);
+                let n = 1 << bit;
+                for m in 0..n {
+                    bytes[0][k][n + m] = bytes[0][k][m] ^ product[0];
+                    bytes[1][k][n + m] = bytes[1][k][m] ^ product[1];
+                }
+            }
+        }
+        SplitTables { bytes }
+    }
+
+    /// Writes the tables into the `TABLE_LEN` elements of `table`.
+    pub(crate) fn write_to(&self, table: &mut [[u8; 2]]) {
+        let bytes = self.bytes.iter().flatten().flatten();
+        for (t, &byte) in table.as_flattened_mut()[..2 * TABLE_LEN]
+            .iter_mut()
+            .zip(bytes)
+        {
+            *t = byte;
+        }
+    }
+
+    /// Reads tables written by `write_to`.
+    pub(crate) fn read_from(table: &[[u8; 2]]) -> SplitTables {
+        let mut bytes = [[[0; 16]; 4]; 2];
+        for (b, &t) in bytes
+            .iter_mut()
+            .flatten()
+            .flatten()
+            .zip(table[..TABLE_LEN].as_flattened())
+        {
+            *b = t;
+        }
+        SplitTables { bytes }
+    }
+
+    fn mul(&self, elem: [u8; 2]) -> [u8; 2] {
+        let nibbles = [
+            (elem[0] & 0x0F) as usize,
+            (elem[0] >> 4) as usize,
+            (elem[1] & 0x0F) as usize,
+            (elem[1] >> 4) as usize,
+        ];
+        let mut product = [0; 2];
+        for (b, tables) in self.bytes.iter().enumerate() {
+            product[b] = tables[0][nibbles[0]]
+                ^ tables[1][nibbles[1]]
+                ^ tables[2][nibbles[2]]
+                ^ tables[3][nibbles[3]];
+        }
+        product
+    }
+}
+
+/// The number of elements taken by `SplitTables::write_to`.
+pub(crate) const TABLE_LEN: usize = 64;
+
+/// Overwrites each output with the sum of the products of the inputs, where
+/// `tables[j]` holds the tables of output `j` and every input, as written by
+/// `SplitTables::write_to`, back to back.
+pub(crate) fn dot_prod(
+    tables: &[&[[u8; 2]]],
+    inputs: &[&[[u8; 2]]],
+    outputs: &mut [&mut [[u8; 2]]],
+) {
+    for (i_input, input) in inputs.iter().enumerate() {
+        for (row, output) in tables.iter().zip(outputs.iter_mut()) {
+            let tables = SplitTables::read_from(&row[i_input * TABLE_LEN..]);
+            if i_input == 0 {
+                mul_slice(&tables, input, output);
+            } else {
+                mul_slice_xor(&tables, input, output);
+            }
+        }
+    }
+}
+
+/// Multiplies `input` by the constant of `tables`, writing into `out`.
+pub(crate) fn mul_slice(tables: &SplitTables, input: &[[u8; 2]], out: &mut [[u8; 2]]) {
+    assert_eq!(input.len(), out.len());
+
+    let done = simd::mul_slice::<false>(tables, input, out);
+    for (i, o) in input[done..].iter().zip(out[done..].iter_mut()) {
+        *o = tables.mul(*i);
+    }
+}
+
+/// Multiplies `input` by the constant of `tables`, adding the products to `out`.
+pub(crate) fn mul_slice_xor(tables: &SplitTables, input: &[[u8; 2]], out: &mut [[u8; 2]]) {
+    assert_eq!(input.len(), out.len());
+
+    let done = simd::mul_slice::<true>(tables, input, out);
+    for (i, o) in input[done..].iter().zip(out[done..].iter_mut()) {
+        let product = tables.mul(*i);
+        o[0] ^= product[0];
+        o[1] ^= product[1];
+    }
+}
+
+/// Adds `input` to `out`.
+pub(crate) fn slice_xor(input: &[[u8; 2]], out: &mut [[u8; 2]]) {
+    assert_eq!(input.len(), out.len());
+
+    for (i, o) in input.iter().zip(out.iter_mut()) {
+        o[0] ^= i[0];
+        o[1] ^= i[1];
+    }
+}
+
+/// Overwrites `out` with the sum of `inputs`.
+pub(crate) fn xor_slices(inputs: &[&[[u8; 2]]], out: &mut [[u8; 2]]) {
+    let inputs: SmallVec<[&[u8]; 32]> = inputs.iter().map(|input| input.as_flattened()).collect();
+    galois_8::xor_slices(&inputs, out.as_flattened_mut())
+}
+
+/// Length of the chunks of `mul_chunks` and `mul_chunks_xor`.
+pub(crate) const CHUNK_LEN: usize = 64;
+
+/// Same as `mul_slice`, for elements stored in chunks of 64 bytes, each
+/// holding the bytes 0 of 32 elements followed by their bytes 1.
+pub(crate) fn mul_chunks(tables: &SplitTables, input: &[u8], out: &mut [u8]) {
+    assert_eq!(input.len(), out.len());
+    assert!(input.len().is_multiple_of(CHUNK_LEN));
+
+    let done = simd::mul_chunks::<false>(tables, input, out);
+    for (i, o) in input[done..]
+        .chunks_exact(CHUNK_LEN)
+        .zip(out[done..].chunks_exact_mut(CHUNK_LEN))
+    {
+        for j in 0..CHUNK_LEN / 2 {
+            let product = tables.mul([i[j], i[j + CHUNK_LEN / 2]]);
+            o[j] = product[0];
+            o[j + CHUNK_LEN / 2] = product[1];
+        }
+    }
+}
+
+/// Same as `mul_slice_xor`, for elements stored like in `mul_chunks`.
+pub(crate) fn mul_chunks_xor(tables: &SplitTables, input: &[u8], out: &mut [u8]) {
+    assert_eq!(input.len(), out.len());
+    assert!(input.len().is_multiple_of(CHUNK_LEN));
+
+    let done = simd::mul_chunks::<true>(tables, input, out);
+    for (i, o) in input[done..]
+        .chunks_exact(CHUNK_LEN)
+        .zip(out[done..].chunks_exact_mut(CHUNK_LEN))
+    {
+        for j in 0..CHUNK_LEN / 2 {
+            let product = tables.mul([i[j], i[j + CHUNK_LEN / 2]]);
+            o[j] ^= product[0];
+            o[j + CHUNK_LEN / 2] ^= product[1];
+        }
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+mod simd {
+    use super::SplitTables;
+
+    pub(super) fn mul_slice<const XOR: bool>(
+        _tables: &SplitTables,
+        _input: &[[u8; 2]],
+        _out: &mut [[u8; 2]],
+    ) -> usize {
+        0
+    }
+
+    pub(super) fn mul_chunks<const XOR: bool>(
+        _tables: &SplitTables,
+        _input: &[u8],
+        _out: &mut [u8],
+    ) -> usize {
+        0
+    }
+}
+
+// The routines return the number of elements or bytes processed, leaving the
+// rest to the pure Rust routines.
+#[cfg(target_arch = "x86_64")]
+mod simd {
+    use super::{SplitTables, CHUNK_LEN};
+    use core::arch::x86_64::*;
+
+    #[derive(Clone, Copy)]
+    enum Level {
+        Avx2,
+        Ssse3,
+        None,
+    }
+
+    fn level() -> Level {
+        #[cfg(feature = "std")]
+        let (avx2, ssse3) = (
+            std::is_x86_feature_detected!("avx2"),
+            std::is_x86_feature_detected!("ssse3"),
+        );
+        #[cfg(not(feature = "std"))]
+        let (avx2, ssse3) = (
+            cfg!(target_feature = "avx2"),
+            cfg!(target_feature = "ssse3"),
+        );
+
+        if avx2 {
+            Level::Avx2
+        } else if ssse3 {
+            Level::Ssse3
+        } else {
+            Level::None
+        }
+    }
+
+    pub(super) fn mul_slice<const XOR: bool>(
+        tables: &SplitTables,
+        input: &[[u8; 2]],
+        out: &mut [[u8; 2]],
+    ) -> usize {
+        unsafe {
+            match level() {
+                Level::Avx2 => mul_slice_avx2::<XOR>(tables, input, out),
+                Level::Ssse3 => mul_slice_ssse3::<XOR>(tables, input, out),
+                Level::None => 0,
+            }
+        }
+    }
+
+    pub(super) fn mul_chunks<const XOR: bool>(
+        tables: &SplitTables,
+        input: &[u8],
+        out: &mut [u8],
+    ) -> usize {
+        unsafe {
+            match level() {
+                Level::Avx2 => mul_chunks_avx2::<XOR>(tables, input, out),
+                Level::Ssse3 => mul_chunks_ssse3::<XOR>(tables, input, out),
+                Level::None => 0,
+            }
+        }
+    }
+
+    struct Ssse3Tables {
+        bytes: [[__m128i; 4]; 2],
+        mask: __m128i,
+    }
+
+    impl Ssse3Tables {
+        #[target_feature(enable = "ssse3")]
+        unsafe fn new(tables: &SplitTables) -> Ssse3Tables {
+            let load =
+                |b: usize, k: usize| _mm_loadu_si128(tables.bytes[b][k].as_ptr() as *const __m128i);
+            Ssse3Tables {
+                bytes: [
+                    [load(0, 0), load(0, 1), load(0, 2), load(0, 3)],
+                    [load(1, 0), load(1, 1), load(1, 2), load(1, 3)],
+                ],
+                mask: _mm_set1_epi8(0x0F),
+            }
+        }
+
+        // Looks up the products of the nibbles of the bytes 0 and the bytes 1
+        // of 16 elements.
+        #[target_feature(enable = "ssse3")]
+        #[inline]
+        unsafe fn mul(&self, byte0: __m128i, byte1: __m128i) -> (__m128i, __m128i) {
+            let nibbles = [
+                _mm_and_si128(byte0, self.mask),
+                _mm_and_si128(_mm_srli_epi64(byte0, 4), self.mask),
+                _mm_and_si128(byte1, self.mask),
+                _mm_and_si128(_mm_srli_epi64(byte1, 4), self.mask),
+            ];
+            let lookup = |t: &[__m128i; 4]| {
+                _mm_xor_si128(
+                    _mm_xor_si128(
+                        _mm_shuffle_epi8(t[0], nibbles[0]),
+                        _mm_shuffle_epi8(t[1], nibbles[1]),
+                    ),
+                    _mm_xor_si128(
+                        _mm_shuffle_epi8(t[2], nibbles[2]),
+                        _mm_shuffle_epi8(t[3], nibbles[3]),
+                    ),
+                )
+            };
+            (lookup(&self.bytes[0]), lookup(&self.bytes[1]))
+        }
+    }
+
+    #[target_feature(enable = "ssse3")]
+    #[inline]
+    unsafe fn store_ssse3<const XOR: bool>(ptr: *mut u8, mut value: __m128i) {
+        let ptr = ptr as *mut __m128i;
+        if XOR {
+            value = _mm_xor_si128(value, _mm_loadu_si128(ptr));
+        }
+        _mm_storeu_si128(ptr, value);
+    }
+
+    // Each iteration loads 16 elements as two vectors of interleaved bytes,
+    // gathers the bytes 0 and the bytes 1 into a vector each, looks up the
+    // products of their nibbles, and interleaves the bytes of the products
+    // again.
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn mul_slice_ssse3<const XOR: bool>(
+        tables: &SplitTables,
+        input: &[[u8; 2]],
+        out: &mut [[u8; 2]],
+    ) -> usize {
+        let len = input.len() / 16 * 16;
+        let tables = Ssse3Tables::new(tables);
+        let deinterleave = _mm_setr_epi8(0, 2, 4, 6, 8, 10, 12, 14, 1, 3, 5, 7, 9, 11, 13, 15);
+
+        let in_ptr = input.as_ptr() as *const u8;
+        let out_ptr = out.as_mut_ptr() as *mut u8;
+        let mut i = 0;
+        while i < len {
+            let a = _mm_loadu_si128(in_ptr.add(2 * i) as *const __m128i);
+            let b = _mm_loadu_si128(in_ptr.add(2 * i + 16) as *const __m128i);
+            let a = _mm_shuffle_epi8(a, deinterleave);
+            let b = _mm_shuffle_epi8(b, deinterleave);
+
+            let (product0, product1) =
+                tables.mul(_mm_unpacklo_epi64(a, b), _mm_unpackhi_epi64(a, b));
+
+            store_ssse3::<XOR>(out_ptr.add(2 * i), _mm_unpacklo_epi8(product0, product1));
+            store_ssse3::<XOR>(
+                out_ptr.add(2 * i + 16),
+                _mm_unpackhi_epi8(product0, product1),
+            );
+
+            i += 16;
+        }
+        len
+    }
+
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn mul_chunks_ssse3<const XOR: bool>(
+        tables: &SplitTables,
+        input: &[u8],
+        out: &mut [u8],
+    ) -> usize {
+        let len = input.len() / CHUNK_LEN * CHUNK_LEN;
+        let tables = Ssse3Tables::new(tables);
+
+        let in_ptr = input.as_ptr();
+        let out_ptr = out.as_mut_ptr();
+        let mut i = 0;
+        while i < len {
+            for half in [0, 16].iter() {
+                let byte0 = _mm_loadu_si128(in_ptr.add(i + half) as *const __m128i);
+                let byte1 = _mm_loadu_si128(in_ptr.add(i + half + 32) as *const __m128i);
+
+                let (product0, product1) = tables.mul(byte0, byte1);
+
+                store_ssse3::<XOR>(out_ptr.add(i + half), product0);
+                store_ssse3::<XOR>(out_ptr.add(i + half + 32), product1);
+            }
+
+            i += CHUNK_LEN;
+        }
+        len
+    }
+
+    struct Avx2Tables {
+        bytes: [[__m256i; 4]; 2],
+        mask: __m256i,
+    }
+
+    impl Avx2Tables {
+        #[target_feature(enable = "avx2")]
+        unsafe fn new(tables: &SplitTables) -> Avx2Tables {
+            let load = |b: usize, k: usize| {
+                _mm256_broadcastsi128_si256(_mm_loadu_si128(
+                    tables.bytes[b][k].as_ptr() as *const __m128i
+                ))
+            };
+            Avx2Tables {
+                bytes: [
+                    [load(0, 0), load(0, 1), load(0, 2), load(0, 3)],
+                    [load(1, 0), load(1, 1), load(1, 2), load(1, 3)],
+                ],
+                mask: _mm256_set1_epi8(0x0F),
+            }
+        }
+
+        // Looks up the products of the nibbles of the bytes 0 and the bytes 1
+        // of 32 elements.
+        #[target_feature(enable = "avx2")]
+        #[inline]
+        unsafe fn mul(&self, byte0: __m256i, byte1: __m256i) -> (__m256i, __m256i) {
+            let nibbles = [
+                _mm256_and_si256(byte0, self.mask),
+                _mm256_and_si256(_mm256_srli_epi64(byte0, 4), self.mask),
+                _mm256_and_si256(byte1, self.mask),
+                _mm256_and_si256(_mm256_srli_epi64(byte1, 4), self.mask),
+            ];
+            let lookup = |t: &[__m256i; 4]| {
+                _mm256_xor_si256(
+                    _mm256_xor_si256(
+                        _mm256_shuffle_epi8(t[0], nibbles[0]),
+                        _mm256_shuffle_epi8(t[1], nibbles[1]),
+                    ),
+                    _mm256_xor_si256(
+                        _mm256_shuffle_epi8(t[2], nibbles[2]),
+                        _mm256_shuffle_epi8(t[3], nibbles[3]),
+                    ),
+                )
+            };
+            (lookup(&self.bytes[0]), lookup(&self.bytes[1]))
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn store_avx2<const XOR: bool>(ptr: *mut u8, mut value: __m256i) {
+        let ptr = ptr as *mut __m256i;
+        if XOR {
+            value = _mm256_xor_si256(value, _mm256_loadu_si256(ptr));
+        }
+        _mm256_storeu_si256(ptr, value);
+    }
+
+    // Same as the SSSE3 routine with 32 elements per iteration. The shuffles
+    // and unpacks work within 128-bit lanes, which keeps the elements in order.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn mul_slice_avx2<const XOR: bool>(
+        tables: &SplitTables,
+        input: &[[u8; 2]],
+        out: &mut [[u8; 2]],
+    ) -> usize {
+        let len = input.len() / 32 * 32;
+        let tables = Avx2Tables::new(tables);
+        let deinterleave = _mm256_setr_epi8(
+            0, 2, 4, 6, 8, 10, 12, 14, 1, 3, 5, 7, 9, 11, 13, 15, 0, 2, 4, 6, 8, 10, 12, 14, 1, 3,
+            5, 7, 9, 11, 13, 15,
+        );
+
+        let in_ptr = input.as_ptr() as *const u8;
+        let out_ptr = out.as_mut_ptr() as *mut u8;
+        let mut i = 0;
+        while i < len {
+            let a = _mm256_loadu_si256(in_ptr.add(2 * i) as *const __m256i);
+            let b = _mm256_loadu_si256(in_ptr.add(2 * i + 32) as *const __m256i);
+            let a = _mm256_shuffle_epi8(a, deinterleave);
+            let b = _mm256_shuffle_epi8(b, deinterleave);
+
+            let (product0, product1) =
+                tables.mul(_mm256_unpacklo_epi64(a, b), _mm256_unpackhi_epi64(a, b));
+
+            store_avx2::<XOR>(out_ptr.add(2 * i), _mm256_unpacklo_epi8(product0, product1));
+            store_avx2::<XOR>(
+                out_ptr.add(2 * i + 32),
+                _mm256_unpackhi_epi8(product0, product1),
+            );
+
+            i += 32;
+        }
+        len
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn mul_chunks_avx2<const XOR: bool>(
+        tables: &SplitTables,
+        input: &[u8],
+        out: &mut [u8],
+    ) -> usize {
+        let len = input.len() / CHUNK_LEN * CHUNK_LEN;
+        let tables = Avx2Tables::new(tables);
+
+        let in_ptr = input.as_ptr();
+        let out_ptr = out.as_mut_ptr();
+        let mut i = 0;
+        while i < len {
+            let byte0 = _mm256_loadu_si256(in_ptr.add(i) as *const __m256i);
+            let byte1 = _mm256_loadu_si256(in_ptr.add(i + 32) as *const __m256i);
+
+            let (product0, product1) = tables.mul(byte0, byte1);
+
+            store_avx2::<XOR>(out_ptr.add(i), product0);
+            store_avx2::<XOR>(out_ptr.add(i + 32), product1);
+
+            i += CHUNK_LEN;
+        }
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::tests::fill_random;
+    use crate::Field;
+
+    fn check<F: Field<Elem = [u8; 2]>>() {
+        // lengths covering the AVX2, SSSE3 and pure Rust parts
+        for &len in [0, 1, 15, 16, 17, 31, 32, 33, 63, 64, 100, 1000].iter() {
+            let mut bytes = vec![0u8; 2 * len];
+            fill_random(&mut bytes);
+            let input: Vec<[u8; 2]> = bytes.chunks(2).map(|b| [b[0], b[1]]).collect();
+            fill_random(&mut bytes);
+            let mut out: Vec<[u8; 2]> = bytes.chunks(2).map(|b| [b[0], b[1]]).collect();
+
+            for &c in [[0, 0], [1, 0], [0, 1], [0x8E, 0x3C], [0xFF, 0xFF]].iter() {
+                let tables = SplitTables::new(|x| F::mul(c, x));
+
+                let mut expect = out.clone();
+                for (e, i) in expect.iter_mut().zip(input.iter()) {
+                    *e = F::add(*e, F::mul(c, *i));
+                }
+                mul_slice_xor(&tables, &input, &mut out);
+                assert_eq!(expect, out);
+
+                mul_slice(&tables, &input, &mut out);
+                for (o, i) in out.iter().zip(input.iter()) {
+                    assert_eq!(F::mul(c, *i), *o);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_tables_mul_chunks() {
+        type F = crate::galois_16_native::Field;
+
+        for &chunks in [0, 1, 2, 3, 10].iter() {
+            let mut input = vec![0u8; chunks * CHUNK_LEN];
+            fill_random(&mut input);
+            let mut out = vec![0u8; chunks * CHUNK_LEN];
+            fill_random(&mut out);
+
+            let c = [0x8E, 0x3C];
+            let tables = SplitTables::new(|x| F::mul(c, x));
+            let mut expect = out.clone();
+            mul_chunks_xor(&tables, &input, &mut out);
+            for chunk in 0..chunks {
+                for j in 0..CHUNK_LEN / 2 {
+                    let i = chunk * CHUNK_LEN + j;
+                    let product = F::mul(c, [input[i], input[i + CHUNK_LEN / 2]]);
+                    expect[i] ^= product[0];
+                    expect[i + CHUNK_LEN / 2] ^= product[1];
+                }
+            }
+            assert_eq!(expect, out);
+
+            mul_chunks(&tables, &input, &mut out);
+            mul_chunks_xor(&tables, &input, &mut out);
+            assert!(out.iter().all(|&b| b == 0));
+        }
+    }
+
+    #[cfg(all(feature = "std", target_arch = "x86_64"))]
+    #[test]
+    fn test_ssse3_same_as_pure_rust() {
+        if !std::is_x86_feature_detected!("ssse3") {
+            return;
+        }
+        type F = crate::galois_16_native::Field;
+        let tables = SplitTables::new(|x| F::mul([0x8E, 0x3C], x));
+
+        let mut bytes = vec![0u8; 640];
+        fill_random(&mut bytes);
+        let input: Vec<[u8; 2]> = bytes.chunks(2).map(|b| [b[0], b[1]]).collect();
+        let mut out = vec![[0u8; 2]; 320];
+        assert_eq!(320, unsafe {
+            simd::mul_slice_ssse3::<false>(&tables, &input, &mut out)
+        });
+        for (o, i) in out.iter().zip(input.iter()) {
+            assert_eq!(tables.mul(*i), *o);
+        }
+
+        let mut out = vec![0u8; 640];
+        assert_eq!(640, unsafe {
+            simd::mul_chunks_ssse3::<false>(&tables, &bytes, &mut out)
+        });
+        let mut expect = vec![0u8; 640];
+        mul_chunks(&tables, &bytes, &mut expect);
+        assert_eq!(expect, out);
+    }
+
+    #[test]
+    fn test_split_tables_mul_slice() {
+        check::<crate::galois_16::Field>();
+        check::<crate::galois_16_native::Field>();
+    }
+}
@@ -2,28 +2,215 @@
 
 include!(concat!(env!("OUT_DIR"), "/table.rs"));
 
-/// The field GF(2^8).
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::galois_8_simd::{self, NibbleTables};
+
+/// The polynomial of `Field`, `x^8 + x^4 + x^3 + x^2 + 1`.
+pub const DEFAULT_POLYNOMIAL: u16 = 0x11D;
+
+/// The polynomial of QR codes, which is the same as `DEFAULT_POLYNOMIAL`.
+pub const QR_CODE_POLYNOMIAL: u16 = 0x11D;
+
+/// The polynomial of AES, `x^8 + x^4 + x^3 + x + 1`.
+pub const AES_POLYNOMIAL: u16 = 0x11B;
+
+/// The polynomial of Data Matrix codes, `x^8 + x^5 + x^3 + x^2 + 1`.
+pub const DATA_MATRIX_POLYNOMIAL: u16 = 0x12D;
+
+/// The polynomial of the CCSDS Reed-Solomon code, `x^8 + x^7 + x^2 + x + 1`.
+pub const CCSDS_POLYNOMIAL: u16 = 0x187;
+
+/// The field GF(2^8) with the reducing polynomial `POLY`, including the `x^8`
+/// term, such as `0x11B` for `x^8 + x^4 + x^3 + x + 1`.
+///
+/// The log and exp tables of the field are built at compile time, which fails
+/// if `POLY` is not an irreducible polynomial of degree 8. `POLY` does not
+/// need to be primitive: the tables use the smallest generator of the field,
+/// which is `3` for `AES_POLYNOMIAL`.
+///
+/// Slices are multiplied with SSSE3, AVX2, AVX-512BW or NEON when the CPU
+/// supports them. `Gf8<DEFAULT_POLYNOMIAL>`, aliased as `Field`, uses the
+/// tables of the build script, and the C routines instead with the
+/// `simd-accel` feature.
+///
+/// ```
+/// # use reed_solomon_erasure::galois_8::{Gf8, AES_POLYNOMIAL};
+/// type Aes = reed_solomon_erasure::ReedSolomon<Gf8<AES_POLYNOMIAL>>;
+///
+/// let r = Aes::new(3, 2).unwrap();
+/// let mut shards = vec![vec![0x57u8, 1], vec![0x83, 2], vec![0, 3], vec![0; 2], vec![0; 2]];
+/// r.encode(&mut shards).unwrap();
+/// assert!(r.verify(&shards).unwrap());
+/// ```
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
-pub struct Field;
+pub struct Gf8<const POLY: u16>;
 
-impl crate::Field for Field {
+/// The field GF(2^8) with `DEFAULT_POLYNOMIAL`.
+pub type Field = Gf8<DEFAULT_POLYNOMIAL>;
+
+struct PolyTables {
+    log: [u8; 256],
+    exp: [u8; 510],
+}
+
+// Multiplication by shifting and adding, only used to build the tables.
+const fn poly_mul(mut a: u8, mut b: u8, poly: u16) -> u8 {
+    let mut result = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            result ^= a;
+        }
+        a = if a & 0x80 != 0 {
+            (((a as u16) << 1) ^ poly) as u8
+        } else {
+            a << 1
+        };
+        b >>= 1;
+    }
+    result
+}
+
+const fn poly_tables(poly: u16) -> PolyTables {
+    assert!(poly >> 8 == 1, "polynomial must be of degree 8");
+
+    // the multiplicative group of a field is cyclic, the powers of a
+    // generator go through all 255 non-zero elements
+    let mut generator = 2;
+    loop {
+        let mut x = generator;
+        let mut order = 1;
+        while x != 1 && order < 256 {
+            x = poly_mul(x, generator, poly);
+            order += 1;
+        }
+        if x == 1 && order == 255 {
+            break;
+        }
+        assert!(generator < 255, "polynomial is not irreducible");
+        generator += 1;
+    }
+
+    let mut tables = PolyTables {
+        log: [0; 256],
+        exp: [0; 510],
+    };
+    let mut x = 1;
+    let mut i = 0;
+    while i < 255 {
+        tables.exp[i] = x;
+        tables.exp[i + 255] = x;
+        tables.log[x as usize] = i as u8;
+        x = poly_mul(x, generator, poly);
+        i += 1;
+    }
+    tables
+}
+
+// The tables of the SIMD routines for the products with every element.
+const fn nibble_tables(poly: u16) -> [NibbleTables; 256] {
+    const EMPTY: NibbleTables = NibbleTables::new([0; 16], [0; 16]);
+    let mut tables = [EMPTY; 256];
+    let mut c = 0;
+    while c < 256 {
+        let mut low = [0; 16];
+        let mut high = [0; 16];
+        let mut i = 0;
+        while i < 16 {
+            low[i] = poly_mul(c as u8, i as u8, poly);
+            high[i] = poly_mul(c as u8, (i as u8) << 4, poly);
+            i += 1;
+        }
+        tables[c] = NibbleTables::new(low, high);
+        c += 1;
+    }
+    tables
+}
+
+impl<const POLY: u16> Gf8<POLY> {
+    const TABLES: &'static PolyTables = &poly_tables(POLY);
+
+    const IS_DEFAULT: bool = POLY == DEFAULT_POLYNOMIAL;
+
+    const NIBBLE_TABLES: &'static [NibbleTables; 256] = &nibble_tables(POLY);
+
+    fn table_mul(a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            0
+        } else {
+            let log_a = Self::TABLES.log[a as usize] as usize;
+            let log_b = Self::TABLES.log[b as usize] as usize;
+            Self::TABLES.exp[log_a + log_b]
+        }
+    }
+
+    fn table_div(a: u8, b: u8) -> u8 {
+        if a == 0 {
+            0
+        } else if b == 0 {
+            panic!("Divisor is 0")
+        } else {
+            let log_a = Self::TABLES.log[a as usize] as usize;
+            let log_b = Self::TABLES.log[b as usize] as usize;
+            Self::TABLES.exp[log_a + 255 - log_b]
+        }
+    }
+
+    fn table_exp(a: u8, n: usize) -> u8 {
+        if n == 0 {
+            1
+        } else if a == 0 {
+            0
+        } else {
+            let log_a = Self::TABLES.log[a as usize] as usize;
+            Self::TABLES.exp[log_a * (n % 255) % 255]
+        }
+    }
+
+    fn mul_row(c: u8) -> [u8; 256] {
+        let mut row = [0; 256];
+        for (b, product) in row.iter_mut().enumerate() {
+            *product = Self::table_mul(c, b as u8);
+        }
+        row
+    }
+}
+
+impl<const POLY: u16> crate::Field for Gf8<POLY> {
     const ORDER: usize = 256;
     type Elem = u8;
 
+    const WRITES_OUTPUTS_FIRST: bool = true;
+
     fn add(a: u8, b: u8) -> u8 {
         add(a, b)
     }
 
     fn mul(a: u8, b: u8) -> u8 {
-        mul(a, b)
+        if Self::IS_DEFAULT {
+            mul(a, b)
+        } else {
+            Self::table_mul(a, b)
+        }
     }
 
     fn div(a: u8, b: u8) -> u8 {
-        div(a, b)
+        if Self::IS_DEFAULT {
+            div(a, b)
+        } else {
+            Self::table_div(a, b)
+        }
     }
 
     fn exp(elem: u8, n: usize) -> u8 {
-        exp(elem, n)
+        if Self::IS_DEFAULT {
+            exp(elem, n)
+        } else {
+            Self::table_exp(elem, n)
+        }
     }
 
     fn zero() -> u8 {
@@ -39,15 +226,89 @@ impl crate::Field for Field {
     }
 
     fn mul_slice(c: u8, input: &[u8], out: &mut [u8]) {
-        mul_slice(c, input, out)
+        if Self::IS_DEFAULT {
+            mul_slice(c, input, out)
+        } else {
+            let row = Self::mul_row(c);
+            let done =
+                galois_8_simd::mul_slice::<false>(&Self::NIBBLE_TABLES[c as usize], input, out);
+            for (o, i) in out[done..].iter_mut().zip(&input[done..]) {
+                *o = row[*i as usize];
+            }
+        }
     }
 
     fn mul_slice_add(c: u8, input: &[u8], out: &mut [u8]) {
-        mul_slice_xor(c, input, out)
+        if Self::IS_DEFAULT {
+            mul_slice_xor(c, input, out)
+        } else {
+            let row = Self::mul_row(c);
+            let done =
+                galois_8_simd::mul_slice::<true>(&Self::NIBBLE_TABLES[c as usize], input, out);
+            for (o, i) in out[done..].iter_mut().zip(&input[done..]) {
+                *o ^= row[*i as usize];
+            }
+        }
+    }
+
+    const TABLE_LEN: usize = galois_8_simd::TABLE_LEN;
+
+    fn prepare(c: u8, table: &mut [u8]) {
+        Self::NIBBLE_TABLES[c as usize].write_to(table);
+    }
+
+    fn dot_prod(matrix_rows: &[&[u8]], inputs: &[&[u8]], outputs: &mut [&mut [u8]]) {
+        let tables: Vec<Vec<u8>> = matrix_rows[..outputs.len()]
+            .iter()
+            .map(|row| {
+                let mut tables = vec![0; inputs.len() * Self::TABLE_LEN];
+                for (&c, table) in row[..inputs.len()]
+                    .iter()
+                    .zip(tables.chunks_exact_mut(Self::TABLE_LEN))
+                {
+                    Self::prepare(c, table);
+                }
+                tables
+            })
+            .collect();
+        let tables: Vec<&[u8]> = tables.iter().map(|row| row.as_slice()).collect();
+        Self::dot_prod_prepared(matrix_rows, &tables, inputs, outputs)
+    }
+
+    fn dot_prod_prepared(
+        matrix_rows: &[&[u8]],
+        tables: &[&[u8]],
+        inputs: &[&[u8]],
+        outputs: &mut [&mut [u8]],
+    ) {
+        let done = galois_8_simd::dot_prod(tables, inputs, outputs);
+        // the kernels may leave no tail, e.g. for lengths multiple of 64
+        if outputs.iter().all(|output| output.len() == done) {
+            return;
+        }
+
+        for (i_input, input) in inputs.iter().enumerate() {
+            for (row, output) in matrix_rows.iter().zip(outputs.iter_mut()) {
+                let (input, output) = (&input[done..], &mut output[done..]);
+                if i_input == 0 {
+                    Self::mul_slice(row[i_input], input, output);
+                } else {
+                    Self::mul_slice_add(row[i_input], input, output);
+                }
+            }
+        }
+    }
+
+    fn sum_slices(inputs: &[&[u8]], out: &mut [u8]) {
+        xor_slices(inputs, out)
+    }
+
+    fn add_slice(input: &[u8], out: &mut [u8]) {
+        slice_xor(input, out)
     }
 }
 
-/// Type alias of ReedSolomon over GF(2^8).
+/// Type alias of ReedSolomon over GF(2^8) with `DEFAULT_POLYNOMIAL`.
 pub type ReedSolomon = crate::ReedSolomon<Field>;
 
 /// Type alias of ShardByShard over GF(2^8).
@@ -121,7 +382,9 @@ macro_rules! return_if_empty {
     not(any(target_os = "android", target_os = "ios"))
 )))]
 pub fn mul_slice(c: u8, input: &[u8], out: &mut [u8]) {
-    mul_slice_pure_rust(c, input, out);
+    let tables = &Field::NIBBLE_TABLES[c as usize];
+    let done = galois_8_simd::mul_slice::<false>(tables, input, out);
+    mul_slice_pure_rust(c, &input[done..], &mut out[done..]);
 }
 
 #[cfg(not(all(
@@ -131,7 +394,9 @@ pub fn mul_slice(c: u8, input: &[u8], out: &mut [u8]) {
     not(any(target_os = "android", target_os = "ios"))
 )))]
 pub fn mul_slice_xor(c: u8, input: &[u8], out: &mut [u8]) {
-    mul_slice_xor_pure_rust(c, input, out);
+    let tables = &Field::NIBBLE_TABLES[c as usize];
+    let done = galois_8_simd::mul_slice::<true>(tables, input, out);
+    mul_slice_xor_pure_rust(c, &input[done..], &mut out[done..]);
 }
 
 fn mul_slice_pure_rust(c: u8, input: &[u8], out: &mut [u8]) {
@@ -218,8 +483,16 @@ fn mul_slice_xor_pure_rust(c: u8, input: &[u8], out: &mut [u8]) {
      */
 }
 
-#[cfg(test)]
-fn slice_xor(input: &[u8], out: &mut [u8]) {
+/// Overwrites `out` with the exclusive or of `inputs`.
+pub(crate) fn xor_slices(inputs: &[&[u8]], out: &mut [u8]) {
+    let done = galois_8_simd::xor_slices(inputs, out);
+    for (i, o) in out.iter_mut().enumerate().skip(done) {
+        *o = inputs.iter().fold(0, |acc, input| acc ^ input[i]);
+    }
+}
+
+/// XOR a slice into another. Writes into the output slice.
+pub fn slice_xor(input: &[u8], out: &mut [u8]) {
     assert_eq!(input.len(), out.len());
 
     let len: isize = input.len() as isize;
@@ -293,6 +566,7 @@ pub fn mul_slice(c: u8, input: &[u8], out: &mut [u8]) {
     let high: *const u8 = &MUL_TABLE_HIGH[c as usize][0];
 
     assert_eq!(input.len(), out.len());
+    return_if_empty!(input.len());
 
     let input_ptr: *const u8 = &input[0];
     let out_ptr: *mut u8 = &mut out[0];
@@ -315,6 +589,7 @@ pub fn mul_slice_xor(c: u8, input: &[u8], out: &mut [u8]) {
     let high: *const u8 = &MUL_TABLE_HIGH[c as usize][0];
 
     assert_eq!(input.len(), out.len());
+    return_if_empty!(input.len());
 
     let input_ptr: *const u8 = &input[0];
     let out_ptr: *mut u8 = &mut out[0];
@@ -334,6 +609,8 @@ mod tests {
 
     use super::*;
     use crate::tests::fill_random;
+    use crate::Field as _;
+    use alloc::vec::Vec;
     use rand;
 
     static BACKBLAZE_LOG_TABLE: [u8; 256] = [
@@ -578,6 +855,12 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_mul_slice_empty() {
+        mul_slice(0x8E, &[], &mut []);
+        mul_slice_xor(0x8E, &[], &mut []);
+    }
+
     #[test]
     fn test_div_a_is_0() {
         assert_eq!(0, div(0, 100));
@@ -618,4 +901,135 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_gf8_default_same_as_tables() {
+        type Default = Gf8<DEFAULT_POLYNOMIAL>;
+        for a in 0..=255 {
+            assert_eq!(LOG_TABLE[a as usize], Default::TABLES.log[a as usize]);
+            for b in 0..=255 {
+                assert_eq!(mul(a, b), Default::table_mul(a, b));
+                if b != 0 {
+                    assert_eq!(div(a, b), Default::table_div(a, b));
+                }
+            }
+            for n in 0..600 {
+                assert_eq!(exp(a, n), Default::table_exp(a, n));
+            }
+        }
+    }
+
+    #[test]
+    fn test_gf8_aes() {
+        type Aes = Gf8<AES_POLYNOMIAL>;
+        // FIPS-197, section 4.2 and the S-box of 0x53
+        assert_eq!(0xC1, Aes::mul(0x57, 0x83));
+        assert_eq!(0xFE, Aes::mul(0x57, 0x13));
+        assert_eq!(0xCA, Aes::div(1, 0x53));
+        assert_eq!(0x53, Aes::div(1, 0xCA));
+        // 2 is not a generator with this polynomial
+        assert_eq!(1, Aes::exp(2, 51));
+        assert_eq!(3, Aes::TABLES.exp[1]);
+    }
+
+    #[test]
+    fn test_gf8_polynomials() {
+        fn check<F: crate::Field<Elem = u8>>() {
+            for a in 1..=255u8 {
+                assert_eq!(1, F::div(a, a));
+                assert_eq!(a, F::exp(a, 256));
+                for b in 1..=255u8 {
+                    let product = F::mul(a, b);
+                    assert_eq!(product, F::mul(b, a));
+                    assert_eq!(a, F::div(product, b));
+                }
+            }
+
+            let mut input = vec![0u8; 1000];
+            fill_random(&mut input);
+            let mut output = vec![0u8; 1000];
+            fill_random(&mut output);
+            let mut expect = output.clone();
+            F::mul_slice_add(0x8E, &input, &mut output);
+            for (e, i) in expect.iter_mut().zip(input.iter()) {
+                *e ^= F::mul(0x8E, *i);
+            }
+            assert_eq!(expect, output);
+            F::mul_slice(0x8E, &input, &mut output);
+            for (o, i) in output.iter().zip(input.iter()) {
+                assert_eq!(*o, F::mul(0x8E, *i));
+            }
+        }
+
+        check::<Field>();
+        check::<Gf8<AES_POLYNOMIAL>>();
+        check::<Gf8<DATA_MATRIX_POLYNOMIAL>>();
+        check::<Gf8<CCSDS_POLYNOMIAL>>();
+
+        assert_eq!(
+            [2, 2, 2, 3],
+            [
+                Gf8::<QR_CODE_POLYNOMIAL>::TABLES.exp[1],
+                Gf8::<DATA_MATRIX_POLYNOMIAL>::TABLES.exp[1],
+                Gf8::<CCSDS_POLYNOMIAL>::TABLES.exp[1],
+                Gf8::<AES_POLYNOMIAL>::TABLES.exp[1]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dot_prod_same_as_mul_slice() {
+        // lengths which the SIMD kernels code entirely, and ones with a tail
+        for &len in [1, 64, 100, 4096].iter() {
+            let mut inputs = vec![vec![0u8; len]; 5];
+            for input in inputs.iter_mut() {
+                fill_random(input);
+            }
+            let rows: Vec<Vec<u8>> = (0..3)
+                .map(|_| (0..5).map(|_| rand::random::<u8>()).collect())
+                .collect();
+
+            let mut expect = vec![vec![0u8; len]; 3];
+            for (row, output) in rows.iter().zip(expect.iter_mut()) {
+                for (&c, input) in row.iter().zip(inputs.iter()) {
+                    mul_slice_xor(c, input, output);
+                }
+            }
+
+            let row_refs: Vec<&[u8]> = rows.iter().map(|x| x.as_slice()).collect();
+            let input_refs: Vec<&[u8]> = inputs.iter().map(|x| x.as_slice()).collect();
+            let mut outputs = vec![vec![0u8; len]; 3];
+            for output in outputs.iter_mut() {
+                fill_random(output);
+            }
+            let mut output_refs: Vec<&mut [u8]> =
+                outputs.iter_mut().map(|x| x.as_mut_slice()).collect();
+            Field::dot_prod(&row_refs, &input_refs, &mut output_refs);
+
+            assert_eq!(expect, outputs);
+        }
+    }
+
+    #[test]
+    fn test_gf8_codec() {
+        let mut shards = vec![vec![0u8; 100]; 7];
+        for shard in shards[..4].iter_mut() {
+            fill_random(shard);
+        }
+
+        let mut aes_shards = shards.clone();
+        let aes = crate::ReedSolomon::<Gf8<AES_POLYNOMIAL>>::new(4, 3).unwrap();
+        aes.encode(&mut aes_shards).unwrap();
+        ReedSolomon::new(4, 3).unwrap().encode(&mut shards).unwrap();
+        assert_eq!(shards[..4], aes_shards[..4]);
+        assert_ne!(shards[4..], aes_shards[4..]);
+
+        let mut some: Vec<_> = aes_shards.iter().cloned().map(Some).collect();
+        some[1] = None;
+        some[2] = None;
+        some[5] = None;
+        aes.reconstruct(&mut some).unwrap();
+        let some: Vec<_> = some.into_iter().map(Option::unwrap).collect();
+        assert_eq!(aes_shards, some);
+    }
 }
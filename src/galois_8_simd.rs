@@ -0,0 +1,864 @@
+//! SIMD routines multiplying slices of GF(2^8) elements.
+//!
+//! A product `c * x` is the exclusive or of the products of `c` with the low
+//! and the high nibble of `x`, which are looked up in two tables of 16 bytes
+//! with byte shuffles. The routines are written with `core::arch` and the
+//! instruction set is chosen at runtime with the `std` feature, or at
+//! compile time from the target features without it.
+
+/// The products of an element with the 16 low nibbles and the 16 high
+/// nibbles.
+#[repr(C)]
+pub(crate) struct NibbleTables {
+    low: [u8; 16],
+    high: [u8; 16],
+}
+
+impl NibbleTables {
+    /// Takes the products of an element with the low nibbles and with the
+    /// high nibbles.
+    pub(crate) const fn new(low: [u8; 16], high: [u8; 16]) -> NibbleTables {
+        NibbleTables { low, high }
+    }
+
+    /// Writes the low and the high tables into the `TABLE_LEN` bytes of
+    /// `table`, in the layout read by `dot_prod`.
+    pub(crate) fn write_to(&self, table: &mut [u8]) {
+        table[..16].copy_from_slice(&self.low);
+        table[16..TABLE_LEN].copy_from_slice(&self.high);
+    }
+}
+
+/// The bytes of the tables of an element in the rows of `dot_prod`.
+pub(crate) const TABLE_LEN: usize = 32;
+
+/// The number of outputs of a pass of `dot_prod`, whose accumulators are
+/// kept in registers.
+const GROUP: usize = 4;
+
+type DotProdKernel = unsafe fn(&[&[u8]], &[&[u8]], &mut [&mut [u8]]) -> usize;
+
+// Runs `kernels[n - 1]` on each group of `n` outputs, with `n <= GROUP`.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+unsafe fn dot_prod_groups(
+    kernels: &[DotProdKernel; GROUP],
+    tables: &[&[u8]],
+    inputs: &[&[u8]],
+    outputs: &mut [&mut [u8]],
+) -> usize {
+    let mut done = 0;
+    for (i_group, outputs) in outputs.chunks_mut(GROUP).enumerate() {
+        let tables = &tables[i_group * GROUP..];
+        done = kernels[outputs.len() - 1](tables, inputs, outputs);
+    }
+    done
+}
+
+/// Multiplies a prefix of `input` and writes or, with `XOR`, adds the
+/// products to `out`. Returns the length of the prefix, leaving the rest to
+/// the pure Rust routines.
+pub(crate) fn mul_slice<const XOR: bool>(
+    tables: &NibbleTables,
+    input: &[u8],
+    out: &mut [u8],
+) -> usize {
+    assert_eq!(input.len(), out.len());
+    arch::mul_slice::<XOR>(tables, input, out)
+}
+
+/// Overwrites each output with the sum of the products of the inputs, in
+/// passes of several outputs which load each input once. `tables[j]` holds
+/// the tables of output `j` and every input, as written by `write_to`, back
+/// to back. Returns the length of the prefix computed, leaving the rest to
+/// the pure Rust routines.
+pub(crate) fn dot_prod(tables: &[&[u8]], inputs: &[&[u8]], outputs: &mut [&mut [u8]]) -> usize {
+    assert!(tables.len() >= outputs.len());
+    assert!(tables[..outputs.len()]
+        .iter()
+        .all(|row| row.len() == inputs.len() * TABLE_LEN));
+    let len = match outputs.first() {
+        Some(output) => output.len(),
+        None => return 0,
+    };
+    assert!(inputs.iter().all(|input| input.len() == len));
+    assert!(outputs.iter().all(|output| output.len() == len));
+    if inputs.is_empty() {
+        return 0;
+    }
+    arch::dot_prod(tables, inputs, outputs)
+}
+
+/// Overwrites a prefix of `out` with the exclusive or of `inputs`, loading
+/// each input once. Returns the length of the prefix, leaving the rest to the
+/// pure Rust routines.
+pub(crate) fn xor_slices(inputs: &[&[u8]], out: &mut [u8]) -> usize {
+    assert!(inputs.iter().all(|input| input.len() == out.len()));
+    arch::xor_slices(inputs, out)
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod arch {
+    use super::NibbleTables;
+
+    pub(super) fn mul_slice<const XOR: bool>(
+        _tables: &NibbleTables,
+        _input: &[u8],
+        _out: &mut [u8],
+    ) -> usize {
+        0
+    }
+
+    pub(super) fn dot_prod(
+        _tables: &[&[u8]],
+        _inputs: &[&[u8]],
+        _outputs: &mut [&mut [u8]],
+    ) -> usize {
+        0
+    }
+
+    pub(super) fn xor_slices(_inputs: &[&[u8]], _out: &mut [u8]) -> usize {
+        0
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    use super::{dot_prod_groups, DotProdKernel, NibbleTables, GROUP, TABLE_LEN};
+    use core::arch::x86_64::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub(super) enum Level {
+        Avx512bw,
+        Avx2,
+        Ssse3,
+        None,
+    }
+
+    pub(super) fn level() -> Level {
+        #[cfg(feature = "std")]
+        let (avx512bw, avx2, ssse3) = (
+            std::is_x86_feature_detected!("avx512f") && std::is_x86_feature_detected!("avx512bw"),
+            std::is_x86_feature_detected!("avx2"),
+            std::is_x86_feature_detected!("ssse3"),
+        );
+        #[cfg(not(feature = "std"))]
+        let (avx512bw, avx2, ssse3) = (
+            cfg!(all(target_feature = "avx512f", target_feature = "avx512bw")),
+            cfg!(target_feature = "avx2"),
+            cfg!(target_feature = "ssse3"),
+        );
+
+        if avx512bw {
+            Level::Avx512bw
+        } else if avx2 {
+            Level::Avx2
+        } else if ssse3 {
+            Level::Ssse3
+        } else {
+            Level::None
+        }
+    }
+
+    pub(super) fn mul_slice<const XOR: bool>(
+        tables: &NibbleTables,
+        input: &[u8],
+        out: &mut [u8],
+    ) -> usize {
+        unsafe { mul_slice_at::<XOR>(level(), tables, input, out) }
+    }
+
+    /// Multiplies with the instructions of `level`, which the CPU must
+    /// support.
+    pub(super) unsafe fn mul_slice_at<const XOR: bool>(
+        level: Level,
+        tables: &NibbleTables,
+        input: &[u8],
+        out: &mut [u8],
+    ) -> usize {
+        match level {
+            Level::Avx512bw => mul_slice_avx512bw::<XOR>(tables, input, out),
+            Level::Avx2 => mul_slice_avx2::<XOR>(tables, input, out),
+            Level::Ssse3 => mul_slice_ssse3::<XOR>(tables, input, out),
+            Level::None => 0,
+        }
+    }
+
+    pub(super) fn dot_prod(tables: &[&[u8]], inputs: &[&[u8]], outputs: &mut [&mut [u8]]) -> usize {
+        unsafe { dot_prod_at(level(), tables, inputs, outputs) }
+    }
+
+    /// Computes the dot products with the instructions of `level`, which the
+    /// CPU must support.
+    pub(super) unsafe fn dot_prod_at(
+        level: Level,
+        tables: &[&[u8]],
+        inputs: &[&[u8]],
+        outputs: &mut [&mut [u8]],
+    ) -> usize {
+        let kernels: [DotProdKernel; GROUP] = match level {
+            Level::Avx512bw => [
+                dot_prod_avx512bw::<1>,
+                dot_prod_avx512bw::<2>,
+                dot_prod_avx512bw::<3>,
+                dot_prod_avx512bw::<4>,
+            ],
+            Level::Avx2 => [
+                dot_prod_avx2::<1>,
+                dot_prod_avx2::<2>,
+                dot_prod_avx2::<3>,
+                dot_prod_avx2::<4>,
+            ],
+            Level::Ssse3 => [
+                dot_prod_ssse3::<1>,
+                dot_prod_ssse3::<2>,
+                dot_prod_ssse3::<3>,
+                dot_prod_ssse3::<4>,
+            ],
+            Level::None => return 0,
+        };
+        dot_prod_groups(&kernels, tables, inputs, outputs)
+    }
+
+    pub(super) fn xor_slices(inputs: &[&[u8]], out: &mut [u8]) -> usize {
+        unsafe { xor_slices_at(level(), inputs, out) }
+    }
+
+    /// Adds the slices with the instructions of `level`, which the CPU must
+    /// support. SSE2 is part of x86_64, so there is no level without SIMD.
+    pub(super) unsafe fn xor_slices_at(level: Level, inputs: &[&[u8]], out: &mut [u8]) -> usize {
+        match level {
+            Level::Avx512bw => xor_slices_avx512f(inputs, out),
+            Level::Avx2 => xor_slices_avx2(inputs, out),
+            Level::Ssse3 | Level::None => xor_slices_sse2(inputs, out),
+        }
+    }
+
+    #[target_feature(enable = "ssse3")]
+    unsafe fn mul_slice_ssse3<const XOR: bool>(
+        tables: &NibbleTables,
+        input: &[u8],
+        out: &mut [u8],
+    ) -> usize {
+        let len = input.len() / 16 * 16;
+        let low = _mm_loadu_si128(tables.low.as_ptr() as *const __m128i);
+        let high = _mm_loadu_si128(tables.high.as_ptr() as *const __m128i);
+        let mask = _mm_set1_epi8(0x0F);
+
+        let in_ptr = input.as_ptr();
+        let out_ptr = out.as_mut_ptr();
+        let mut i = 0;
+        while i < len {
+            let x = _mm_loadu_si128(in_ptr.add(i) as *const __m128i);
+            let mut product = _mm_xor_si128(
+                _mm_shuffle_epi8(low, _mm_and_si128(x, mask)),
+                _mm_shuffle_epi8(high, _mm_and_si128(_mm_srli_epi64(x, 4), mask)),
+            );
+            let ptr = out_ptr.add(i) as *mut __m128i;
+            if XOR {
+                product = _mm_xor_si128(product, _mm_loadu_si128(ptr));
+            }
+            _mm_storeu_si128(ptr, product);
+
+            i += 16;
+        }
+        len
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn mul_slice_avx2<const XOR: bool>(
+        tables: &NibbleTables,
+        input: &[u8],
+        out: &mut [u8],
+    ) -> usize {
+        let len = input.len() / 32 * 32;
+        let low =
+            _mm256_broadcastsi128_si256(_mm_loadu_si128(tables.low.as_ptr() as *const __m128i));
+        let high =
+            _mm256_broadcastsi128_si256(_mm_loadu_si128(tables.high.as_ptr() as *const __m128i));
+        let mask = _mm256_set1_epi8(0x0F);
+
+        let in_ptr = input.as_ptr();
+        let out_ptr = out.as_mut_ptr();
+        let mut i = 0;
+        while i < len {
+            let x = _mm256_loadu_si256(in_ptr.add(i) as *const __m256i);
+            let mut product = _mm256_xor_si256(
+                _mm256_shuffle_epi8(low, _mm256_and_si256(x, mask)),
+                _mm256_shuffle_epi8(high, _mm256_and_si256(_mm256_srli_epi64(x, 4), mask)),
+            );
+            let ptr = out_ptr.add(i) as *mut __m256i;
+            if XOR {
+                product = _mm256_xor_si256(product, _mm256_loadu_si256(ptr));
+            }
+            _mm256_storeu_si256(ptr, product);
+
+            i += 32;
+        }
+        len
+    }
+
+    #[target_feature(enable = "avx512f,avx512bw")]
+    unsafe fn mul_slice_avx512bw<const XOR: bool>(
+        tables: &NibbleTables,
+        input: &[u8],
+        out: &mut [u8],
+    ) -> usize {
+        let len = input.len() / 64 * 64;
+        let low = _mm512_broadcast_i32x4(_mm_loadu_si128(tables.low.as_ptr() as *const __m128i));
+        let high = _mm512_broadcast_i32x4(_mm_loadu_si128(tables.high.as_ptr() as *const __m128i));
+        let mask = _mm512_set1_epi8(0x0F);
+
+        let in_ptr = input.as_ptr();
+        let out_ptr = out.as_mut_ptr();
+        let mut i = 0;
+        while i < len {
+            let x = _mm512_loadu_si512(in_ptr.add(i) as *const __m512i);
+            let mut product = _mm512_xor_si512(
+                _mm512_shuffle_epi8(low, _mm512_and_si512(x, mask)),
+                _mm512_shuffle_epi8(high, _mm512_and_si512(_mm512_srli_epi64(x, 4), mask)),
+            );
+            let ptr = out_ptr.add(i) as *mut __m512i;
+            if XOR {
+                product = _mm512_xor_si512(product, _mm512_loadu_si512(ptr));
+            }
+            _mm512_storeu_si512(ptr, product);
+
+            i += 64;
+        }
+        len
+    }
+
+    // Each iteration accumulates the products of 16 bytes of every input
+    // for the `N` outputs in registers, then stores them.
+    #[target_feature(enable = "ssse3")]
+    unsafe fn dot_prod_ssse3<const N: usize>(
+        tables: &[&[u8]],
+        inputs: &[&[u8]],
+        outputs: &mut [&mut [u8]],
+    ) -> usize {
+        let len = outputs[0].len() / 16 * 16;
+        let mask = _mm_set1_epi8(0x0F);
+
+        let mut pos = 0;
+        while pos < len {
+            let mut acc = [_mm_setzero_si128(); N];
+            for (i, input) in inputs.iter().enumerate() {
+                let x = _mm_loadu_si128(input.as_ptr().add(pos) as *const __m128i);
+                let low_nibbles = _mm_and_si128(x, mask);
+                let high_nibbles = _mm_and_si128(_mm_srli_epi64(x, 4), mask);
+                for (j, acc) in acc.iter_mut().enumerate() {
+                    let t = tables[j].as_ptr().add(i * TABLE_LEN);
+                    let low = _mm_loadu_si128(t as *const __m128i);
+                    let high = _mm_loadu_si128(t.add(16) as *const __m128i);
+                    *acc = _mm_xor_si128(
+                        *acc,
+                        _mm_xor_si128(
+                            _mm_shuffle_epi8(low, low_nibbles),
+                            _mm_shuffle_epi8(high, high_nibbles),
+                        ),
+                    );
+                }
+            }
+            for (output, acc) in outputs.iter_mut().zip(acc.iter()) {
+                _mm_storeu_si128(output.as_mut_ptr().add(pos) as *mut __m128i, *acc);
+            }
+
+            pos += 16;
+        }
+        len
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn dot_prod_avx2<const N: usize>(
+        tables: &[&[u8]],
+        inputs: &[&[u8]],
+        outputs: &mut [&mut [u8]],
+    ) -> usize {
+        let len = outputs[0].len() / 32 * 32;
+        let mask = _mm256_set1_epi8(0x0F);
+
+        let mut pos = 0;
+        while pos < len {
+            let mut acc = [_mm256_setzero_si256(); N];
+            for (i, input) in inputs.iter().enumerate() {
+                let x = _mm256_loadu_si256(input.as_ptr().add(pos) as *const __m256i);
+                let low_nibbles = _mm256_and_si256(x, mask);
+                let high_nibbles = _mm256_and_si256(_mm256_srli_epi64(x, 4), mask);
+                for (j, acc) in acc.iter_mut().enumerate() {
+                    let t = tables[j].as_ptr().add(i * TABLE_LEN);
+                    let low = _mm256_broadcastsi128_si256(_mm_loadu_si128(t as *const __m128i));
+                    let high =
+                        _mm256_broadcastsi128_si256(_mm_loadu_si128(t.add(16) as *const __m128i));
+                    *acc = _mm256_xor_si256(
+                        *acc,
+                        _mm256_xor_si256(
+                            _mm256_shuffle_epi8(low, low_nibbles),
+                            _mm256_shuffle_epi8(high, high_nibbles),
+                        ),
+                    );
+                }
+            }
+            for (output, acc) in outputs.iter_mut().zip(acc.iter()) {
+                _mm256_storeu_si256(output.as_mut_ptr().add(pos) as *mut __m256i, *acc);
+            }
+
+            pos += 32;
+        }
+        len
+    }
+
+    #[target_feature(enable = "avx512f,avx512bw")]
+    unsafe fn dot_prod_avx512bw<const N: usize>(
+        tables: &[&[u8]],
+        inputs: &[&[u8]],
+        outputs: &mut [&mut [u8]],
+    ) -> usize {
+        let len = outputs[0].len() / 64 * 64;
+        let mask = _mm512_set1_epi8(0x0F);
+
+        let mut pos = 0;
+        while pos < len {
+            let mut acc = [_mm512_setzero_si512(); N];
+            for (i, input) in inputs.iter().enumerate() {
+                let x = _mm512_loadu_si512(input.as_ptr().add(pos) as *const __m512i);
+                let low_nibbles = _mm512_and_si512(x, mask);
+                let high_nibbles = _mm512_and_si512(_mm512_srli_epi64(x, 4), mask);
+                for (j, acc) in acc.iter_mut().enumerate() {
+                    let t = tables[j].as_ptr().add(i * TABLE_LEN);
+                    let low = _mm512_broadcast_i32x4(_mm_loadu_si128(t as *const __m128i));
+                    let high = _mm512_broadcast_i32x4(_mm_loadu_si128(t.add(16) as *const __m128i));
+                    *acc = _mm512_xor_si512(
+                        *acc,
+                        _mm512_xor_si512(
+                            _mm512_shuffle_epi8(low, low_nibbles),
+                            _mm512_shuffle_epi8(high, high_nibbles),
+                        ),
+                    );
+                }
+            }
+            for (output, acc) in outputs.iter_mut().zip(acc.iter()) {
+                _mm512_storeu_si512(output.as_mut_ptr().add(pos) as *mut __m512i, *acc);
+            }
+
+            pos += 64;
+        }
+        len
+    }
+
+    // Each iteration adds 4 vectors of every input in registers, then stores
+    // them.
+    #[target_feature(enable = "sse2")]
+    unsafe fn xor_slices_sse2(inputs: &[&[u8]], out: &mut [u8]) -> usize {
+        let len = out.len() / 64 * 64;
+
+        let mut pos = 0;
+        while pos < len {
+            let mut acc = [_mm_setzero_si128(); 4];
+            for input in inputs.iter() {
+                let ptr = input.as_ptr().add(pos);
+                for (v, acc) in acc.iter_mut().enumerate() {
+                    let x = _mm_loadu_si128(ptr.add(16 * v) as *const __m128i);
+                    *acc = _mm_xor_si128(*acc, x);
+                }
+            }
+            for (v, acc) in acc.iter().enumerate() {
+                _mm_storeu_si128(out.as_mut_ptr().add(pos + 16 * v) as *mut __m128i, *acc);
+            }
+
+            pos += 64;
+        }
+        len
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn xor_slices_avx2(inputs: &[&[u8]], out: &mut [u8]) -> usize {
+        let len = out.len() / 128 * 128;
+
+        let mut pos = 0;
+        while pos < len {
+            let mut acc = [_mm256_setzero_si256(); 4];
+            for input in inputs.iter() {
+                let ptr = input.as_ptr().add(pos);
+                for (v, acc) in acc.iter_mut().enumerate() {
+                    let x = _mm256_loadu_si256(ptr.add(32 * v) as *const __m256i);
+                    *acc = _mm256_xor_si256(*acc, x);
+                }
+            }
+            for (v, acc) in acc.iter().enumerate() {
+                _mm256_storeu_si256(out.as_mut_ptr().add(pos + 32 * v) as *mut __m256i, *acc);
+            }
+
+            pos += 128;
+        }
+        len
+    }
+
+    #[target_feature(enable = "avx512f")]
+    unsafe fn xor_slices_avx512f(inputs: &[&[u8]], out: &mut [u8]) -> usize {
+        let len = out.len() / 256 * 256;
+
+        let mut pos = 0;
+        while pos < len {
+            let mut acc = [_mm512_setzero_si512(); 4];
+            for input in inputs.iter() {
+                let ptr = input.as_ptr().add(pos);
+                for (v, acc) in acc.iter_mut().enumerate() {
+                    let x = _mm512_loadu_si512(ptr.add(64 * v) as *const __m512i);
+                    *acc = _mm512_xor_si512(*acc, x);
+                }
+            }
+            for (v, acc) in acc.iter().enumerate() {
+                _mm512_storeu_si512(out.as_mut_ptr().add(pos + 64 * v) as *mut __m512i, *acc);
+            }
+
+            pos += 256;
+        }
+        len
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arch {
+    use super::{dot_prod_groups, DotProdKernel, NibbleTables, GROUP, TABLE_LEN};
+    use core::arch::aarch64::*;
+
+    fn neon() -> bool {
+        #[cfg(feature = "std")]
+        let neon = std::arch::is_aarch64_feature_detected!("neon");
+        #[cfg(not(feature = "std"))]
+        let neon = cfg!(target_feature = "neon");
+        neon
+    }
+
+    pub(super) fn mul_slice<const XOR: bool>(
+        tables: &NibbleTables,
+        input: &[u8],
+        out: &mut [u8],
+    ) -> usize {
+        if neon() {
+            unsafe { mul_slice_neon::<XOR>(tables, input, out) }
+        } else {
+            0
+        }
+    }
+
+    pub(super) fn dot_prod(tables: &[&[u8]], inputs: &[&[u8]], outputs: &mut [&mut [u8]]) -> usize {
+        if neon() {
+            let kernels: [DotProdKernel; GROUP] = [
+                dot_prod_neon::<1>,
+                dot_prod_neon::<2>,
+                dot_prod_neon::<3>,
+                dot_prod_neon::<4>,
+            ];
+            unsafe { dot_prod_groups(&kernels, tables, inputs, outputs) }
+        } else {
+            0
+        }
+    }
+
+    pub(super) fn xor_slices(inputs: &[&[u8]], out: &mut [u8]) -> usize {
+        if neon() {
+            unsafe { xor_slices_neon(inputs, out) }
+        } else {
+            0
+        }
+    }
+
+    // Each iteration adds 4 vectors of every input in registers, then stores
+    // them.
+    #[target_feature(enable = "neon")]
+    unsafe fn xor_slices_neon(inputs: &[&[u8]], out: &mut [u8]) -> usize {
+        let len = out.len() / 64 * 64;
+
+        let mut pos = 0;
+        while pos < len {
+            let mut acc = [vdupq_n_u8(0); 4];
+            for input in inputs.iter() {
+                let ptr = input.as_ptr().add(pos);
+                for (v, acc) in acc.iter_mut().enumerate() {
+                    *acc = veorq_u8(*acc, vld1q_u8(ptr.add(16 * v)));
+                }
+            }
+            for (v, acc) in acc.iter().enumerate() {
+                vst1q_u8(out.as_mut_ptr().add(pos + 16 * v), *acc);
+            }
+
+            pos += 64;
+        }
+        len
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn dot_prod_neon<const N: usize>(
+        tables: &[&[u8]],
+        inputs: &[&[u8]],
+        outputs: &mut [&mut [u8]],
+    ) -> usize {
+        let len = outputs[0].len() / 16 * 16;
+        let mask = vdupq_n_u8(0x0F);
+
+        let mut pos = 0;
+        while pos < len {
+            let mut acc = [vdupq_n_u8(0); N];
+            for (i, input) in inputs.iter().enumerate() {
+                let x = vld1q_u8(input.as_ptr().add(pos));
+                let low_nibbles = vandq_u8(x, mask);
+                let high_nibbles = vshrq_n_u8::<4>(x);
+                for (j, acc) in acc.iter_mut().enumerate() {
+                    let t = tables[j].as_ptr().add(i * TABLE_LEN);
+                    *acc = veorq_u8(
+                        *acc,
+                        veorq_u8(
+                            vqtbl1q_u8(vld1q_u8(t), low_nibbles),
+                            vqtbl1q_u8(vld1q_u8(t.add(16)), high_nibbles),
+                        ),
+                    );
+                }
+            }
+            for (output, acc) in outputs.iter_mut().zip(acc.iter()) {
+                vst1q_u8(output.as_mut_ptr().add(pos), *acc);
+            }
+
+            pos += 16;
+        }
+        len
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn mul_slice_neon<const XOR: bool>(
+        tables: &NibbleTables,
+        input: &[u8],
+        out: &mut [u8],
+    ) -> usize {
+        let len = input.len() / 16 * 16;
+        let low = vld1q_u8(tables.low.as_ptr());
+        let high = vld1q_u8(tables.high.as_ptr());
+        let mask = vdupq_n_u8(0x0F);
+
+        let in_ptr = input.as_ptr();
+        let out_ptr = out.as_mut_ptr();
+        let mut i = 0;
+        while i < len {
+            let x = vld1q_u8(in_ptr.add(i));
+            let mut product = veorq_u8(
+                vqtbl1q_u8(low, vandq_u8(x, mask)),
+                vqtbl1q_u8(high, vshrq_n_u8::<4>(x)),
+            );
+            if XOR {
+                product = veorq_u8(product, vld1q_u8(out_ptr.add(i)));
+            }
+            vst1q_u8(out_ptr.add(i), product);
+
+            i += 16;
+        }
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::galois_8::mul;
+    use crate::tests::fill_random;
+
+    fn tables_of(c: u8) -> NibbleTables {
+        let mut low = [0; 16];
+        let mut high = [0; 16];
+        for i in 0..16 {
+            low[i] = mul(c, i as u8);
+            high[i] = mul(c, (i as u8) << 4);
+        }
+        NibbleTables::new(low, high)
+    }
+
+    // The expected dot products and the rows of tables of random
+    // coefficients.
+    fn dot_prod_case(inputs: &[Vec<u8>], output_count: usize) -> (Vec<Vec<u8>>, Vec<Vec<u8>>) {
+        let len = inputs[0].len();
+        let mut expected = vec![vec![0; len]; output_count];
+        let mut tables = vec![vec![0; inputs.len() * TABLE_LEN]; output_count];
+        for (output, row) in expected.iter_mut().zip(tables.iter_mut()) {
+            for (input, table) in inputs.iter().zip(row.chunks_exact_mut(TABLE_LEN)) {
+                let c = rand::random::<u8>();
+                tables_of(c).write_to(table);
+                for (o, &x) in output.iter_mut().zip(input.iter()) {
+                    *o ^= mul(c, x);
+                }
+            }
+        }
+        (expected, tables)
+    }
+
+    fn random_inputs(count: usize, len: usize) -> Vec<Vec<u8>> {
+        let mut inputs = vec![vec![0; len]; count];
+        for input in inputs.iter_mut() {
+            fill_random(input);
+        }
+        inputs
+    }
+
+    #[test]
+    fn test_mul_slice_same_as_table() {
+        for &len in [0, 1, 15, 16, 33, 64, 100, 1000].iter() {
+            for _ in 0..20 {
+                let c = rand::random::<u8>();
+                let tables = tables_of(c);
+                let mut input = vec![0; len];
+                let mut out = vec![0; len];
+                fill_random(&mut input);
+                fill_random(&mut out);
+                let original = out.clone();
+
+                let done = mul_slice::<false>(&tables, &input, &mut out);
+                for i in 0..done {
+                    assert_eq!(mul(c, input[i]), out[i]);
+                }
+                assert_eq!(original[done..], out[done..]);
+
+                let mut out = original.clone();
+                let done = mul_slice::<true>(&tables, &input, &mut out);
+                for i in 0..done {
+                    assert_eq!(original[i] ^ mul(c, input[i]), out[i]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", target_arch = "x86_64"))]
+    fn test_x86_levels_same_as_table() {
+        use super::arch::{level, mul_slice_at, Level};
+
+        let levels = [Level::Ssse3, Level::Avx2, Level::Avx512bw];
+        let supported = match level() {
+            Level::Avx512bw => 3,
+            Level::Avx2 => 2,
+            Level::Ssse3 => 1,
+            Level::None => 0,
+        };
+        let len = 1000;
+        for &level in levels[..supported].iter() {
+            let c = rand::random::<u8>();
+            let tables = tables_of(c);
+            let mut input = vec![0; len];
+            let mut out = vec![0; len];
+            fill_random(&mut input);
+            fill_random(&mut out);
+            let mut expected = out.clone();
+            for (e, &x) in expected.iter_mut().zip(input.iter()) {
+                *e ^= mul(c, x);
+            }
+
+            let done = unsafe { mul_slice_at::<true>(level, &tables, &input, &mut out) };
+            assert!(done > len - 64);
+            assert_eq!(expected[..done], out[..done]);
+        }
+    }
+
+    #[test]
+    fn test_dot_prod_same_as_table() {
+        for &(input_count, output_count, len) in [
+            (1, 1, 100),
+            (3, 5, 64),
+            (10, 4, 1000),
+            (7, 9, 33),
+            (2, 3, 15),
+        ]
+        .iter()
+        {
+            let inputs = random_inputs(input_count, len);
+            let (expected, tables) = dot_prod_case(&inputs, output_count);
+            let mut outputs = random_inputs(output_count, len);
+
+            let input_refs: Vec<&[u8]> = inputs.iter().map(|x| x.as_slice()).collect();
+            let mut output_refs: Vec<&mut [u8]> =
+                outputs.iter_mut().map(|x| x.as_mut_slice()).collect();
+            let table_refs: Vec<&[u8]> = tables.iter().map(|x| x.as_slice()).collect();
+            let done = dot_prod(&table_refs, &input_refs, &mut output_refs);
+
+            for (output, expected) in outputs.iter().zip(expected.iter()) {
+                assert_eq!(expected[..done], output[..done]);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", target_arch = "x86_64"))]
+    fn test_x86_dot_prod_levels_same_as_table() {
+        use super::arch::{dot_prod_at, level, Level};
+
+        let levels = [Level::Ssse3, Level::Avx2, Level::Avx512bw];
+        let supported = match level() {
+            Level::Avx512bw => 3,
+            Level::Avx2 => 2,
+            Level::Ssse3 => 1,
+            Level::None => 0,
+        };
+        let len = 1000;
+        for &level in levels[..supported].iter() {
+            for output_count in 1..=9 {
+                let inputs = random_inputs(6, len);
+                let (expected, tables) = dot_prod_case(&inputs, output_count);
+                let mut outputs = random_inputs(output_count, len);
+
+                let input_refs: Vec<&[u8]> = inputs.iter().map(|x| x.as_slice()).collect();
+                let mut output_refs: Vec<&mut [u8]> =
+                    outputs.iter_mut().map(|x| x.as_mut_slice()).collect();
+                let table_refs: Vec<&[u8]> = tables.iter().map(|x| x.as_slice()).collect();
+                let done =
+                    unsafe { dot_prod_at(level, &table_refs, &input_refs, &mut output_refs) };
+
+                assert!(done > len - 64);
+                for (output, expected) in outputs.iter().zip(expected.iter()) {
+                    assert_eq!(expected[..done], output[..done]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_xor_slices_same_as_fold() {
+        for &(input_count, len) in [(0, 100), (1, 64), (10, 1000), (3, 255), (2, 15)].iter() {
+            let inputs = random_inputs(input_count, len);
+            let mut out = random_inputs(1, len).remove(0);
+
+            let input_refs: Vec<&[u8]> = inputs.iter().map(|x| x.as_slice()).collect();
+            let done = xor_slices(&input_refs, &mut out);
+
+            for (i, &o) in out[..done].iter().enumerate() {
+                assert_eq!(inputs.iter().fold(0, |acc, input| acc ^ input[i]), o);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", target_arch = "x86_64"))]
+    fn test_x86_xor_slices_levels_same_as_fold() {
+        use super::arch::{level, xor_slices_at, Level};
+
+        let levels = [Level::None, Level::Avx2, Level::Avx512bw];
+        let supported = match level() {
+            Level::Avx512bw => 3,
+            Level::Avx2 => 2,
+            Level::Ssse3 | Level::None => 1,
+        };
+        let len = 1000;
+        for &level in levels[..supported].iter() {
+            let inputs = random_inputs(5, len);
+            let mut out = vec![0; len];
+
+            let input_refs: Vec<&[u8]> = inputs.iter().map(|x| x.as_slice()).collect();
+            let done = unsafe { xor_slices_at(level, &input_refs, &mut out) };
+
+            assert!(done > len - 256);
+            for (i, &o) in out[..done].iter().enumerate() {
+                assert_eq!(inputs.iter().fold(0, |acc, input| acc ^ input[i]), o);
+            }
+        }
+    }
+}
@@ -0,0 +1,716 @@
+//! Reed-Solomon erasure code over GF(2^16) with O(n log n) encoding and
+//! decoding, for up to 65536 shards.
+//!
+//! `galois_16::ReedSolomon` encodes with a matrix, which costs O(k m) per
+//! byte for `k` data and `m` parity shards, and inverts a `k x k` matrix to
+//! reconstruct. This codec follows the algorithm of Leopard-RS instead: the
+//! data shards are the values of a polynomial at points of GF(2^16) in a
+//! Cantor basis, the parity shards are obtained with an additive FFT, and
+//! missing shards are recovered with the error locator polynomial evaluated
+//! by fast Walsh-Hadamard transforms. Stripes of 32768 data and 32768 parity
+//! shards are practical.
+//!
+//! The parity is different from the one of `ReedSolomon`. Shard sizes must
+//! be multiples of `CHUNK_LEN`: each chunk of 64 bytes holds the low bytes of
+//! 32 elements followed by their high bytes.
+//!
+//! The number of parity shards rounded up to a power of two plus the number
+//! of data shards may not exceed 65536.
+//!
+//! This module is only available with the `std` feature enabled.
+//!
+//! # Example
+//!
+//! ```
+//! # use reed_solomon_erasure::leopard::ReedSolomon;
+//! let r = ReedSolomon::new(1000, 200).unwrap();
+//!
+//! let mut shards = vec![vec![0u8; 64]; 1200];
+//! for (i, shard) in shards[..1000].iter_mut().enumerate() {
+//!     shard[0] = i as u8;
+//! }
+//! r.encode(&mut shards).unwrap();
+//!
+//! let mut received: Vec<_> = shards.iter().cloned().map(Some).collect();
+//! for i in 0..200 {
+//!     received[i * 5] = None;
+//! }
+//! r.reconstruct(&mut received).unwrap();
+//! assert_eq!(shards, received.into_iter().map(Option::unwrap).collect::<Vec<_>>());
+//! ```
+
+use std::sync::OnceLock;
+
+use crate::errors::Error;
+use crate::galois_16_slice::{self, SplitTables};
+use crate::{galois_8, ReconstructShard};
+
+/// Length of the chunks shards are made of.
+pub const CHUNK_LEN: usize = galois_16_slice::CHUNK_LEN;
+
+/// Maximum number of shards, including the parity shards rounded up to a
+/// power of two.
+pub const MAX_SHARDS: usize = 65536;
+
+const ORDER: usize = 65536;
+
+// also the logarithm of zero
+const MODULUS: u16 = 65535;
+
+// x^16 + x^5 + x^3 + x^2 + 1
+const POLYNOMIAL: usize = 0x1002D;
+
+const CANTOR_BASIS: [u16; 16] = [
+    0x0001, 0xACCA, 0x3C0E, 0x163E, 0xC582, 0xED2E, 0x914C, 0x4012, 0x6C98, 0x10D8, 0x6A72, 0xB900,
+    0xFDB8, 0xFB34, 0xFF38, 0x991E,
+];
+
+// Sum modulo 65535, where 65535 may be left unreduced.
+fn add_mod(a: u16, b: u16) -> u16 {
+    let sum = a as u32 + b as u32;
+    (sum + (sum >> 16)) as u16
+}
+
+// Difference modulo 65535, where 65535 may be left unreduced.
+fn sub_mod(a: u16, b: u16) -> u16 {
+    let dif = (a as u32).wrapping_sub(b as u32);
+    dif.wrapping_add(dif >> 16) as u16
+}
+
+// Walsh-Hadamard transform modulo 65535 of `data`, of which only the first
+// `truncated` values may be non-zero.
+fn fwht(data: &mut [u16], truncated: usize) {
+    let mut width = 1;
+    while width < data.len() {
+        for r in (0..truncated).step_by(2 * width) {
+            for i in r..r + width {
+                let (a, b) = (data[i], data[i + width]);
+                data[i] = add_mod(a, b);
+                data[i + width] = sub_mod(a, b);
+            }
+        }
+        width *= 2;
+    }
+}
+
+struct Tables {
+    // logarithms and exponentials of elements in the Cantor basis
+    log: Vec<u16>,
+    exp: Vec<u16>,
+    // logarithms of the twiddle factors of the FFT
+    skew: Vec<u16>,
+    // Walsh-Hadamard transform of the logarithms
+    log_walsh: Vec<u16>,
+}
+
+impl Tables {
+    fn new() -> Tables {
+        let mut log = vec![0u16; ORDER];
+        let mut exp = vec![0u16; ORDER];
+
+        // logarithms in the polynomial basis, stored in `exp` for now
+        let mut state = 1;
+        for i in 0..MODULUS {
+            exp[state] = i;
+            state <<= 1;
+            if state >= ORDER {
+                state ^= POLYNOMIAL;
+            }
+        }
+        exp[0] = MODULUS;
+
+        // the elements of the Cantor basis in the polynomial basis
+        for (i, basis) in CANTOR_BASIS.iter().enumerate() {
+            let width = 1 << i;
+            for j in 0..width {
+                log[j + width] = log[j] ^ basis;
+            }
+        }
+        for elem in log.iter_mut() {
+            *elem = exp[*elem as usize];
+        }
+        for (elem, &l) in log.iter().enumerate() {
+            exp[l as usize] = elem as u16;
+        }
+        exp[MODULUS as usize] = exp[0];
+
+        let mul_log = |a: u16, log_b: u16| {
+            if a == 0 {
+                0
+            } else {
+                exp[add_mod(log[a as usize], log_b) as usize]
+            }
+        };
+
+        let mut skew = vec![0u16; MODULUS as usize];
+        let mut temp = [0u16; 15];
+        for (i, t) in temp.iter_mut().enumerate() {
+            *t = 1 << (i + 1);
+        }
+        for m in 0..15 {
+            let step = 1 << (m + 1);
+            skew[(1 << m) - 1] = 0;
+            for (i, &t) in temp.iter().enumerate().skip(m) {
+                let s = 1 << (i + 1);
+                for j in ((1 << m) - 1..s).step_by(step) {
+                    skew[j + s] = skew[j] ^ t;
+                }
+            }
+
+            temp[m] = MODULUS - log[mul_log(temp[m], log[(temp[m] ^ 1) as usize]) as usize];
+            for i in m + 1..15 {
+                let sum = add_mod(log[(temp[i] ^ 1) as usize], temp[m]);
+                temp[i] = mul_log(temp[i], sum);
+            }
+        }
+        for s in skew.iter_mut() {
+            *s = log[*s as usize];
+        }
+
+        let mut log_walsh = log.clone();
+        log_walsh[0] = 0;
+        fwht(&mut log_walsh, ORDER);
+
+        Tables {
+            log,
+            exp,
+            skew,
+            log_walsh,
+        }
+    }
+
+    fn get() -> &'static Tables {
+        static TABLES: OnceLock<Tables> = OnceLock::new();
+        TABLES.get_or_init(Tables::new)
+    }
+
+    // The products with the element of logarithm `log_m`.
+    fn mul_tables(&self, log_m: u16) -> SplitTables {
+        SplitTables::new(|x| {
+            let a = u16::from_le_bytes(x);
+            let product = if a == 0 {
+                0
+            } else {
+                self.exp[add_mod(self.log[a as usize], log_m) as usize]
+            };
+            product.to_le_bytes()
+        })
+    }
+}
+
+fn xor(input: &[u8], out: &mut [u8]) {
+    for (o, i) in out.iter_mut().zip(input) {
+        *o ^= i;
+    }
+}
+
+// Shards of the FFT, stored contiguously.
+struct Work {
+    buf: Vec<u8>,
+    shard_len: usize,
+}
+
+impl Work {
+    fn new(count: usize, shard_len: usize) -> Work {
+        Work {
+            buf: vec![0; count * shard_len],
+            shard_len,
+        }
+    }
+
+    fn shard(&self, i: usize) -> &[u8] {
+        &self.buf[i * self.shard_len..(i + 1) * self.shard_len]
+    }
+
+    fn shard_mut(&mut self, i: usize) -> &mut [u8] {
+        &mut self.buf[i * self.shard_len..(i + 1) * self.shard_len]
+    }
+
+    fn clear(&mut self, from: usize, to: usize) {
+        for b in self.buf[from * self.shard_len..to * self.shard_len].iter_mut() {
+            *b = 0;
+        }
+    }
+
+    // Shards `i` and `j`, with `i < j`.
+    fn pair(&mut self, i: usize, j: usize) -> (&mut [u8], &mut [u8]) {
+        let (left, right) = self.buf.split_at_mut(j * self.shard_len);
+        (
+            &mut left[i * self.shard_len..(i + 1) * self.shard_len],
+            &mut right[..self.shard_len],
+        )
+    }
+
+    // Inverse FFT of the `size` shards at `pos`, of which only the first
+    // `truncated` may be non-zero, with the twiddle factors at `skew_pos`.
+    fn ifft(&mut self, pos: usize, size: usize, truncated: usize, skew_pos: usize) {
+        let tables = Tables::get();
+        let mut width = 1;
+        while width < size {
+            for r in (0..truncated).step_by(2 * width) {
+                let log_m = tables.skew[skew_pos + r + width - 1];
+                let mul = if log_m == MODULUS {
+                    None
+                } else {
+                    Some(tables.mul_tables(log_m))
+                };
+                for i in pos + r..pos + r + width {
+                    let (x, y) = self.pair(i, i + width);
+                    xor(x, y);
+                    if let Some(mul) = &mul {
+                        galois_16_slice::mul_chunks_xor(mul, y, x);
+                    }
+                }
+            }
+            width *= 2;
+        }
+    }
+
+    // FFT of the `size` shards at `pos`, of which only the first `truncated`
+    // are computed.
+    fn fft(&mut self, pos: usize, size: usize, truncated: usize, skew_pos: usize) {
+        let tables = Tables::get();
+        let mut width = size / 2;
+        while width > 0 {
+            for r in (0..truncated).step_by(2 * width) {
+                let log_m = tables.skew[skew_pos + r + width - 1];
+                let mul = if log_m == MODULUS {
+                    None
+                } else {
+                    Some(tables.mul_tables(log_m))
+                };
+                for i in pos + r..pos + r + width {
+                    let (x, y) = self.pair(i, i + width);
+                    if let Some(mul) = &mul {
+                        galois_16_slice::mul_chunks_xor(mul, y, x);
+                    }
+                    xor(x, y);
+                }
+            }
+            width /= 2;
+        }
+    }
+}
+
+/// Reed-Solomon erasure code encoder/decoder over GF(2^16) using FFTs.
+///
+/// The methods behave like those of `crate::ReedSolomon`, with shards of
+/// bytes whose length is a multiple of `CHUNK_LEN`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReedSolomon {
+    data_shard_count: usize,
+    parity_shard_count: usize,
+    total_shard_count: usize,
+}
+
+impl ReedSolomon {
+    /// Creates a new instance of the codec.
+    ///
+    /// Returns `Error::TooFewDataShards` if `data_shards == 0`,
+    /// `Error::TooFewParityShards` if `parity_shards == 0`, and
+    /// `Error::TooManyShards` if `parity_shards` rounded up to a power of two
+    /// plus `data_shards` exceeds `MAX_SHARDS`.
+    pub fn new(data_shards: usize, parity_shards: usize) -> Result<ReedSolomon, Error> {
+        if data_shards == 0 {
+            return Err(Error::TooFewDataShards);
+        }
+        if parity_shards == 0 {
+            return Err(Error::TooFewParityShards);
+        }
+        if parity_shards.next_power_of_two() + data_shards > MAX_SHARDS {
+            return Err(Error::TooManyShards);
+        }
+
+        Ok(ReedSolomon {
+            data_shard_count: data_shards,
+            parity_shard_count: parity_shards,
+            total_shard_count: data_shards + parity_shards,
+        })
+    }
+
+    pub fn data_shard_count(&self) -> usize {
+        self.data_shard_count
+    }
+
+    pub fn parity_shard_count(&self) -> usize {
+        self.parity_shard_count
+    }
+
+    pub fn total_shard_count(&self) -> usize {
+        self.total_shard_count
+    }
+
+    // The parity of `data` at the first `parity_shard_count` shards.
+    fn encode_work<T: AsRef<[u8]>>(&self, data: &[T], shard_len: usize) -> Work {
+        let m = self.parity_shard_count.next_power_of_two();
+        let mut work = Work::new(2 * m, shard_len);
+
+        // the data shards are at positions m, m + 1, ... of the FFT, so they
+        // are transformed m at a time and summed
+        for (i, chunk) in data.chunks(m).enumerate() {
+            let pos = if i == 0 { 0 } else { m };
+            work.clear(pos, pos + m);
+            for (j, shard) in chunk.iter().enumerate() {
+                work.shard_mut(pos + j).copy_from_slice(shard.as_ref());
+            }
+            work.ifft(pos, m, chunk.len(), m * (i + 1));
+            if i > 0 {
+                let (sum, shards) = work.buf.split_at_mut(m * shard_len);
+                xor(shards, sum);
+            }
+        }
+
+        work.fft(0, m, self.parity_shard_count, 0);
+        work
+    }
+
+    /// Constructs the parity shards.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    pub fn encode<T, U>(&self, mut shards: T) -> Result<(), Error>
+    where
+        T: AsRef<[U]> + AsMut<[U]>,
+        U: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        let slices: &mut [U] = shards.as_mut();
+
+        check_piece_count!(all => self, slices);
+
+        let (input, output) = slices.split_at_mut(self.data_shard_count);
+
+        self.encode_sep(&*input, output)
+    }
+
+    /// Constructs the parity shards using a read-only view into the
+    /// data shards.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    pub fn encode_sep<T: AsRef<[u8]>, U: AsRef<[u8]> + AsMut<[u8]>>(
+        &self,
+        data: &[T],
+        parity: &mut [U],
+    ) -> Result<(), Error> {
+        check_piece_count!(data => self, data);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => data, multi => parity);
+
+        let shard_len = data[0].as_ref().len();
+        if !shard_len.is_multiple_of(CHUNK_LEN) {
+            return Err(Error::IncorrectShardSize);
+        }
+
+        let work = self.encode_work(data, shard_len);
+        for (i, shard) in parity.iter_mut().enumerate() {
+            shard.as_mut().copy_from_slice(work.shard(i));
+        }
+
+        Ok(())
+    }
+
+    /// Checks if the parity shards are correct.
+    pub fn verify<T: AsRef<[u8]>>(&self, slices: &[T]) -> Result<bool, Error> {
+        check_piece_count!(all => self, slices);
+        check_slices!(multi => slices);
+
+        let shard_len = slices[0].as_ref().len();
+        if !shard_len.is_multiple_of(CHUNK_LEN) {
+            return Err(Error::IncorrectShardSize);
+        }
+
+        let (data, parity) = slices.split_at(self.data_shard_count);
+        let work = self.encode_work(data, shard_len);
+
+        Ok(parity
+            .iter()
+            .enumerate()
+            .all(|(i, shard)| shard.as_ref() == work.shard(i)))
+    }
+
+    /// Reconstructs all shards.
+    ///
+    /// The shards marked not present are only overwritten when no error
+    /// is detected. All provided shards must have the same length.
+    ///
+    /// This means if the method returns an `Error`, then nothing is touched.
+    pub fn reconstruct<T: ReconstructShard<galois_8::Field>>(
+        &self,
+        slices: &mut [T],
+    ) -> Result<(), Error> {
+        self.reconstruct_internal(slices, false)
+    }
+
+    /// Reconstructs only the data shards.
+    ///
+    /// The shards marked not present are only overwritten when no error
+    /// is detected. All provided shards must have the same length.
+    ///
+    /// This means if the method returns an `Error`, then nothing is touched.
+    pub fn reconstruct_data<T: ReconstructShard<galois_8::Field>>(
+        &self,
+        slices: &mut [T],
+    ) -> Result<(), Error> {
+        self.reconstruct_internal(slices, true)
+    }
+
+    fn reconstruct_internal<T: ReconstructShard<galois_8::Field>>(
+        &self,
+        shards: &mut [T],
+        data_only: bool,
+    ) -> Result<(), Error> {
+        check_piece_count!(all => self, shards);
+
+        let data_shard_count = self.data_shard_count;
+
+        let mut number_present = 0;
+        let mut shard_len = None;
+
+        for shard in shards.iter() {
+            if let Some(len) = shard.len() {
+                if len == 0 {
+                    return Err(Error::EmptyShard);
+                }
+                number_present += 1;
+                if let Some(old_len) = shard_len {
+                    if len != old_len {
+                        return Err(Error::IncorrectShardSize);
+                    }
+                }
+                shard_len = Some(len);
+            }
+        }
+
+        if number_present == self.total_shard_count {
+            return Ok(());
+        }
+
+        if number_present < data_shard_count {
+            return Err(Error::TooFewShardsPresent);
+        }
+
+        let shard_len = shard_len.expect("at least one shard present; qed");
+        if !shard_len.is_multiple_of(CHUNK_LEN) {
+            return Err(Error::IncorrectShardSize);
+        }
+
+        let present: Vec<bool> = shards.iter().map(|shard| shard.len().is_some()).collect();
+
+        let mut slices: Vec<&mut [u8]> = Vec::with_capacity(self.total_shard_count);
+        for (i, shard) in shards.iter_mut().enumerate() {
+            let slice = if i >= data_shard_count && data_only {
+                shard.get().unwrap_or(&mut [])
+            } else {
+                match shard.get_or_initialize(shard_len) {
+                    Ok(slice) | Err(Ok(slice)) => slice,
+                    Err(Err(e)) => return Err(e),
+                }
+            };
+            slices.push(slice);
+        }
+
+        if present[..data_shard_count].contains(&false) {
+            self.decode(&mut slices, &present, shard_len);
+        }
+
+        if !data_only && present[data_shard_count..].contains(&false) {
+            let (data, parity) = slices.split_at_mut(data_shard_count);
+            let work = self.encode_work(data, shard_len);
+            for (i, shard) in parity.iter_mut().enumerate() {
+                if !present[data_shard_count + i] {
+                    shard.copy_from_slice(work.shard(i));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Recovers the missing data shards.
+    fn decode(&self, slices: &mut [&mut [u8]], present: &[bool], shard_len: usize) {
+        let tables = Tables::get();
+        let k = self.data_shard_count;
+        let p = self.parity_shard_count;
+        let m = p.next_power_of_two();
+        let n = (m + k).next_power_of_two();
+
+        // the parity shards are at positions 0, 1, ... of the FFT, and the
+        // data shards at positions m, m + 1, ...
+        let position = |i: usize| if i < k { m + i } else { i - k };
+
+        let mut error_locations = vec![0u16; ORDER];
+        for e in error_locations[p..m].iter_mut() {
+            *e = 1;
+        }
+        for (i, _) in present.iter().enumerate().filter(|(_, &present)| !present) {
+            error_locations[position(i)] = 1;
+        }
+
+        // evaluate the error locator polynomial
+        fwht(&mut error_locations, m + k);
+        for (e, &l) in error_locations.iter_mut().zip(tables.log_walsh.iter()) {
+            *e = ((*e as u32 * l as u32) % MODULUS as u32) as u16;
+        }
+        fwht(&mut error_locations, ORDER);
+
+        let mut work = Work::new(n, shard_len);
+        for (i, slice) in slices.iter().enumerate() {
+            if present[i] {
+                let pos = position(i);
+                let mul = tables.mul_tables(error_locations[pos]);
+                galois_16_slice::mul_chunks(&mul, slice, work.shard_mut(pos));
+            }
+        }
+
+        work.ifft(0, n, m + k, 0);
+
+        // formal derivative
+        for i in 1..n {
+            let width = ((i ^ (i - 1)) + 1) >> 1;
+            let (left, right) = work.buf.split_at_mut(i * shard_len);
+            xor(
+                &right[..width * shard_len],
+                &mut left[(i - width) * shard_len..],
+            );
+        }
+
+        work.fft(0, n, m + k, 0);
+
+        for (i, slice) in slices[..k].iter_mut().enumerate() {
+            if !present[i] {
+                let mul = tables.mul_tables(MODULUS - error_locations[m + i]);
+                galois_16_slice::mul_chunks(&mul, work.shard(m + i), slice);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fill_random;
+
+    fn random_shards(r: &ReedSolomon, shard_len: usize) -> Vec<Vec<u8>> {
+        let mut shards = vec![vec![0u8; shard_len]; r.total_shard_count()];
+        for shard in shards[..r.data_shard_count()].iter_mut() {
+            fill_random(shard);
+        }
+        r.encode(&mut shards).unwrap();
+        shards
+    }
+
+    fn reconstruct_without(r: &ReedSolomon, shards: &[Vec<u8>], missing: &[usize]) {
+        let mut some: Vec<_> = shards.iter().cloned().map(Some).collect();
+        for &i in missing {
+            some[i] = None;
+        }
+        r.reconstruct_data(&mut some).unwrap();
+        for (i, shard) in some.iter().enumerate() {
+            if i < r.data_shard_count() || !missing.contains(&i) {
+                assert_eq!(Some(&shards[i]), shard.as_ref());
+            } else {
+                assert!(shard.is_none());
+            }
+        }
+
+        r.reconstruct(&mut some).unwrap();
+        let some: Vec<_> = some.into_iter().map(Option::unwrap).collect();
+        assert_eq!(shards, &some[..]);
+    }
+
+    #[test]
+    fn test_tables() {
+        let tables = Tables::get();
+        assert_eq!(MODULUS, tables.log[0]);
+        assert_eq!(0, tables.log[1]);
+        assert_eq!(1, tables.exp[0]);
+        assert_eq!(1, tables.exp[MODULUS as usize]);
+        for a in 1..ORDER {
+            assert_eq!(a, tables.exp[tables.log[a] as usize] as usize);
+        }
+        assert_eq!(5, add_mod(2, 3));
+        assert_eq!(1, add_mod(MODULUS, 1));
+        assert_eq!(MODULUS - 1, sub_mod(2, 3));
+    }
+
+    #[test]
+    fn test_encode_linear() {
+        let r = ReedSolomon::new(10, 5).unwrap();
+        let a = random_shards(&r, 128);
+        let b = random_shards(&r, 128);
+        let mut sum: Vec<Vec<u8>> = a
+            .iter()
+            .zip(b.iter())
+            .map(|(a, b)| a.iter().zip(b).map(|(a, b)| a ^ b).collect())
+            .collect();
+        assert!(r.verify(&sum).unwrap());
+
+        sum[12][3] ^= 1;
+        assert!(!r.verify(&sum).unwrap());
+
+        let zeros = vec![vec![0u8; 64]; 15];
+        assert!(r.verify(&zeros).unwrap());
+    }
+
+    #[test]
+    fn test_reconstruct_all_patterns() {
+        // every selection of missing shards, with fewer data shards than
+        // the rounded parity count and more
+        for &(k, p) in [(4, 3), (3, 5), (6, 2)].iter() {
+            let r = ReedSolomon::new(k, p).unwrap();
+            let shards = random_shards(&r, 128);
+            for pattern in 0u32..1 << (k + p) {
+                if pattern.count_ones() as usize > p {
+                    continue;
+                }
+                let missing: Vec<usize> = (0..k + p).filter(|i| pattern >> i & 1 == 1).collect();
+                reconstruct_without(&r, &shards, &missing);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reconstruct_shard_counts() {
+        for &(k, p) in [(1, 1), (2, 1), (1, 7), (17, 9), (100, 28), (300, 1000)].iter() {
+            let r = ReedSolomon::new(k, p).unwrap();
+            let shards = random_shards(&r, 192);
+
+            let missing: Vec<usize> = (0..p).map(|i| i * (k + p) / p).collect();
+            reconstruct_without(&r, &shards, &missing);
+            let missing: Vec<usize> = (0..p.min(k)).collect();
+            reconstruct_without(&r, &shards, &missing);
+            let missing: Vec<usize> = (k..k + p).collect();
+            reconstruct_without(&r, &shards, &missing);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Err(Error::TooFewDataShards), ReedSolomon::new(0, 1));
+        assert_eq!(Err(Error::TooFewParityShards), ReedSolomon::new(1, 0));
+        assert!(ReedSolomon::new(32768, 32768).is_ok());
+        assert!(ReedSolomon::new(65535, 1).is_ok());
+        assert_eq!(Err(Error::TooManyShards), ReedSolomon::new(32769, 32768));
+        assert_eq!(Err(Error::TooManyShards), ReedSolomon::new(1, 65535));
+
+        let r = ReedSolomon::new(3, 2).unwrap();
+        assert_eq!(
+            Err(Error::IncorrectShardSize),
+            r.encode(&mut vec![vec![0u8; 100]; 5])
+        );
+        assert_eq!(Err(Error::EmptyShard), r.encode(&mut vec![vec![0u8; 0]; 5]));
+        assert_eq!(
+            Err(Error::TooFewShards),
+            r.encode(&mut vec![vec![0u8; 64]; 4])
+        );
+
+        let mut shards = vec![Some(vec![0u8; 64]); 5];
+        shards[0] = None;
+        shards[1] = None;
+        shards[2] = None;
+        assert_eq!(Err(Error::TooFewShardsPresent), r.reconstruct(&mut shards));
+        assert!(shards[..3].iter().all(Option::is_none));
+
+        shards[2] = Some(vec![0u8; 128]);
+        assert_eq!(Err(Error::IncorrectShardSize), r.reconstruct(&mut shards));
+        assert!(shards[..2].iter().all(Option::is_none));
+    }
+}
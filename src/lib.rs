@@ -35,10 +35,38 @@ mod matrix;
 #[cfg(test)]
 mod tests;
 
+#[cfg(feature = "std")]
+pub mod archive;
+#[cfg(feature = "async")]
+pub mod async_stream;
+#[cfg(feature = "async")]
+pub mod fetch;
+pub mod fingerprint;
 pub mod galois_16;
+pub mod galois_16_native;
+mod galois_16_slice;
 pub mod galois_8;
+mod galois_8_simd;
+#[cfg(feature = "std")]
+pub mod leopard;
+#[cfg(feature = "merkle")]
+pub mod merkle;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod ntt;
+#[cfg(feature = "par2")]
+pub mod par2;
+pub mod profile;
+pub mod shard_format;
+#[cfg(feature = "std")]
+pub mod stream;
+#[cfg(feature = "std")]
+pub mod zfec;
 
 pub use crate::errors::Error;
+pub use crate::errors::FormatError;
+#[cfg(feature = "par2")]
+pub use crate::errors::Par2Error;
 pub use crate::errors::SBSError;
 
 pub use crate::core::ReedSolomon;
@@ -64,6 +92,15 @@ pub trait Field: Sized {
     /// Add two elements together.
     fn add(a: Self::Elem, b: Self::Elem) -> Self::Elem;
 
+    /// Subtract `b` from `a`.
+    ///
+    /// The default implementation is `add`, which is correct for fields of
+    /// characteristic 2 such as GF(2^8) and GF(2^16). Other fields must
+    /// override it.
+    fn sub(a: Self::Elem, b: Self::Elem) -> Self::Elem {
+        Self::add(a, b)
+    }
+
     /// Multiply two elements together.
     fn mul(a: Self::Elem, b: Self::Elem) -> Self::Elem;
 
@@ -100,7 +137,7 @@ pub trait Field: Sized {
         assert_eq!(input.len(), out.len());
 
         for (i, o) in input.iter().zip(out) {
-            *o = Self::mul(elem.clone(), i.clone())
+            *o = Self::mul(elem, *i)
         }
     }
 
@@ -113,7 +150,99 @@ pub trait Field: Sized {
         assert_eq!(input.len(), out.len());
 
         for (i, o) in input.iter().zip(out) {
-            *o = Self::add(o.clone(), Self::mul(elem.clone(), i.clone()))
+            *o = Self::add(*o, Self::mul(elem, *i))
+        }
+    }
+
+    /// Multiply slices of elements by a matrix. Each output slice is overwritten with
+    /// the sum of the input slices multiplied by the elements of its row of `matrix_rows`.
+    ///
+    /// The default implementation calls `mul_slice` and `mul_slice_add` for each pair
+    /// of input and output.
+    ///
+    /// # Panics
+    /// Panics if the slices do not all have equal length, or if there are fewer rows
+    /// than outputs or fewer elements in a row than inputs.
+    fn dot_prod(
+        matrix_rows: &[&[Self::Elem]],
+        inputs: &[&[Self::Elem]],
+        outputs: &mut [&mut [Self::Elem]],
+    ) {
+        for (i_input, input) in inputs.iter().enumerate() {
+            for (i_row, output) in outputs.iter_mut().enumerate() {
+                let c = matrix_rows[i_row][i_input];
+                if i_input == 0 {
+                    Self::mul_slice(c, input, output);
+                } else {
+                    Self::mul_slice_add(c, input, output);
+                }
+            }
+        }
+    }
+
+    /// Whether `dot_prod_prepared` and `sum_slices` write every element of
+    /// their outputs before reading it, which the default implementations
+    /// do. `ReedSolomon::encode_sep_uninit` and
+    /// `ReedSolomon::reconstruct_uninit` then code into uninitialized
+    /// outputs, and zero them first otherwise.
+    const WRITES_OUTPUTS_FIRST: bool = false;
+
+    /// The number of elements of the table which `prepare` writes for an
+    /// element, zero if the field has no tables.
+    const TABLE_LEN: usize = 0;
+
+    /// Writes the table of `elem` used by `dot_prod_prepared` into `table`,
+    /// which holds `TABLE_LEN` elements.
+    fn prepare(_elem: Self::Elem, _table: &mut [Self::Elem]) {}
+
+    /// Same as `dot_prod`, with the tables of the elements of the matrix as
+    /// written by `prepare`. `tables[j]` holds the tables of the elements of
+    /// `matrix_rows[j]` back to back, so they can be computed once for a
+    /// matrix instead of at every call.
+    ///
+    /// The default implementation ignores the tables and calls `dot_prod`.
+    ///
+    /// # Panics
+    /// Panics in the same cases as `dot_prod`, or if a row of `tables` does
+    /// not hold the tables of as many elements as there are inputs.
+    fn dot_prod_prepared(
+        matrix_rows: &[&[Self::Elem]],
+        _tables: &[&[Self::Elem]],
+        inputs: &[&[Self::Elem]],
+        outputs: &mut [&mut [Self::Elem]],
+    ) {
+        Self::dot_prod(matrix_rows, inputs, outputs)
+    }
+
+    /// Overwrite `out` with the sum of the input slices, element by element,
+    /// which is the dot product with a row of ones.
+    ///
+    /// The default implementation copies the first input and calls
+    /// `add_slice` for the others.
+    ///
+    /// # Panics
+    /// Panics if the slices do not all have equal length.
+    fn sum_slices(inputs: &[&[Self::Elem]], out: &mut [Self::Elem]) {
+        match inputs.split_first() {
+            Some((first, rest)) => {
+                out.copy_from_slice(first);
+                for input in rest {
+                    Self::add_slice(input, out);
+                }
+            }
+            None => out.iter_mut().for_each(|o| *o = Self::zero()),
+        }
+    }
+
+    /// Add a slice of elements to another, element by element. Writes into the output slice.
+    ///
+    /// # Panics
+    /// Panics if the output slice does not have equal length to the input.
+    fn add_slice(input: &[Self::Elem], out: &mut [Self::Elem]) {
+        assert_eq!(input.len(), out.len());
+
+        for (i, o) in input.iter().zip(out) {
+            *o = Self::add(*o, *i)
         }
     }
 }
@@ -122,6 +251,7 @@ pub trait Field: Sized {
 ///
 /// This trait is used in reconstruction, where some of the shards
 /// may be unknown.
+#[allow(clippy::len_without_is_empty)]
 pub trait ReconstructShard<F: Field> {
     /// The size of the shard data; `None` if empty.
     fn len(&self) -> Option<usize>;
@@ -131,6 +261,7 @@ pub trait ReconstructShard<F: Field> {
 
     /// Get a mutable reference to the shard data, initializing it to the
     /// given length if it was `None`. Returns an error if initialization fails.
+    #[allow(clippy::type_complexity)]
     fn get_or_initialize(
         &mut self,
         len: usize,
@@ -154,7 +285,7 @@ impl<F: Field, T: AsRef<[F::Elem]> + AsMut<[F::Elem]> + FromIterator<F::Elem>> R
     ) -> Result<&mut [F::Elem], Result<&mut [F::Elem], Error>> {
         let is_some = self.is_some();
         let x = self
-            .get_or_insert_with(|| iter::repeat(F::zero()).take(len).collect())
+            .get_or_insert_with(|| iter::repeat_with(F::zero).take(len).collect())
             .as_mut();
 
         if is_some {
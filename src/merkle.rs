@@ -0,0 +1,410 @@
+//! Merkle authentication of shard sets.
+//!
+//! A `MerkleTree` is built over the shards of an encoded stripe, in order,
+//! and a `MerkleProof` is emitted for every shard. The root is distributed
+//! through a trusted channel, while the shards and their proofs may travel
+//! through untrusted peers.
+//!
+//! On receipt, every shard is wrapped in an `AuthenticatedShard` which checks
+//! the proof against the root. Shards failing the check are treated as absent
+//! when passed to `reconstruct` or `reconstruct_data`, and are rebuilt from
+//! the authentic ones.
+//!
+//! # Example
+//!
+//! ```
+//! # #[macro_use] extern crate reed_solomon_erasure;
+//! # use reed_solomon_erasure::galois_8::ReedSolomon;
+//! # use reed_solomon_erasure::merkle::{AuthenticatedShard, MerkleTree, Sha256};
+//! # fn main () {
+//! let r = ReedSolomon::new(3, 2).unwrap();
+//!
+//! let mut shards = shards!([0u8, 1, 2, 3],
+//!                          [4, 5, 6, 7],
+//!                          [8, 9, 10, 11],
+//!                          [0, 0, 0, 0],
+//!                          [0, 0, 0, 0]);
+//! r.encode(&mut shards).unwrap();
+//!
+//! let tree = MerkleTree::<Sha256>::new(&shards).unwrap();
+//! let root = tree.root().clone();
+//! let proofs: Vec<_> = (0..5).map(|i| tree.proof(i).unwrap()).collect();
+//!
+//! // a peer tampers with the first shard
+//! let mut received = shards.clone();
+//! received[0][0] = 100;
+//!
+//! let mut authenticated: Vec<_> = received
+//!     .into_iter()
+//!     .zip(proofs.iter())
+//!     .enumerate()
+//!     .map(|(i, (shard, proof))| AuthenticatedShard::new(&root, 5, i, Some(shard), proof))
+//!     .collect();
+//! assert!(authenticated[0].is_rejected());
+//!
+//! r.reconstruct(&mut authenticated).unwrap();
+//! assert_eq!(Some(shards[0].clone()), authenticated.swap_remove(0).into_inner());
+//! # }
+//! ```
+//!
+//! This module is only available with the `merkle` feature enabled.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::errors::Error;
+use crate::{Field, ReconstructShard};
+
+/// Hash function used to build a `MerkleTree`.
+///
+/// Leaves and inner nodes should be hashed with different domain
+/// separation, so that an inner node cannot be passed off as a leaf.
+pub trait MerkleHasher {
+    /// The output of the hash function.
+    type Digest: AsRef<[u8]> + Clone + PartialEq + Debug;
+
+    /// Hash a shard into a leaf.
+    fn hash_leaf(shard: &[u8]) -> Self::Digest;
+
+    /// Hash two child nodes into their parent.
+    fn hash_node(left: &Self::Digest, right: &Self::Digest) -> Self::Digest;
+}
+
+/// SHA-256 with a `0x00` prefix for leaves and a `0x01` prefix for inner nodes.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct Sha256;
+
+impl MerkleHasher for Sha256 {
+    type Digest = [u8; 32];
+
+    fn hash_leaf(shard: &[u8]) -> [u8; 32] {
+        use sha2::Digest;
+
+        let mut hasher = sha2::Sha256::new();
+        hasher.update([0x00]);
+        hasher.update(shard);
+        hasher.finalize().into()
+    }
+
+    fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        use sha2::Digest;
+
+        let mut hasher = sha2::Sha256::new();
+        hasher.update([0x01]);
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
+}
+
+/// Merkle tree over the shards of a stripe.
+///
+/// When a level has an odd number of nodes, the last node is promoted
+/// to the next level as is.
+#[derive(PartialEq, Debug, Clone)]
+pub struct MerkleTree<H: MerkleHasher> {
+    // levels[0] holds the leaves, the last level holds the root
+    levels: Vec<Vec<H::Digest>>,
+}
+
+impl<H: MerkleHasher> MerkleTree<H> {
+    /// Builds a Merkle tree over the shards, in order.
+    ///
+    /// Returns `Error::TooFewShards` if `shards` is empty.
+    pub fn new<T: AsRef<[u8]>>(shards: &[T]) -> Result<MerkleTree<H>, Error> {
+        if shards.is_empty() {
+            return Err(Error::TooFewShards);
+        }
+
+        let mut levels = Vec::new();
+        let mut level: Vec<H::Digest> = shards.iter().map(|s| H::hash_leaf(s.as_ref())).collect();
+
+        while level.len() > 1 {
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => H::hash_node(left, right),
+                    [single] => single.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+
+            levels.push(level);
+            level = next;
+        }
+        levels.push(level);
+
+        Ok(MerkleTree { levels })
+    }
+
+    /// The number of shards the tree was built over.
+    pub fn leaf_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// The root of the tree.
+    pub fn root(&self) -> &H::Digest {
+        &self.levels[self.levels.len() - 1][0]
+    }
+
+    /// Emits the inclusion proof of the shard at `index`.
+    ///
+    /// Returns `Error::InvalidIndex` if `index >= leaf_count`.
+    pub fn proof(&self, index: usize) -> Result<MerkleProof<H>, Error> {
+        if index >= self.leaf_count() {
+            return Err(Error::InvalidIndex);
+        }
+
+        let mut path = Vec::with_capacity(self.levels.len() - 1);
+        let mut i = index;
+        for level in self.levels[..self.levels.len() - 1].iter() {
+            let sibling = i ^ 1;
+            if sibling < level.len() {
+                path.push(level[sibling].clone());
+            }
+            i /= 2;
+        }
+
+        Ok(MerkleProof { index, path })
+    }
+}
+
+/// Inclusion proof of a single shard in a `MerkleTree`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct MerkleProof<H: MerkleHasher> {
+    index: usize,
+    path: Vec<H::Digest>,
+}
+
+impl<H: MerkleHasher> MerkleProof<H> {
+    /// Creates a proof from its parts, e.g. after receiving it over the wire.
+    pub fn new(index: usize, path: Vec<H::Digest>) -> MerkleProof<H> {
+        MerkleProof { index, path }
+    }
+
+    /// The index of the shard the proof is for.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The sibling hashes from the leaf up to the root.
+    pub fn path(&self) -> &[H::Digest] {
+        &self.path
+    }
+
+    /// Checks that `shard` is the shard at `self.index()` of the tree
+    /// with the given root and leaf count.
+    pub fn verify(&self, root: &H::Digest, leaf_count: usize, shard: &[u8]) -> bool {
+        if self.index >= leaf_count {
+            return false;
+        }
+
+        let mut path = self.path.iter();
+        let mut hash = H::hash_leaf(shard);
+        let mut i = self.index;
+        let mut width = leaf_count;
+
+        while width > 1 {
+            if i % 2 == 1 {
+                match path.next() {
+                    Some(sibling) => hash = H::hash_node(sibling, &hash),
+                    None => return false,
+                }
+            } else if i + 1 < width {
+                match path.next() {
+                    Some(sibling) => hash = H::hash_node(&hash, sibling),
+                    None => return false,
+                }
+            }
+            // otherwise the node is promoted as is
+
+            i /= 2;
+            width = width.div_ceil(2);
+        }
+
+        path.next().is_none() && &hash == root
+    }
+}
+
+/// A shard which is only considered present if its inclusion proof
+/// checked out on construction.
+///
+/// Implements `ReconstructShard`, so a slice of these can be passed
+/// to `reconstruct` directly. Rejected shards are treated as absent,
+/// and are overwritten with the reconstructed data.
+#[derive(PartialEq, Debug, Clone)]
+pub struct AuthenticatedShard<T> {
+    shard: Option<T>,
+    rejected: bool,
+}
+
+impl<T: AsRef<[u8]>> AuthenticatedShard<T> {
+    /// Checks `proof` for the shard expected at `index` of the tree
+    /// with the given root and leaf count.
+    ///
+    /// The shard is dropped if the proof does not check out,
+    /// or if it was produced for a different index.
+    pub fn new<H: MerkleHasher>(
+        root: &H::Digest,
+        leaf_count: usize,
+        index: usize,
+        shard: Option<T>,
+        proof: &MerkleProof<H>,
+    ) -> AuthenticatedShard<T> {
+        match shard {
+            Some(shard) => {
+                if proof.index() == index && proof.verify(root, leaf_count, shard.as_ref()) {
+                    AuthenticatedShard {
+                        shard: Some(shard),
+                        rejected: false,
+                    }
+                } else {
+                    AuthenticatedShard {
+                        shard: None,
+                        rejected: true,
+                    }
+                }
+            }
+            None => AuthenticatedShard {
+                shard: None,
+                rejected: false,
+            },
+        }
+    }
+}
+
+impl<T> AuthenticatedShard<T> {
+    /// Whether a shard was provided but failed proof verification.
+    ///
+    /// This stays `true` after the shard is reconstructed.
+    pub fn is_rejected(&self) -> bool {
+        self.rejected
+    }
+
+    /// Returns the shard, if present or reconstructed.
+    pub fn into_inner(self) -> Option<T> {
+        self.shard
+    }
+}
+
+impl<F: Field, T> ReconstructShard<F> for AuthenticatedShard<T>
+where
+    Option<T>: ReconstructShard<F>,
+{
+    fn len(&self) -> Option<usize> {
+        self.shard.len()
+    }
+
+    fn get(&mut self) -> Option<&mut [F::Elem]> {
+        self.shard.get()
+    }
+
+    fn get_or_initialize(
+        &mut self,
+        len: usize,
+    ) -> Result<&mut [F::Elem], Result<&mut [F::Elem], Error>> {
+        self.shard.get_or_initialize(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::galois_8;
+    use crate::tests::fill_random;
+
+    fn make_random_shards(count: usize, len: usize) -> Vec<Vec<u8>> {
+        let mut shards = vec![vec![0u8; len]; count];
+        for shard in shards.iter_mut() {
+            fill_random(shard);
+        }
+        shards
+    }
+
+    #[test]
+    fn test_proofs_verify() {
+        for leaf_count in 1..=33 {
+            let shards = make_random_shards(leaf_count, 10);
+            let tree = MerkleTree::<Sha256>::new(&shards).unwrap();
+
+            assert_eq!(leaf_count, tree.leaf_count());
+
+            for i in 0..leaf_count {
+                let proof = tree.proof(i).unwrap();
+                assert!(proof.verify(tree.root(), leaf_count, &shards[i]));
+
+                // wrong content, root or position
+                assert!(
+                    !proof.verify(tree.root(), leaf_count, &shards[(i + 1) % leaf_count])
+                        || leaf_count == 1
+                );
+                assert!(!proof.verify(&[0; 32], leaf_count, &shards[i]));
+                assert!(
+                    !MerkleProof::<Sha256>::new(i + leaf_count, proof.path().to_vec()).verify(
+                        tree.root(),
+                        leaf_count,
+                        &shards[i]
+                    )
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_merkle_error_handling() {
+        let shards: Vec<Vec<u8>> = vec![];
+        assert_eq!(
+            Error::TooFewShards,
+            MerkleTree::<Sha256>::new(&shards).unwrap_err()
+        );
+
+        let tree = MerkleTree::<Sha256>::new(&make_random_shards(3, 10)).unwrap();
+        assert_eq!(Error::InvalidIndex, tree.proof(3).unwrap_err());
+    }
+
+    #[test]
+    fn test_reconstruct_drops_tampered_shards() {
+        let r = galois_8::ReedSolomon::new(10, 4).unwrap();
+
+        let mut shards = make_random_shards(14, 100);
+        r.encode(&mut shards).unwrap();
+
+        let tree = MerkleTree::<Sha256>::new(&shards).unwrap();
+        let proofs: Vec<_> = (0..14).map(|i| tree.proof(i).unwrap()).collect();
+
+        let mut received: Vec<Option<Vec<u8>>> = shards.iter().cloned().map(Some).collect();
+        received[1].as_mut().unwrap()[0] ^= 1;
+        received[12].as_mut().unwrap()[99] ^= 1;
+        received[5] = None;
+        // a valid shard moved to the wrong position
+        received[7] = Some(shards[8].clone());
+
+        let mut authenticated: Vec<_> = received
+            .into_iter()
+            .enumerate()
+            .map(|(i, shard)| AuthenticatedShard::new(tree.root(), 14, i, shard, &proofs[i]))
+            .collect();
+
+        let rejected: Vec<_> = authenticated.iter().map(|s| s.is_rejected()).collect();
+        let mut expect = vec![false; 14];
+        expect[1] = true;
+        expect[7] = true;
+        expect[12] = true;
+        assert_eq!(expect, rejected);
+
+        r.reconstruct(&mut authenticated).unwrap();
+
+        let result: Vec<_> = authenticated
+            .into_iter()
+            .map(|s| s.into_inner().unwrap())
+            .collect();
+        assert_eq!(shards, result);
+    }
+}
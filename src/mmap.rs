@@ -0,0 +1,439 @@
+//! Memory-mapped file encoding and repair.
+//!
+//! A file is treated as `data_shard_count` data shards of `FileLayout::shard_size`
+//! bytes each, laid out one after another, with the last data shards padded
+//! with zeros. The parity shards are stored in one file each.
+//!
+//! The data shards are views into the memory-mapped file rather than copies,
+//! except for the at most one data shard which is only partially covered by the
+//! file, so files larger than the available memory can be encoded and repaired.
+//!
+//! As with any memory-mapped file, the files must not be modified by other
+//! processes while they are mapped.
+//!
+//! This module is only available with the `mmap` feature enabled.
+
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+
+use memmap2::{Mmap, MmapMut};
+
+use crate::errors::Error;
+use crate::galois_8::ReedSolomon;
+
+fn invalid_input(e: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e)
+}
+
+/// Layout of a file as data shards.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct FileLayout {
+    /// Length of the file in bytes.
+    pub file_len: u64,
+    /// Size of every shard in bytes.
+    pub shard_size: usize,
+}
+
+impl FileLayout {
+    /// Creates the layout of a file of `file_len` bytes, split into the
+    /// data shards of `codec`.
+    ///
+    /// Returns `Error::EmptyShard` if `file_len == 0`.
+    pub fn new(codec: &ReedSolomon, file_len: u64) -> Result<FileLayout, Error> {
+        if file_len == 0 {
+            return Err(Error::EmptyShard);
+        }
+
+        let shard_size = file_len.div_ceil(codec.data_shard_count() as u64);
+
+        Ok(FileLayout {
+            file_len,
+            shard_size: shard_size as usize,
+        })
+    }
+
+    // Range of the file covered by a data shard, which may be shorter than
+    // the shard or empty.
+    fn data_range(&self, i: usize) -> (usize, usize) {
+        let file_len = self.file_len as usize;
+        let start = (i * self.shard_size).min(file_len);
+        let end = ((i + 1) * self.shard_size).min(file_len);
+        (start, end)
+    }
+}
+
+fn check_parity_count<P>(codec: &ReedSolomon, parity: &[P]) -> io::Result<()> {
+    if parity.len() < codec.parity_shard_count() {
+        return Err(invalid_input(Error::TooFewParityShards));
+    }
+    if parity.len() > codec.parity_shard_count() {
+        return Err(invalid_input(Error::TooManyParityShards));
+    }
+    Ok(())
+}
+
+fn create_mapped(path: &Path, len: usize) -> io::Result<MmapMut> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    file.set_len(len as u64)?;
+
+    // Safety: the file must not be modified elsewhere while mapped,
+    // as documented for the module
+    unsafe { MmapMut::map_mut(&file) }
+}
+
+// A shard of the file being repaired, either a view into the file or
+// a padded copy of the end of the file.
+enum ShardView<'a> {
+    Mapped(&'a mut [u8]),
+    Padded(Vec<u8>, &'a mut [u8]),
+}
+
+impl AsRef<[u8]> for ShardView<'_> {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            ShardView::Mapped(view) => view,
+            ShardView::Padded(padded, _) => padded,
+        }
+    }
+}
+
+impl AsMut<[u8]> for ShardView<'_> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        match self {
+            ShardView::Mapped(view) => view,
+            ShardView::Padded(padded, _) => padded,
+        }
+    }
+}
+
+/// Encodes the file at `input`, writing the parity shards into the files at `parity`.
+///
+/// The parity files are created or overwritten.
+///
+/// Returns the layout of the input file, which is needed for repairing it.
+///
+/// Returns an error of kind `InvalidInput` wrapping `Error::TooFewParityShards`
+/// or `Error::TooManyParityShards` if the number of parity files does not
+/// match the parity shard count of the codec, or `Error::EmptyShard` if
+/// the input file is empty. I/O errors are passed through.
+///
+/// # Example
+///
+/// ```no_run
+/// # use reed_solomon_erasure::galois_8::ReedSolomon;
+/// # use reed_solomon_erasure::mmap::{encode_file, reconstruct_file};
+/// # fn main() -> std::io::Result<()> {
+/// let r = ReedSolomon::new(10, 4).unwrap();
+/// let parity = ["disk.img.p0", "disk.img.p1", "disk.img.p2", "disk.img.p3"];
+///
+/// let layout = encode_file(&r, "disk.img", &parity)?;
+///
+/// // after the second and third data shard of the image were damaged
+/// reconstruct_file(&r, "disk.img", &layout, &[1, 2], &parity)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn encode_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    codec: &ReedSolomon,
+    input: P,
+    parity: &[Q],
+) -> io::Result<FileLayout> {
+    check_parity_count(codec, parity)?;
+
+    let file = File::open(input)?;
+    let layout = FileLayout::new(codec, file.metadata()?.len()).map_err(invalid_input)?;
+
+    // Safety: the file must not be modified elsewhere while mapped,
+    // as documented for the module
+    let input = unsafe { Mmap::map(&file)? };
+
+    let data: Vec<Cow<[u8]>> = (0..codec.data_shard_count())
+        .map(|i| {
+            let (start, end) = layout.data_range(i);
+            if end - start == layout.shard_size {
+                Cow::Borrowed(&input[start..end])
+            } else {
+                let mut padded = vec![0u8; layout.shard_size];
+                padded[..end - start].copy_from_slice(&input[start..end]);
+                Cow::Owned(padded)
+            }
+        })
+        .collect();
+
+    let mut outputs = parity
+        .iter()
+        .map(|path| create_mapped(path.as_ref(), layout.shard_size))
+        .collect::<io::Result<Vec<MmapMut>>>()?;
+
+    codec
+        .encode_sep(&data, &mut outputs)
+        .map_err(invalid_input)?;
+
+    for output in outputs.iter() {
+        output.flush()?;
+    }
+
+    Ok(layout)
+}
+
+/// Repairs the file at `input` and the parity files at `parity` in place.
+///
+/// `missing_data` lists the indices of the data shards of the file which
+/// are damaged, e.g. as found by checksums. If the file does not exist or
+/// is not of the length in `layout`, it is recreated and all of its data
+/// shards are considered missing. Parity files which do not exist or are
+/// not of the shard size are considered missing, and are recreated.
+///
+/// Returns an error of kind `InvalidInput` wrapping `Error::TooFewParityShards`
+/// or `Error::TooManyParityShards` if the number of parity files does not
+/// match the parity shard count of the codec, or `Error::InvalidIndex` if
+/// an index in `missing_data` is out of range. Returns an error of kind
+/// `InvalidData` wrapping `Error::TooFewShardsPresent` if too many shards
+/// are missing. Other I/O errors are passed through.
+pub fn reconstruct_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    codec: &ReedSolomon,
+    input: P,
+    layout: &FileLayout,
+    missing_data: &[usize],
+    parity: &[Q],
+) -> io::Result<()> {
+    check_parity_count(codec, parity)?;
+
+    let data_shards = codec.data_shard_count();
+    if missing_data.iter().any(|&i| i >= data_shards) {
+        return Err(invalid_input(Error::InvalidIndex));
+    }
+
+    let input = input.as_ref();
+    let input_present = match std::fs::metadata(input) {
+        Ok(metadata) => metadata.len() == layout.file_len,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => false,
+        Err(e) => return Err(e),
+    };
+
+    let mut parity_present = Vec::with_capacity(parity.len());
+    for path in parity.iter() {
+        parity_present.push(match std::fs::metadata(path) {
+            Ok(metadata) => metadata.len() == layout.shard_size as u64,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => false,
+            Err(e) => return Err(e),
+        });
+    }
+
+    let present_count = parity_present.iter().filter(|&&present| present).count()
+        + if input_present {
+            (0..data_shards)
+                .filter(|i| !missing_data.contains(i))
+                .count()
+        } else {
+            0
+        };
+    if present_count < data_shards {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            Error::TooFewShardsPresent,
+        ));
+    }
+
+    let mut file_map = create_mapped(input, layout.file_len as usize)?;
+    let mut parity_maps = parity
+        .iter()
+        .map(|path| create_mapped(path.as_ref(), layout.shard_size))
+        .collect::<io::Result<Vec<MmapMut>>>()?;
+
+    {
+        let mut shards: Vec<(ShardView, bool)> = Vec::with_capacity(codec.total_shard_count());
+
+        let mut rest: &mut [u8] = &mut file_map;
+        for i in 0..data_shards {
+            let (start, end) = layout.data_range(i);
+            let (view, tail) = rest.split_at_mut(end - start);
+            rest = tail;
+
+            let present = input_present && !missing_data.contains(&i);
+            let view = if end - start == layout.shard_size {
+                ShardView::Mapped(view)
+            } else {
+                let mut padded = vec![0u8; layout.shard_size];
+                padded[..view.len()].copy_from_slice(view);
+                ShardView::Padded(padded, view)
+            };
+            shards.push((view, present));
+        }
+
+        for (map, &present) in parity_maps.iter_mut().zip(parity_present.iter()) {
+            shards.push((ShardView::Mapped(map), present));
+        }
+
+        codec
+            .reconstruct(&mut shards)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        for (view, _) in shards.iter_mut() {
+            if let ShardView::Padded(padded, view) = view {
+                let len = view.len();
+                view.copy_from_slice(&padded[..len]);
+            }
+        }
+    }
+
+    file_map.flush()?;
+    for map in parity_maps.iter() {
+        map.flush()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fill_random;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // Scratch directory removed on drop.
+    struct ScratchDir(PathBuf);
+
+    impl ScratchDir {
+        fn new() -> ScratchDir {
+            let dir =
+                std::env::temp_dir().join(format!("rse-mmap-test-{:016x}", rand::random::<u64>()));
+            fs::create_dir(&dir).unwrap();
+            ScratchDir(dir)
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_encode_file_same_as_encode() {
+        let r = ReedSolomon::new(4, 2).unwrap();
+        let dir = ScratchDir::new();
+        let input = dir.path("input");
+        let parity = [dir.path("p0"), dir.path("p1")];
+
+        for &len in [1, 3, 4, 399, 400, 401, 1234].iter() {
+            let mut data = vec![0u8; len];
+            fill_random(&mut data);
+            fs::write(&input, &data).unwrap();
+
+            let layout = encode_file(&r, &input, &parity).unwrap();
+            assert_eq!(FileLayout::new(&r, len as u64).unwrap(), layout);
+
+            let mut shards: Vec<Vec<u8>> = data
+                .chunks(layout.shard_size)
+                .map(|chunk| chunk.to_vec())
+                .collect();
+            shards.resize(6, vec![]);
+            for shard in shards.iter_mut() {
+                shard.resize(layout.shard_size, 0);
+            }
+            r.encode(&mut shards).unwrap();
+
+            assert_eq!(shards[4], fs::read(&parity[0]).unwrap());
+            assert_eq!(shards[5], fs::read(&parity[1]).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_reconstruct_file() {
+        let r = ReedSolomon::new(4, 2).unwrap();
+        let dir = ScratchDir::new();
+        let input = dir.path("input");
+        let parity = [dir.path("p0"), dir.path("p1")];
+
+        let mut data = vec![0u8; 1001];
+        fill_random(&mut data);
+        fs::write(&input, &data).unwrap();
+        let layout = encode_file(&r, &input, &parity).unwrap();
+        let p0 = fs::read(&parity[0]).unwrap();
+        let p1 = fs::read(&parity[1]).unwrap();
+
+        // damaged data shards, including the padded last one
+        let mut damaged = data.clone();
+        for b in damaged[251..502].iter_mut() {
+            *b = 0xAA;
+        }
+        for b in damaged[753..].iter_mut() {
+            *b = 0xAA;
+        }
+        fs::write(&input, &damaged).unwrap();
+        reconstruct_file(&r, &input, &layout, &[1, 3], &parity).unwrap();
+        assert_eq!(data, fs::read(&input).unwrap());
+
+        // a missing parity file and a damaged data shard
+        fs::remove_file(&parity[1]).unwrap();
+        let mut damaged = data.clone();
+        damaged[0] ^= 1;
+        fs::write(&input, &damaged).unwrap();
+        reconstruct_file(&r, &input, &layout, &[0], &parity).unwrap();
+        assert_eq!(data, fs::read(&input).unwrap());
+        assert_eq!(p0, fs::read(&parity[0]).unwrap());
+        assert_eq!(p1, fs::read(&parity[1]).unwrap());
+
+        // a missing file cannot be recovered from the parity files alone
+        fs::remove_file(&input).unwrap();
+        let e = reconstruct_file(&r, &input, &layout, &[], &parity).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+    }
+
+    #[test]
+    fn test_reconstruct_file_from_parity() {
+        // with at least as many parity shards as data shards, the whole file
+        // can be recreated from the parity files
+        let r = ReedSolomon::new(2, 3).unwrap();
+        let dir = ScratchDir::new();
+        let input = dir.path("input");
+        let parity = [dir.path("p0"), dir.path("p1"), dir.path("p2")];
+
+        let mut data = vec![0u8; 777];
+        fill_random(&mut data);
+        fs::write(&input, &data).unwrap();
+        let layout = encode_file(&r, &input, &parity).unwrap();
+
+        fs::remove_file(&input).unwrap();
+        fs::remove_file(&parity[0]).unwrap();
+        reconstruct_file(&r, &input, &layout, &[], &parity).unwrap();
+        assert_eq!(data, fs::read(&input).unwrap());
+    }
+
+    #[test]
+    fn test_file_error_handling() {
+        let r = ReedSolomon::new(4, 2).unwrap();
+        let dir = ScratchDir::new();
+        let input = dir.path("input");
+        let parity = [dir.path("p0"), dir.path("p1")];
+
+        assert_eq!(Error::EmptyShard, FileLayout::new(&r, 0).unwrap_err());
+
+        fs::write(&input, []).unwrap();
+        let e = encode_file(&r, &input, &parity).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+
+        fs::write(&input, [1, 2, 3, 4, 5]).unwrap();
+        let e = encode_file(&r, &input, &parity[..1]).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+
+        let layout = encode_file(&r, &input, &parity).unwrap();
+        let e = reconstruct_file(&r, &input, &layout, &[4], &parity).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+        let e = reconstruct_file(&r, &input, &layout, &[0, 1, 2], &parity).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+    }
+}
@@ -0,0 +1,822 @@
+//! Reed-Solomon erasure code over the prime field GF(65537) with number
+//! theoretic transforms.
+//!
+//! The fields of the rest of this crate have characteristic 2, so addition
+//! and subtraction are both exclusive or. Applications which commit to their
+//! shards with proofs over a prime field need the parity to be computed in
+//! that field instead. `Field` is GF(65537), the field of integers modulo
+//! the Fermat prime `2^16 + 1`, and works with `crate::ReedSolomon` like
+//! any other field:
+//!
+//! ```
+//! # use reed_solomon_erasure::ntt::Field;
+//! let r = reed_solomon_erasure::ReedSolomon::<Field>::new(3, 2).unwrap();
+//!
+//! let mut shards = vec![vec![1, 2], vec![3, 4], vec![65535, 65536], vec![0; 2], vec![0; 2]];
+//! r.encode(&mut shards).unwrap();
+//! assert!(r.verify(&shards).unwrap());
+//! ```
+//!
+//! The multiplicative group of GF(65537) has order `2^16`, so the field has
+//! roots of unity of every power of two up to `2^16` and polynomials can be
+//! evaluated and interpolated with NTTs. `ReedSolomon` of this module uses
+//! them for O(n log n) encoding and decoding: with `K` the number of data
+//! shards rounded up to a power of two, the data shards are the values of a
+//! polynomial of degree less than `K` on the subgroup of order `K`, and the
+//! parity shards are its values on the other points of a larger subgroup.
+//! Missing shards are recovered with the polynomial vanishing on their
+//! points. The parity is different from the one of `crate::ReedSolomon`.
+//!
+//! Elements are `u32` and must be less than `MODULUS`. Elements of parity
+//! shards may be `65536`, so bytes do not map to elements one to one: two
+//! bytes of data fit in an element, but an element of parity needs more.
+//!
+//! The number of data shards rounded up to a power of two plus the number
+//! of parity shards may not exceed `MAX_SHARDS`.
+//!
+//! # Example
+//!
+//! ```
+//! # use reed_solomon_erasure::ntt::ReedSolomon;
+//! let r = ReedSolomon::new(100, 30).unwrap();
+//!
+//! let mut shards = vec![vec![0u32; 16]; 130];
+//! for (i, shard) in shards[..100].iter_mut().enumerate() {
+//!     shard[0] = i as u32;
+//! }
+//! r.encode(&mut shards).unwrap();
+//!
+//! let mut received: Vec<_> = shards.iter().cloned().map(Some).collect();
+//! for i in 0..30 {
+//!     received[i * 4] = None;
+//! }
+//! r.reconstruct(&mut received).unwrap();
+//! assert_eq!(shards, received.into_iter().map(Option::unwrap).collect::<Vec<_>>());
+//! ```
+
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{Error, ReconstructShard};
+
+/// The modulus of the field, `2^16 + 1`.
+pub const MODULUS: u32 = 65537;
+
+/// The maximum number of data shards rounded up to a power of two plus
+/// parity shards.
+///
+/// Decoding evaluates polynomials on a coset of the subgroup holding the
+/// shards, so the subgroup may be at most half of the multiplicative group.
+pub const MAX_SHARDS: usize = 32768;
+
+// A generator of the multiplicative group.
+const GENERATOR: u32 = 3;
+
+fn add(a: u32, b: u32) -> u32 {
+    let sum = a + b;
+    if sum >= MODULUS {
+        sum - MODULUS
+    } else {
+        sum
+    }
+}
+
+fn sub(a: u32, b: u32) -> u32 {
+    if a >= b {
+        a - b
+    } else {
+        a + MODULUS - b
+    }
+}
+
+fn mul(a: u32, b: u32) -> u32 {
+    (a as u64 * b as u64 % MODULUS as u64) as u32
+}
+
+fn exp(a: u32, mut n: usize) -> u32 {
+    let mut result = 1;
+    let mut square = a;
+    while n > 0 {
+        if n & 1 == 1 {
+            result = mul(result, square);
+        }
+        square = mul(square, square);
+        n >>= 1;
+    }
+    result
+}
+
+fn inv(a: u32) -> u32 {
+    if a == 0 {
+        panic!("Divisor is 0")
+    }
+    exp(a, MODULUS as usize - 2)
+}
+
+fn div(a: u32, b: u32) -> u32 {
+    mul(a, inv(b))
+}
+
+// A primitive `n`th root of unity, for `n` a power of two.
+fn root_of_unity(n: usize) -> u32 {
+    exp(GENERATOR, (MODULUS as usize - 1) / n)
+}
+
+// Reverses the lowest `bits` bits of `i`.
+fn bit_reverse(i: usize, bits: u32) -> usize {
+    if bits == 0 {
+        0
+    } else {
+        i.reverse_bits() >> (usize::BITS - bits)
+    }
+}
+
+/// The field GF(65537).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Field;
+
+impl crate::Field for Field {
+    const ORDER: usize = MODULUS as usize;
+
+    type Elem = u32;
+
+    const WRITES_OUTPUTS_FIRST: bool = true;
+
+    fn add(a: u32, b: u32) -> u32 {
+        add(a, b)
+    }
+
+    fn sub(a: u32, b: u32) -> u32 {
+        sub(a, b)
+    }
+
+    fn mul(a: u32, b: u32) -> u32 {
+        mul(a, b)
+    }
+
+    fn div(a: u32, b: u32) -> u32 {
+        div(a, b)
+    }
+
+    fn exp(elem: u32, n: usize) -> u32 {
+        exp(elem, n)
+    }
+
+    fn zero() -> u32 {
+        0
+    }
+
+    fn one() -> u32 {
+        1
+    }
+
+    fn nth_internal(n: usize) -> u32 {
+        n as u32
+    }
+}
+
+fn mul_slice(c: u32, input: &[u32], out: &mut [u32]) {
+    for (o, &i) in out.iter_mut().zip(input) {
+        *o = mul(c, i);
+    }
+}
+
+// Shards of the NTT, stored contiguously.
+struct Work {
+    buf: Vec<u32>,
+    shard_len: usize,
+}
+
+impl Work {
+    fn new(count: usize, shard_len: usize) -> Work {
+        Work {
+            buf: vec![0; count * shard_len],
+            shard_len,
+        }
+    }
+
+    fn shard(&self, i: usize) -> &[u32] {
+        &self.buf[i * self.shard_len..(i + 1) * self.shard_len]
+    }
+
+    fn shard_mut(&mut self, i: usize) -> &mut [u32] {
+        &mut self.buf[i * self.shard_len..(i + 1) * self.shard_len]
+    }
+
+    fn scale(&mut self, i: usize, c: u32) {
+        if c != 1 {
+            for x in self.shard_mut(i) {
+                *x = mul(c, *x);
+            }
+        }
+    }
+
+    // Shards `i` and `j`, with `i < j`.
+    fn pair(&mut self, i: usize, j: usize) -> (&mut [u32], &mut [u32]) {
+        let (left, right) = self.buf.split_at_mut(j * self.shard_len);
+        (
+            &mut left[i * self.shard_len..(i + 1) * self.shard_len],
+            &mut right[..self.shard_len],
+        )
+    }
+
+    // NTT of the first `n` shards, taking coefficients in natural order to
+    // values in bit-reversed order.
+    fn ntt(&mut self, n: usize) {
+        let root = root_of_unity(n);
+        let mut width = n / 2;
+        while width > 0 {
+            let step = exp(root, n / (2 * width));
+            for start in (0..n).step_by(2 * width) {
+                let mut w = 1;
+                for i in start..start + width {
+                    let (x, y) = self.pair(i, i + width);
+                    for (x, y) in x.iter_mut().zip(y.iter_mut()) {
+                        let (a, b) = (*x, *y);
+                        *x = add(a, b);
+                        *y = mul(sub(a, b), w);
+                    }
+                    w = mul(w, step);
+                }
+            }
+            width /= 2;
+        }
+    }
+
+    // Inverse of `ntt`, taking values in bit-reversed order to coefficients
+    // in natural order.
+    fn intt(&mut self, n: usize) {
+        let root = inv(root_of_unity(n));
+        let mut width = 1;
+        while width < n {
+            let step = exp(root, n / (2 * width));
+            for start in (0..n).step_by(2 * width) {
+                let mut w = 1;
+                for i in start..start + width {
+                    let (x, y) = self.pair(i, i + width);
+                    for (x, y) in x.iter_mut().zip(y.iter_mut()) {
+                        let (a, b) = (*x, mul(*y, w));
+                        *x = add(a, b);
+                        *y = sub(a, b);
+                    }
+                    w = mul(w, step);
+                }
+            }
+            width *= 2;
+        }
+
+        let n_inv = inv(n as u32);
+        for i in 0..n {
+            self.scale(i, n_inv);
+        }
+    }
+}
+
+// The values of the polynomial with `coeffs` at `shift` times the `n`th
+// roots of unity, in bit-reversed order.
+fn evaluate(coeffs: &[u32], n: usize, shift: u32) -> Vec<u32> {
+    let mut work = Work::new(n, 1);
+    let mut power = 1;
+    for (x, &c) in work.buf.iter_mut().zip(coeffs) {
+        *x = mul(c, power);
+        power = mul(power, shift);
+    }
+    work.ntt(n);
+    work.buf
+}
+
+fn poly_mul(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let len = a.len() + b.len() - 1;
+    let n = len.next_power_of_two();
+    let mut x = Work::new(n, 1);
+    let mut y = Work::new(n, 1);
+    x.buf[..a.len()].copy_from_slice(a);
+    y.buf[..b.len()].copy_from_slice(b);
+    x.ntt(n);
+    y.ntt(n);
+    for (x, y) in x.buf.iter_mut().zip(y.buf.iter()) {
+        *x = mul(*x, *y);
+    }
+    x.intt(n);
+    x.buf.truncate(len);
+    x.buf
+}
+
+// The coefficients of the monic polynomial with roots `points`.
+fn vanishing(points: &[u32]) -> Vec<u32> {
+    if points.len() > 64 {
+        let (left, right) = points.split_at(points.len() / 2);
+        return poly_mul(&vanishing(left), &vanishing(right));
+    }
+
+    let mut coeffs = vec![0; points.len() + 1];
+    coeffs[0] = 1;
+    for (degree, &p) in points.iter().enumerate() {
+        // multiply by (x - p)
+        for i in (0..=degree + 1).rev() {
+            let shifted = if i > 0 { coeffs[i - 1] } else { 0 };
+            coeffs[i] = sub(shifted, mul(p, coeffs[i]));
+        }
+    }
+    coeffs
+}
+
+/// Reed-Solomon erasure code encoder/decoder over GF(65537) using NTTs.
+///
+/// The methods behave like those of `crate::ReedSolomon`, with shards of
+/// `u32` elements less than `MODULUS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReedSolomon {
+    data_shard_count: usize,
+    parity_shard_count: usize,
+    total_shard_count: usize,
+}
+
+impl ReedSolomon {
+    /// Creates a new instance of the codec.
+    ///
+    /// Returns `Error::TooFewDataShards` if `data_shards == 0`,
+    /// `Error::TooFewParityShards` if `parity_shards == 0`, and
+    /// `Error::TooManyShards` if `data_shards` rounded up to a power of two
+    /// plus `parity_shards` exceeds `MAX_SHARDS`.
+    pub fn new(data_shards: usize, parity_shards: usize) -> Result<ReedSolomon, Error> {
+        if data_shards == 0 {
+            return Err(Error::TooFewDataShards);
+        }
+        if parity_shards == 0 {
+            return Err(Error::TooFewParityShards);
+        }
+        if data_shards.next_power_of_two() + parity_shards > MAX_SHARDS {
+            return Err(Error::TooManyShards);
+        }
+
+        Ok(ReedSolomon {
+            data_shard_count: data_shards,
+            parity_shard_count: parity_shards,
+            total_shard_count: data_shards + parity_shards,
+        })
+    }
+
+    pub fn data_shard_count(&self) -> usize {
+        self.data_shard_count
+    }
+
+    pub fn parity_shard_count(&self) -> usize {
+        self.parity_shard_count
+    }
+
+    pub fn total_shard_count(&self) -> usize {
+        self.total_shard_count
+    }
+
+    // The orders of the subgroups holding the data shards and all shards.
+    fn domains(&self) -> (usize, usize) {
+        let data_domain = self.data_shard_count.next_power_of_two();
+        let domain = (data_domain + self.parity_shard_count).next_power_of_two();
+        (data_domain, domain)
+    }
+
+    // The position of shard `i` among the values of the NTT, which are in
+    // bit-reversed order. The data shards are at positions below the order
+    // of their subgroup, and the parity shards follow them.
+    fn position(&self, i: usize) -> usize {
+        let (data_domain, _) = self.domains();
+        if i < self.data_shard_count {
+            bit_reverse(i, data_domain.trailing_zeros())
+        } else {
+            data_domain + i - self.data_shard_count
+        }
+    }
+
+    // The values of the polynomial of `data` on the whole domain.
+    fn encode_work<T: AsRef<[u32]>>(&self, data: &[T], shard_len: usize) -> Work {
+        let (data_domain, domain) = self.domains();
+        let mut work = Work::new(domain, shard_len);
+        for (i, shard) in data.iter().enumerate() {
+            work.shard_mut(self.position(i))
+                .copy_from_slice(shard.as_ref());
+        }
+        work.intt(data_domain);
+        work.ntt(domain);
+        work
+    }
+
+    /// Constructs the parity shards.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    pub fn encode<T, U>(&self, mut shards: T) -> Result<(), Error>
+    where
+        T: AsRef<[U]> + AsMut<[U]>,
+        U: AsRef<[u32]> + AsMut<[u32]>,
+    {
+        let slices: &mut [U] = shards.as_mut();
+
+        check_piece_count!(all => self, slices);
+
+        let (input, output) = slices.split_at_mut(self.data_shard_count);
+
+        self.encode_sep(&*input, output)
+    }
+
+    /// Constructs the parity shards using a read-only view into the
+    /// data shards.
+    ///
+    /// The slots where the parity shards sit at will be overwritten.
+    pub fn encode_sep<T: AsRef<[u32]>, U: AsRef<[u32]> + AsMut<[u32]>>(
+        &self,
+        data: &[T],
+        parity: &mut [U],
+    ) -> Result<(), Error> {
+        check_piece_count!(data => self, data);
+        check_piece_count!(parity => self, parity);
+        check_slices!(multi => data, multi => parity);
+
+        let shard_len = data[0].as_ref().len();
+        let work = self.encode_work(data, shard_len);
+        for (i, shard) in parity.iter_mut().enumerate() {
+            let pos = self.position(self.data_shard_count + i);
+            shard.as_mut().copy_from_slice(work.shard(pos));
+        }
+
+        Ok(())
+    }
+
+    /// Checks if the parity shards are correct.
+    pub fn verify<T: AsRef<[u32]>>(&self, slices: &[T]) -> Result<bool, Error> {
+        check_piece_count!(all => self, slices);
+        check_slices!(multi => slices);
+
+        let shard_len = slices[0].as_ref().len();
+        let (data, parity) = slices.split_at(self.data_shard_count);
+        let work = self.encode_work(data, shard_len);
+
+        Ok(parity.iter().enumerate().all(|(i, shard)| {
+            shard.as_ref() == work.shard(self.position(self.data_shard_count + i))
+        }))
+    }
+
+    /// Reconstructs all shards.
+    ///
+    /// The shards marked not present are only overwritten when no error
+    /// is detected. All provided shards must have the same length.
+    ///
+    /// This means if the method returns an `Error`, then nothing is touched.
+    pub fn reconstruct<T: ReconstructShard<Field>>(&self, slices: &mut [T]) -> Result<(), Error> {
+        self.reconstruct_internal(slices, false)
+    }
+
+    /// Reconstructs only the data shards.
+    ///
+    /// The shards marked not present are only overwritten when no error
+    /// is detected. All provided shards must have the same length.
+    ///
+    /// This means if the method returns an `Error`, then nothing is touched.
+    pub fn reconstruct_data<T: ReconstructShard<Field>>(
+        &self,
+        slices: &mut [T],
+    ) -> Result<(), Error> {
+        self.reconstruct_internal(slices, true)
+    }
+
+    fn reconstruct_internal<T: ReconstructShard<Field>>(
+        &self,
+        shards: &mut [T],
+        data_only: bool,
+    ) -> Result<(), Error> {
+        check_piece_count!(all => self, shards);
+
+        let data_shard_count = self.data_shard_count;
+
+        let mut number_present = 0;
+        let mut shard_len = None;
+
+        for shard in shards.iter() {
+            if let Some(len) = shard.len() {
+                if len == 0 {
+                    return Err(Error::EmptyShard);
+                }
+                number_present += 1;
+                if let Some(old_len) = shard_len {
+                    if len != old_len {
+                        return Err(Error::IncorrectShardSize);
+                    }
+                }
+                shard_len = Some(len);
+            }
+        }
+
+        if number_present == self.total_shard_count {
+            return Ok(());
+        }
+
+        if number_present < data_shard_count {
+            return Err(Error::TooFewShardsPresent);
+        }
+
+        let shard_len = shard_len.expect("at least one shard present; qed");
+
+        let present: Vec<bool> = shards.iter().map(|shard| shard.len().is_some()).collect();
+
+        let mut slices: Vec<&mut [u32]> = Vec::with_capacity(self.total_shard_count);
+        for (i, shard) in shards.iter_mut().enumerate() {
+            let slice = if i >= data_shard_count && data_only {
+                shard.get().unwrap_or(&mut [])
+            } else {
+                match shard.get_or_initialize(shard_len) {
+                    Ok(slice) | Err(Ok(slice)) => slice,
+                    Err(Err(e)) => return Err(e),
+                }
+            };
+            slices.push(slice);
+        }
+
+        let work = if present[..data_shard_count].contains(&false) {
+            self.decode(&slices, &present, shard_len)
+        } else if !data_only {
+            self.encode_work(&slices[..data_shard_count], shard_len)
+        } else {
+            return Ok(());
+        };
+
+        let count = if data_only {
+            data_shard_count
+        } else {
+            self.total_shard_count
+        };
+        for (i, slice) in slices[..count].iter_mut().enumerate() {
+            if !present[i] {
+                slice.copy_from_slice(work.shard(self.position(i)));
+            }
+        }
+
+        Ok(())
+    }
+
+    // The values of the polynomial of the data on the whole domain, from the
+    // present shards.
+    //
+    // With `z` the polynomial vanishing on the points of the missing shards
+    // and of the unused parity shards, the values of `f z` are known on the
+    // whole domain. `f` is `f z` divided by `z` on a coset of the domain,
+    // where `z` has no roots.
+    fn decode(&self, slices: &[&mut [u32]], present: &[bool], shard_len: usize) -> Work {
+        let (data_domain, domain) = self.domains();
+        let bits = domain.trailing_zeros();
+
+        // the padding of the data is known to be zero, and the values after
+        // the parity shards are unknown
+        let mut known = vec![false; domain];
+        let data_bits = data_domain.trailing_zeros();
+        for i in 0..data_domain {
+            known[bit_reverse(i, data_bits)] = i >= self.data_shard_count || present[i];
+        }
+        for (i, &p) in present.iter().enumerate().skip(self.data_shard_count) {
+            known[self.position(i)] = p;
+        }
+
+        let root = root_of_unity(domain);
+        let missing: Vec<u32> = (0..domain)
+            .filter(|&pos| !known[pos])
+            .map(|pos| exp(root, bit_reverse(pos, bits)))
+            .collect();
+        let z = vanishing(&missing);
+        let z_domain = evaluate(&z, domain, 1);
+        let z_coset = evaluate(&z, domain, GENERATOR);
+
+        let mut work = Work::new(domain, shard_len);
+        for (i, slice) in slices.iter().enumerate() {
+            if present[i] {
+                let pos = self.position(i);
+                mul_slice(z_domain[pos], slice, work.shard_mut(pos));
+            }
+        }
+
+        // from the values of `f z` on the domain to those on the coset
+        work.intt(domain);
+        let mut power = 1;
+        for i in 0..domain {
+            work.scale(i, power);
+            power = mul(power, GENERATOR);
+        }
+        work.ntt(domain);
+
+        for (i, &z) in z_coset.iter().enumerate() {
+            work.scale(i, inv(z));
+        }
+
+        // from the values of `f` on the coset to those on the domain
+        work.intt(domain);
+        let generator_inv = inv(GENERATOR);
+        let mut power = 1;
+        for i in 0..domain {
+            work.scale(i, power);
+            power = mul(power, generator_inv);
+        }
+        work.ntt(domain);
+
+        work
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::Field as _;
+
+    fn random_shards(count: usize, len: usize) -> Vec<Vec<u32>> {
+        (0..count)
+            .map(|_| (0..len).map(|_| rand::random::<u32>() % MODULUS).collect())
+            .collect()
+    }
+
+    quickcheck! {
+        fn qc_add_sub(a: u32, b: u32) -> bool {
+            let (a, b) = (a % MODULUS, b % MODULUS);
+            sub(add(a, b), b) == a
+        }
+
+        fn qc_mul_div(a: u32, b: u32) -> bool {
+            let (a, b) = (a % MODULUS, b % MODULUS);
+            b == 0 || div(mul(a, b), b) == a
+        }
+
+        fn qc_mul_distributivity(a: u32, b: u32, c: u32) -> bool {
+            let (a, b, c) = (a % MODULUS, b % MODULUS, c % MODULUS);
+            mul(a, sub(b, c)) == sub(mul(a, b), mul(a, c))
+        }
+
+        fn qc_exp(a: u32, n: usize) -> bool {
+            let (a, n) = (a % MODULUS, n % 1000);
+            exp(a, n) == (0..n).fold(1, |acc, _| mul(acc, a))
+        }
+    }
+
+    #[test]
+    fn test_field() {
+        assert_eq!(0, add(65536, 1));
+        assert_eq!(65536, sub(0, 1));
+        // 65536 is -1
+        assert_eq!(1, mul(65536, 65536));
+        assert_eq!(65536, exp(GENERATOR, 32768));
+        assert_eq!(1, exp(GENERATOR, 65536));
+        assert_eq!(65536, Field::sub(Field::zero(), Field::one()));
+    }
+
+    #[test]
+    fn test_ntt() {
+        for &n in [1, 2, 8, 1024].iter() {
+            let coeffs = random_shards(1, n).pop().unwrap();
+            let values = evaluate(&coeffs, n, 1);
+            let root = root_of_unity(n);
+            for (pos, &value) in values.iter().enumerate() {
+                let x = exp(root, bit_reverse(pos, n.trailing_zeros()));
+                let expected = coeffs.iter().rev().fold(0, |acc, &c| add(mul(acc, x), c));
+                assert_eq!(expected, value);
+            }
+
+            let mut work = Work::new(n, 1);
+            work.buf.copy_from_slice(&values);
+            work.intt(n);
+            assert_eq!(coeffs, work.buf);
+        }
+    }
+
+    #[test]
+    fn test_vanishing() {
+        let points: Vec<u32> = (0..200).map(|i| i * 7 + 1).collect();
+        let z = vanishing(&points);
+        assert_eq!(201, z.len());
+        assert_eq!(1, z[200]);
+        for &p in points.iter() {
+            assert_eq!(0, z.iter().rev().fold(0, |acc, &c| add(mul(acc, p), c)));
+        }
+    }
+
+    #[test]
+    fn test_matrix_codec() {
+        // Gaussian elimination must subtract in this field.
+        let r = crate::ReedSolomon::<Field>::new(10, 4).unwrap();
+        let mut shards = random_shards(14, 50);
+        r.encode(&mut shards).unwrap();
+        assert!(r.verify(&shards).unwrap());
+
+        let mut some: Vec<_> = shards.iter().cloned().map(Some).collect();
+        for &i in [0, 3, 7, 12].iter() {
+            some[i] = None;
+        }
+        r.reconstruct(&mut some).unwrap();
+        let some: Vec<_> = some.into_iter().map(Option::unwrap).collect();
+        assert_eq!(shards, some);
+    }
+
+    #[test]
+    fn test_all_erasures() {
+        for &(data, parity) in [(1, 1), (3, 2), (4, 4), (5, 3), (2, 7)].iter() {
+            let r = ReedSolomon::new(data, parity).unwrap();
+            let total = data + parity;
+            let mut shards = random_shards(total, 3);
+            r.encode(&mut shards).unwrap();
+            assert!(r.verify(&shards).unwrap());
+
+            for mask in 0u32..1 << total {
+                if (mask.count_ones() as usize) < data {
+                    continue;
+                }
+                let mut some: Vec<_> = shards
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| {
+                        if mask & 1 << i != 0 {
+                            Some(s.clone())
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                r.reconstruct(&mut some).unwrap();
+                let some: Vec<_> = some.into_iter().map(Option::unwrap).collect();
+                assert_eq!(shards, some);
+            }
+        }
+    }
+
+    #[test]
+    fn test_many_shards() {
+        for &(data, parity) in [(1000, 300), (100, 1000), (3000, 5000)].iter() {
+            let r = ReedSolomon::new(data, parity).unwrap();
+            let total = data + parity;
+            let mut shards = random_shards(total, 4);
+            for shard in shards[data..].iter_mut() {
+                shard.iter_mut().for_each(|x| *x = 0);
+            }
+            r.encode(&mut shards).unwrap();
+            assert!(r.verify(&shards).unwrap());
+
+            let mut some: Vec<_> = shards.iter().cloned().map(Some).collect();
+            let mut lost = 0;
+            while lost < parity {
+                let i = rand::random::<usize>() % total;
+                if some[i].is_some() {
+                    some[i] = None;
+                    lost += 1;
+                }
+            }
+            let mut data_only = some.clone();
+            r.reconstruct_data(&mut data_only).unwrap();
+            for (i, shard) in data_only[..data].iter().enumerate() {
+                assert_eq!(Some(&shards[i]), shard.as_ref());
+            }
+
+            r.reconstruct(&mut some).unwrap();
+            let some: Vec<_> = some.into_iter().map(Option::unwrap).collect();
+            assert_eq!(shards, some);
+        }
+    }
+
+    #[test]
+    fn test_verify_detects_corruption() {
+        let r = ReedSolomon::new(5, 3).unwrap();
+        let mut shards = random_shards(8, 10);
+        r.encode(&mut shards).unwrap();
+        shards[6][4] = add(shards[6][4], 1);
+        assert!(!r.verify(&shards).unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Err(Error::TooFewDataShards), ReedSolomon::new(0, 1));
+        assert_eq!(Err(Error::TooFewParityShards), ReedSolomon::new(1, 0));
+        assert!(ReedSolomon::new(16384, 16384).is_ok());
+        assert_eq!(Err(Error::TooManyShards), ReedSolomon::new(16385, 1));
+        assert_eq!(Err(Error::TooManyShards), ReedSolomon::new(1, 32768));
+
+        let r = ReedSolomon::new(3, 2).unwrap();
+        assert_eq!(
+            Err(Error::EmptyShard),
+            r.encode(&mut vec![vec![0u32; 0]; 5])
+        );
+        assert_eq!(
+            Err(Error::TooFewShards),
+            r.encode(&mut vec![vec![0u32; 4]; 4])
+        );
+
+        let mut shards = vec![None, None, None, Some(vec![0u32; 4]), Some(vec![0u32; 4])];
+        assert_eq!(Err(Error::TooFewShardsPresent), r.reconstruct(&mut shards));
+
+        let mut shards = vec![
+            None,
+            None,
+            Some(vec![0u32; 3]),
+            Some(vec![0u32; 4]),
+            Some(vec![0u32; 4]),
+        ];
+        assert_eq!(Err(Error::IncorrectShardSize), r.reconstruct(&mut shards));
+    }
+}
@@ -0,0 +1,1099 @@
+//! PAR2 recovery files.
+//!
+//! Implements the recovery set of the [PAR 2.0 specification] as produced
+//! and consumed by par2cmdline: creating the recovery slices of a set of
+//! files, writing and parsing the packets of `.par2` files, verifying files
+//! against the checksums of the recovery set, and repairing damaged or
+//! missing slices.
+//!
+//! PAR2 does not use the systematic encoding matrix of `ReedSolomon`. The
+//! `i`-th input slice of the recovery set is assigned the constant `2^n_i`,
+//! where `n_i` is the `i`-th exponent coprime to `65535`, and the recovery
+//! slice with exponent `e` is the sum of all input slices multiplied by their
+//! constant raised to `e`. The field is GF(2^16) with the polynomial
+//! `x^16 + x^12 + x^3 + x + 1`, which differs from the one of `galois_16`, so
+//! it is implemented by `Par2Field`. Slices are read as little-endian 16-bit
+//! words, and the last slice of each file is padded with zeros.
+//!
+//! The files of a recovery set are held in memory, and all packets are
+//! written into a single `.par2` file. Recovery sets spread over several
+//! volumes are parsed by passing all of them to `RecoverySet::parse`.
+//!
+//! This module is only available with the `par2` feature enabled.
+//!
+//! [PAR 2.0 specification]: https://parchive.github.io/doc/Parity%20Volume%20Set%20Specification%20v2.0.html
+//!
+//! # Example
+//!
+//! ```
+//! # use reed_solomon_erasure::par2::RecoverySet;
+//! let a = b"contents of the first file".to_vec();
+//! let b = b"contents of the second file".to_vec();
+//!
+//! let set = RecoverySet::create(8, &[("a.txt", &a), ("b.txt", &b)], 4).unwrap();
+//! let mut par2 = Vec::new();
+//! set.write(&mut par2).unwrap();
+//!
+//! // the files are in the order of the recovery set
+//! let set = RecoverySet::parse(&[&par2]).unwrap();
+//! let mut files: Vec<Vec<u8>> = set
+//!     .files()
+//!     .iter()
+//!     .map(|file| if file.name == "a.txt" { a.clone() } else { b.clone() })
+//!     .collect();
+//!
+//! files[0][3] ^= 1;
+//! files[1].truncate(20);
+//! assert_eq!(3, set.repair(&mut files).unwrap());
+//! assert!(files.contains(&a) && files.contains(&b));
+//! ```
+
+use std::io::{self, Write};
+
+use md5::{Digest, Md5};
+
+use crate::errors::Par2Error;
+use crate::galois_16_slice::{self, SplitTables};
+use crate::matrix::Matrix;
+use crate::Field;
+
+/// Magic bytes at the start of every packet.
+pub const PACKET_MAGIC: [u8; 8] = *b"PAR2\0PKT";
+
+/// Length of the header of a packet.
+pub const PACKET_HEADER_LEN: usize = 64;
+
+/// Maximum number of input slices of a recovery set.
+pub const MAX_INPUT_SLICES: usize = 32768;
+
+/// Maximum number of recovery slices of a recovery set.
+pub const MAX_RECOVERY_SLICES: usize = 65535;
+
+const TYPE_MAIN: [u8; 16] = *b"PAR 2.0\0Main\0\0\0\0";
+const TYPE_FILE_DESC: [u8; 16] = *b"PAR 2.0\0FileDesc";
+const TYPE_IFSC: [u8; 16] = *b"PAR 2.0\0IFSC\0\0\0\0";
+const TYPE_RECOVERY: [u8; 16] = *b"PAR 2.0\0RecvSlic";
+const TYPE_CREATOR: [u8; 16] = *b"PAR 2.0\0Creator\0";
+
+const CREATOR: &[u8] = b"reed-solomon-erasure";
+
+// x^16 + x^12 + x^3 + x + 1
+const POLYNOMIAL: u32 = 0x1100B;
+const LIMIT: usize = 65535;
+
+const fn log_exp_tables() -> ([u16; 65536], [u16; LIMIT]) {
+    let mut log = [0u16; 65536];
+    let mut exp = [0u16; LIMIT];
+    let mut x: u32 = 1;
+    let mut i = 0;
+    while i < LIMIT {
+        exp[i] = x as u16;
+        log[x as usize] = i as u16;
+        x <<= 1;
+        if x & 0x10000 != 0 {
+            x ^= POLYNOMIAL;
+        }
+        i += 1;
+    }
+    (log, exp)
+}
+
+static TABLES: ([u16; 65536], [u16; LIMIT]) = log_exp_tables();
+
+/// The field of PAR2, GF(2^16) with the polynomial `x^16 + x^12 + x^3 + x + 1`.
+///
+/// Elements are stored as two bytes in little-endian order, the order of the
+/// words of PAR2 slices, and slices are multiplied with split tables like
+/// those of `galois_16_native`.
+pub struct Par2Field;
+
+impl Par2Field {
+    fn log(a: u16) -> usize {
+        TABLES.0[a as usize] as usize
+    }
+
+    fn antilog(n: usize) -> u16 {
+        TABLES.1[n % LIMIT]
+    }
+}
+
+impl crate::Field for Par2Field {
+    const ORDER: usize = 65536;
+
+    type Elem = [u8; 2];
+
+    const WRITES_OUTPUTS_FIRST: bool = true;
+
+    fn add(a: [u8; 2], b: [u8; 2]) -> [u8; 2] {
+        [a[0] ^ b[0], a[1] ^ b[1]]
+    }
+
+    fn mul(a: [u8; 2], b: [u8; 2]) -> [u8; 2] {
+        let (a, b) = (u16::from_le_bytes(a), u16::from_le_bytes(b));
+        if a == 0 || b == 0 {
+            [0; 2]
+        } else {
+            Self::antilog(Self::log(a) + Self::log(b)).to_le_bytes()
+        }
+    }
+
+    fn div(a: [u8; 2], b: [u8; 2]) -> [u8; 2] {
+        let (a, b) = (u16::from_le_bytes(a), u16::from_le_bytes(b));
+        if b == 0 {
+            panic!("Divisor is 0")
+        }
+        if a == 0 {
+            [0; 2]
+        } else {
+            Self::antilog(Self::log(a) + LIMIT - Self::log(b)).to_le_bytes()
+        }
+    }
+
+    fn exp(a: [u8; 2], n: usize) -> [u8; 2] {
+        let a = u16::from_le_bytes(a);
+        if n == 0 {
+            [1, 0]
+        } else if a == 0 {
+            [0; 2]
+        } else {
+            Self::antilog(Self::log(a) * (n % LIMIT)).to_le_bytes()
+        }
+    }
+
+    fn zero() -> [u8; 2] {
+        [0; 2]
+    }
+
+    fn one() -> [u8; 2] {
+        [1, 0]
+    }
+
+    fn nth_internal(n: usize) -> [u8; 2] {
+        (n as u16).to_le_bytes()
+    }
+
+    fn mul_slice(c: [u8; 2], input: &[[u8; 2]], out: &mut [[u8; 2]]) {
+        let tables = SplitTables::new(|x| Self::mul(c, x));
+        galois_16_slice::mul_slice(&tables, input, out)
+    }
+
+    fn mul_slice_add(c: [u8; 2], input: &[[u8; 2]], out: &mut [[u8; 2]]) {
+        let tables = SplitTables::new(|x| Self::mul(c, x));
+        galois_16_slice::mul_slice_xor(&tables, input, out)
+    }
+
+    const TABLE_LEN: usize = galois_16_slice::TABLE_LEN;
+
+    fn prepare(c: [u8; 2], table: &mut [[u8; 2]]) {
+        SplitTables::new(|x| Self::mul(c, x)).write_to(table)
+    }
+
+    fn dot_prod_prepared(
+        _matrix_rows: &[&[[u8; 2]]],
+        tables: &[&[[u8; 2]]],
+        inputs: &[&[[u8; 2]]],
+        outputs: &mut [&mut [[u8; 2]]],
+    ) {
+        galois_16_slice::dot_prod(tables, inputs, outputs)
+    }
+
+    fn sum_slices(inputs: &[&[[u8; 2]]], out: &mut [[u8; 2]]) {
+        galois_16_slice::xor_slices(inputs, out)
+    }
+
+    fn add_slice(input: &[[u8; 2]], out: &mut [[u8; 2]]) {
+        galois_16_slice::slice_xor(input, out)
+    }
+}
+
+// Constants assigned to the input slices, in order.
+fn input_constants(count: usize) -> Vec<[u8; 2]> {
+    (1..LIMIT)
+        .filter(|n| n % 3 != 0 && n % 5 != 0 && n % 17 != 0 && n % 257 != 0)
+        .take(count)
+        .map(|n| Par2Field::antilog(n).to_le_bytes())
+        .collect()
+}
+
+fn md5(data: &[u8]) -> [u8; 16] {
+    Md5::digest(data).into()
+}
+
+fn to_words(slice: &[u8], slice_size: usize) -> Vec<[u8; 2]> {
+    let mut words = vec![[0u8; 2]; slice_size / 2];
+    let len = slice.len().min(slice_size);
+    words.as_flattened_mut()[..len].copy_from_slice(&slice[..len]);
+    words
+}
+
+fn u32_at(bytes: &[u8], i: usize) -> u32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[i..i + 4]);
+    u32::from_le_bytes(buf)
+}
+
+fn u64_at(bytes: &[u8], i: usize) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(&bytes[i..i + 8]);
+    u64::from_le_bytes(buf)
+}
+
+fn id_at(bytes: &[u8], i: usize) -> [u8; 16] {
+    let mut id = [0; 16];
+    id.copy_from_slice(&bytes[i..i + 16]);
+    id
+}
+
+/// Description of a file of a recovery set.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Par2File {
+    /// File ID, the MD5 of `md5_16k`, the length and the name.
+    pub id: [u8; 16],
+    /// Name of the file, relative to the recovery files.
+    pub name: String,
+    /// Length of the file in bytes.
+    pub len: u64,
+    /// MD5 of the whole file.
+    pub md5: [u8; 16],
+    /// MD5 of the first 16 KiB of the file.
+    pub md5_16k: [u8; 16],
+    /// MD5 and CRC-32 of every slice of the file, the last one padded
+    /// with zeros.
+    pub slices: Vec<([u8; 16], u32)>,
+}
+
+impl Par2File {
+    fn new(name: &str, data: &[u8], slice_size: usize) -> Par2File {
+        let md5_16k = md5(&data[..data.len().min(16384)]);
+
+        let mut id_input = Vec::with_capacity(24 + name.len());
+        id_input.extend_from_slice(&md5_16k);
+        id_input.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        id_input.extend_from_slice(name.as_bytes());
+
+        let slices = data
+            .chunks(slice_size)
+            .map(|chunk| {
+                let mut slice = chunk.to_vec();
+                slice.resize(slice_size, 0);
+                (md5(&slice), crc32fast::hash(&slice))
+            })
+            .collect();
+
+        Par2File {
+            id: md5(&id_input),
+            name: name.to_string(),
+            len: data.len() as u64,
+            md5: md5(data),
+            md5_16k,
+            slices,
+        }
+    }
+
+    // Returns whether each slice of `data` matches its checksums.
+    fn verify_slices(&self, data: &[u8], slice_size: usize) -> Vec<bool> {
+        let mut slice = vec![0u8; slice_size];
+        self.slices
+            .iter()
+            .enumerate()
+            .map(|(i, &(md5_sum, crc))| {
+                let start = i * slice_size;
+                let end = (start + slice_size).min(self.len as usize);
+                if data.len() < end {
+                    return false;
+                }
+
+                slice[..end - start].copy_from_slice(&data[start..end]);
+                for byte in slice[end - start..].iter_mut() {
+                    *byte = 0;
+                }
+                crc32fast::hash(&slice) == crc && md5(&slice) == md5_sum
+            })
+            .collect()
+    }
+}
+
+/// A PAR2 recovery set: the descriptions of its files and the recovery
+/// slices.
+#[derive(PartialEq, Debug, Clone)]
+pub struct RecoverySet {
+    id: [u8; 16],
+    slice_size: usize,
+    files: Vec<Par2File>,
+    recovery: Vec<(u32, Vec<u8>)>,
+}
+
+impl RecoverySet {
+    /// Creates the recovery set of `files`, given as pairs of name and
+    /// contents, with `recovery_count` recovery slices of `slice_size` bytes.
+    ///
+    /// Returns `Par2Error::InvalidSliceSize` if `slice_size` is zero or not
+    /// a multiple of 4, and `Par2Error::TooManySlices` if the files span more
+    /// than `MAX_INPUT_SLICES` slices or `recovery_count` exceeds
+    /// `MAX_RECOVERY_SLICES`.
+    pub fn create<T: AsRef<[u8]>>(
+        slice_size: usize,
+        files: &[(&str, T)],
+        recovery_count: usize,
+    ) -> Result<RecoverySet, Par2Error> {
+        if slice_size == 0 || !slice_size.is_multiple_of(4) {
+            return Err(Par2Error::InvalidSliceSize);
+        }
+
+        let mut descs: Vec<Par2File> = files
+            .iter()
+            .map(|(name, data)| Par2File::new(name, data.as_ref(), slice_size))
+            .collect();
+        let mut contents: Vec<&[u8]> = files.iter().map(|(_, data)| data.as_ref()).collect();
+
+        let input_count: usize = descs.iter().map(|file| file.slices.len()).sum();
+        if input_count > MAX_INPUT_SLICES || recovery_count > MAX_RECOVERY_SLICES {
+            return Err(Par2Error::TooManySlices);
+        }
+
+        // par2cmdline compares IDs as little-endian 128-bit integers
+        let mut order: Vec<usize> = (0..descs.len()).collect();
+        order.sort_by(|&a, &b| descs[a].id.iter().rev().cmp(descs[b].id.iter().rev()));
+        descs = order.iter().map(|&i| descs[i].clone()).collect();
+        contents = order.iter().map(|&i| contents[i]).collect();
+
+        let constants = input_constants(input_count);
+        let mut recovery: Vec<(u32, Vec<[u8; 2]>)> = (0..recovery_count)
+            .map(|exponent| (exponent as u32, vec![[0u8; 2]; slice_size / 2]))
+            .collect();
+        let input_slices = contents
+            .iter()
+            .flat_map(|data| data.chunks(slice_size))
+            .zip(constants.iter());
+        for (slice, &constant) in input_slices {
+            let words = to_words(slice, slice_size);
+            for (exponent, out) in recovery.iter_mut() {
+                let coefficient = Par2Field::exp(constant, *exponent as usize);
+                Par2Field::mul_slice_add(coefficient, &words, out);
+            }
+        }
+
+        let mut set = RecoverySet {
+            id: [0; 16],
+            slice_size,
+            files: descs,
+            recovery: recovery
+                .into_iter()
+                .map(|(exponent, words)| (exponent, words.as_flattened().to_vec()))
+                .collect(),
+        };
+        set.id = md5(&set.main_body());
+        Ok(set)
+    }
+
+    fn main_body(&self) -> Vec<u8> {
+        let mut body = Vec::with_capacity(12 + 16 * self.files.len());
+        body.extend_from_slice(&(self.slice_size as u64).to_le_bytes());
+        body.extend_from_slice(&(self.files.len() as u32).to_le_bytes());
+        for file in self.files.iter() {
+            body.extend_from_slice(&file.id);
+        }
+        body
+    }
+
+    /// Recovery set ID, the MD5 of the body of the main packet.
+    pub fn id(&self) -> [u8; 16] {
+        self.id
+    }
+
+    /// Size of the slices in bytes.
+    pub fn slice_size(&self) -> usize {
+        self.slice_size
+    }
+
+    /// The files of the recovery set, in the order of the main packet.
+    pub fn files(&self) -> &[Par2File] {
+        &self.files
+    }
+
+    /// Exponents of the available recovery slices, in increasing order.
+    pub fn recovery_exponents(&self) -> Vec<u32> {
+        self.recovery
+            .iter()
+            .map(|(exponent, _)| *exponent)
+            .collect()
+    }
+
+    fn write_packet<W: Write>(&self, out: &mut W, kind: &[u8; 16], body: &[u8]) -> io::Result<()> {
+        let mut md5_input = Md5::new();
+        md5_input.update(self.id);
+        md5_input.update(kind);
+        md5_input.update(body);
+
+        out.write_all(&PACKET_MAGIC)?;
+        out.write_all(&((PACKET_HEADER_LEN + body.len()) as u64).to_le_bytes())?;
+        out.write_all(&md5_input.finalize())?;
+        out.write_all(&self.id)?;
+        out.write_all(kind)?;
+        out.write_all(body)
+    }
+
+    /// Writes all packets of the recovery set into `out`: the main,
+    /// creator, file description and input file slice checksum packets,
+    /// followed by the recovery slice packets.
+    pub fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        self.write_packet(&mut out, &TYPE_MAIN, &self.main_body())?;
+
+        let mut creator = CREATOR.to_vec();
+        creator.resize(CREATOR.len().div_ceil(4) * 4, 0);
+        self.write_packet(&mut out, &TYPE_CREATOR, &creator)?;
+
+        for file in self.files.iter() {
+            let mut desc = Vec::with_capacity(56 + file.name.len() + 3);
+            desc.extend_from_slice(&file.id);
+            desc.extend_from_slice(&file.md5);
+            desc.extend_from_slice(&file.md5_16k);
+            desc.extend_from_slice(&file.len.to_le_bytes());
+            desc.extend_from_slice(file.name.as_bytes());
+            desc.resize(desc.len().div_ceil(4) * 4, 0);
+            self.write_packet(&mut out, &TYPE_FILE_DESC, &desc)?;
+
+            let mut ifsc = Vec::with_capacity(16 + 20 * file.slices.len());
+            ifsc.extend_from_slice(&file.id);
+            for (md5_sum, crc) in file.slices.iter() {
+                ifsc.extend_from_slice(md5_sum);
+                ifsc.extend_from_slice(&crc.to_le_bytes());
+            }
+            self.write_packet(&mut out, &TYPE_IFSC, &ifsc)?;
+        }
+
+        for (exponent, data) in self.recovery.iter() {
+            let mut body = Vec::with_capacity(4 + data.len());
+            body.extend_from_slice(&exponent.to_le_bytes());
+            body.extend_from_slice(data);
+            self.write_packet(&mut out, &TYPE_RECOVERY, &body)?;
+        }
+
+        out.flush()
+    }
+
+    /// Parses a recovery set from the contents of its `.par2` files.
+    ///
+    /// Packets which are damaged or belong to another recovery set are
+    /// skipped, as are duplicates. Only the recovery set of the first valid
+    /// main packet is parsed.
+    ///
+    /// Returns `Par2Error::MissingPackets` if there is no valid main packet,
+    /// or the description or slice checksums of a file are missing.
+    pub fn parse<T: AsRef<[u8]>>(volumes: &[T]) -> Result<RecoverySet, Par2Error> {
+        // (recovery set ID, type, body) of every valid packet
+        let mut packets: Vec<([u8; 16], [u8; 16], &[u8])> = Vec::new();
+        for volume in volumes.iter() {
+            let data = volume.as_ref();
+            let mut pos = 0;
+            while pos + PACKET_HEADER_LEN <= data.len() {
+                if data[pos..pos + 8] != PACKET_MAGIC {
+                    pos += 1;
+                    continue;
+                }
+
+                let len = u64_at(data, pos + 8);
+                let valid = len >= PACKET_HEADER_LEN as u64
+                    && len.is_multiple_of(4)
+                    && len <= (data.len() - pos) as u64
+                    && md5(&data[pos + 32..pos + len as usize]) == id_at(data, pos + 16);
+                if !valid {
+                    pos += 1;
+                    continue;
+                }
+
+                let packet = &data[pos..pos + len as usize];
+                packets.push((
+                    id_at(packet, 32),
+                    id_at(packet, 48),
+                    &packet[PACKET_HEADER_LEN..],
+                ));
+                pos += len as usize;
+            }
+        }
+
+        let (id, main) = packets
+            .iter()
+            .find(|(id, kind, body)| {
+                *kind == TYPE_MAIN
+                    && body.len() >= 12
+                    && md5(body) == *id
+                    && body.len() >= 12 + 16 * u32_at(body, 8) as usize
+            })
+            .map(|(id, _, body)| (*id, *body))
+            .ok_or(Par2Error::MissingPackets)?;
+        let packets: Vec<_> = packets
+            .into_iter()
+            .filter(|(set_id, _, _)| *set_id == id)
+            .collect();
+
+        let slice_size = u64_at(main, 0) as usize;
+        if slice_size == 0 || !slice_size.is_multiple_of(4) {
+            return Err(Par2Error::InvalidSliceSize);
+        }
+
+        let file_count = u32_at(main, 8) as usize;
+        let mut files = Vec::with_capacity(file_count);
+        for i in 0..file_count {
+            let file_id = id_at(main, 12 + 16 * i);
+
+            let desc = packets
+                .iter()
+                .find(|(_, kind, body)| {
+                    *kind == TYPE_FILE_DESC && body.len() >= 56 && id_at(body, 0) == file_id
+                })
+                .map(|(_, _, body)| *body)
+                .ok_or(Par2Error::MissingPackets)?;
+            let len = u64_at(desc, 48);
+            let slice_count = len.div_ceil(slice_size as u64) as usize;
+
+            // an empty file has no slices to check, and some creators omit
+            // its slice checksum packet
+            let ifsc = packets
+                .iter()
+                .find(|(_, kind, body)| {
+                    *kind == TYPE_IFSC
+                        && body.len() == 16 + 20 * slice_count
+                        && id_at(body, 0) == file_id
+                })
+                .map(|(_, _, body)| *body)
+                .or((slice_count == 0).then_some(&file_id[..]))
+                .ok_or(Par2Error::MissingPackets)?;
+
+            let name = &desc[56..];
+            let name_len = name.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+            files.push(Par2File {
+                id: file_id,
+                name: String::from_utf8_lossy(&name[..name_len]).into_owned(),
+                len,
+                md5: id_at(desc, 16),
+                md5_16k: id_at(desc, 32),
+                slices: ifsc[16..]
+                    .chunks(20)
+                    .map(|entry| (id_at(entry, 0), u32_at(entry, 16)))
+                    .collect(),
+            });
+        }
+
+        let mut recovery: Vec<(u32, Vec<u8>)> = packets
+            .iter()
+            .filter(|(_, kind, body)| *kind == TYPE_RECOVERY && body.len() == 4 + slice_size)
+            .map(|(_, _, body)| (u32_at(body, 0), body[4..].to_vec()))
+            .collect();
+        recovery.sort_by_key(|(exponent, _)| *exponent);
+        recovery.dedup_by_key(|(exponent, _)| *exponent);
+
+        Ok(RecoverySet {
+            id,
+            slice_size,
+            files,
+            recovery,
+        })
+    }
+
+    /// Checks the slices of `files`, given in the order of `files()`, against
+    /// the checksums of the recovery set. A missing file can be passed as an
+    /// empty slice.
+    ///
+    /// Returns whether each slice of each file is intact, or
+    /// `Par2Error::FileCountMismatch` if the number of files differs from
+    /// the recovery set.
+    pub fn verify<T: AsRef<[u8]>>(&self, files: &[T]) -> Result<Vec<Vec<bool>>, Par2Error> {
+        if files.len() != self.files.len() {
+            return Err(Par2Error::FileCountMismatch);
+        }
+
+        Ok(self
+            .files
+            .iter()
+            .zip(files.iter())
+            .map(|(file, data)| file.verify_slices(data.as_ref(), self.slice_size))
+            .collect())
+    }
+
+    /// Repairs `files`, given in the order of `files()`, in place. Damaged
+    /// slices are recovered, and every file is truncated or extended to its
+    /// length in the recovery set.
+    ///
+    /// Returns the number of recovered slices, `Par2Error::FileCountMismatch`
+    /// if the number of files differs from the recovery set, or
+    /// `Par2Error::TooFewRecoverySlices` if more slices are damaged than
+    /// recovery slices are available, or `Par2Error::SingularMatrix` if no
+    /// choice of recovery slices can recover the damaged ones.
+    pub fn repair(&self, files: &mut [Vec<u8>]) -> Result<usize, Par2Error> {
+        let status = self.verify(files)?;
+
+        // (file, slice in file, slice in recovery set) of damaged slices
+        let mut damaged = Vec::new();
+        let mut intact = Vec::new();
+        let mut index = 0;
+        for (i_file, slices) in status.iter().enumerate() {
+            for (i_slice, &ok) in slices.iter().enumerate() {
+                if ok {
+                    intact.push((i_file, i_slice, index));
+                } else {
+                    damaged.push((i_file, i_slice, index));
+                }
+                index += 1;
+            }
+        }
+
+        for (file, data) in self.files.iter().zip(files.iter_mut()) {
+            data.resize(file.len as usize, 0);
+        }
+        if damaged.is_empty() {
+            return Ok(0);
+        }
+        if damaged.len() > self.recovery.len() {
+            return Err(Par2Error::TooFewRecoverySlices);
+        }
+
+        let constants = input_constants(index);
+        let (picked, inverse) = self
+            .solvable_recovery(&constants, &damaged)
+            .ok_or(Par2Error::SingularMatrix)?;
+        let recovery: Vec<&(u32, Vec<u8>)> = picked.iter().map(|&i| &self.recovery[i]).collect();
+
+        // remove the intact slices from the recovery slices
+        let mut residuals: Vec<Vec<[u8; 2]>> = recovery
+            .iter()
+            .map(|(_, data)| to_words(data, self.slice_size))
+            .collect();
+        for &(i_file, i_slice, i_input) in intact.iter() {
+            let start = i_slice * self.slice_size;
+            let end = (start + self.slice_size).min(files[i_file].len());
+            let words = to_words(&files[i_file][start..end], self.slice_size);
+            for ((exponent, _), residual) in recovery.iter().zip(residuals.iter_mut()) {
+                let coefficient = Par2Field::exp(constants[i_input], *exponent as usize);
+                Par2Field::mul_slice_add(coefficient, &words, residual);
+            }
+        }
+
+        let mut words = vec![[0u8; 2]; self.slice_size / 2];
+        for (row, &(i_file, i_slice, _)) in damaged.iter().enumerate() {
+            for (col, residual) in residuals.iter().enumerate() {
+                if col == 0 {
+                    Par2Field::mul_slice(inverse.get(row, col), residual, &mut words);
+                } else {
+                    Par2Field::mul_slice_add(inverse.get(row, col), residual, &mut words);
+                }
+            }
+
+            let start = i_slice * self.slice_size;
+            let end = (start + self.slice_size).min(files[i_file].len());
+            files[i_file][start..end].copy_from_slice(&words.as_flattened()[..end - start]);
+        }
+
+        Ok(damaged.len())
+    }
+
+    // Picks as many recovery slices as there are `damaged` slices whose
+    // matrix is invertible, returning their indices with the inverse. The first
+    // recovery slices are tried first, then the other subsets in
+    // lexicographic order, as some matrices of PAR2 are singular.
+    fn solvable_recovery(
+        &self,
+        constants: &[[u8; 2]],
+        damaged: &[(usize, usize, usize)],
+    ) -> Option<(Vec<usize>, Matrix<Par2Field>)> {
+        let count = damaged.len();
+        let mut picked: Vec<usize> = (0..count).collect();
+        loop {
+            let mut matrix: Matrix<Par2Field> = Matrix::new(count, count);
+            for (row, &i_recovery) in picked.iter().enumerate() {
+                let exponent = self.recovery[i_recovery].0;
+                for (col, &(_, _, i_input)) in damaged.iter().enumerate() {
+                    matrix.set(
+                        row,
+                        col,
+                        Par2Field::exp(constants[i_input], exponent as usize),
+                    );
+                }
+            }
+            if let Ok(inverse) = matrix.invert() {
+                return Some((picked, inverse));
+            }
+
+            // next subset: bump the last index that can still move
+            let last = (0..count)
+                .rev()
+                .find(|&i| picked[i] < self.recovery.len() - count + i)?;
+            picked[last] += 1;
+            for i in last + 1..count {
+                picked[i] = picked[i - 1] + 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fill_random;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    // Scratch directory removed on drop.
+    struct ScratchDir(PathBuf);
+
+    impl ScratchDir {
+        fn new() -> ScratchDir {
+            let dir =
+                std::env::temp_dir().join(format!("rse-par2-test-{:016x}", rand::random::<u64>()));
+            fs::create_dir(&dir).unwrap();
+            ScratchDir(dir)
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // Writes `random_files()`, renamed to lie in one directory, into a
+    // scratch directory.
+    fn scratch_files() -> (ScratchDir, Vec<(String, Vec<u8>)>) {
+        let mut files = random_files();
+        let dir = ScratchDir::new();
+        for (name, data) in files.iter_mut() {
+            *name = name.replace('/', "_");
+            fs::write(dir.path(name), data).unwrap();
+        }
+        (dir, files)
+    }
+
+    fn par2cmdline(dir: &ScratchDir, args: &[&str]) -> bool {
+        Command::new("par2")
+            .args(args)
+            .current_dir(&dir.0)
+            .status()
+            .expect("par2cmdline not found")
+            .success()
+    }
+
+    fn random_files() -> Vec<(String, Vec<u8>)> {
+        [("a", 1000), ("dir/b", 64), ("empty", 0), ("c", 20000)]
+            .iter()
+            .map(|&(name, len)| {
+                let mut contents = vec![0u8; len];
+                fill_random(&mut contents);
+                (name.to_string(), contents)
+            })
+            .collect()
+    }
+
+    fn ordered(set: &RecoverySet, files: &[(String, Vec<u8>)]) -> Vec<Vec<u8>> {
+        set.files()
+            .iter()
+            .map(|file| {
+                files
+                    .iter()
+                    .find(|(name, _)| *name == file.name)
+                    .unwrap()
+                    .1
+                    .clone()
+            })
+            .collect()
+    }
+
+    fn create(files: &[(String, Vec<u8>)], recovery_count: usize) -> RecoverySet {
+        let named: Vec<(&str, &[u8])> = files
+            .iter()
+            .map(|(name, data)| (name.as_str(), data.as_slice()))
+            .collect();
+        RecoverySet::create(512, &named, recovery_count).unwrap()
+    }
+
+    #[test]
+    fn test_par2_field() {
+        // the polynomial reduces x^16 to x^12 + x^3 + x + 1
+        let elem = u16::to_le_bytes;
+        assert_eq!(elem(0x8000), Par2Field::exp(elem(2), 15));
+        assert_eq!(elem(0x100B), Par2Field::exp(elem(2), 16));
+        assert_eq!(elem(1), Par2Field::exp(elem(2), 65535));
+
+        for &(a, b) in [(3, 7), (0x1234, 0xFEDC), (0xFFFF, 2), (1, 0x8000)].iter() {
+            let product = Par2Field::mul(elem(a), elem(b));
+            assert_eq!(elem(a), Par2Field::div(product, elem(b)));
+            assert_eq!(elem(b), Par2Field::div(product, elem(a)));
+        }
+        assert_eq!(elem(0), Par2Field::mul(elem(0), elem(0x1234)));
+
+        // the split tables give the products of the log and exp tables
+        let mut input = vec![[0u8; 2]; 1000];
+        fill_random(input.as_flattened_mut());
+        let mut out = vec![[0u8; 2]; 1000];
+        Par2Field::mul_slice(elem(0xBEEF), &input, &mut out);
+        for (i, o) in input.iter().zip(out.iter()) {
+            assert_eq!(Par2Field::mul(elem(0xBEEF), *i), *o);
+        }
+
+        // logarithms 1, 2, 4, 7, 8, 11 are the first ones coprime to 65535
+        let constants = input_constants(6);
+        let logs: Vec<usize> = constants
+            .iter()
+            .map(|&c| Par2Field::log(u16::from_le_bytes(c)))
+            .collect();
+        assert_eq!(vec![1, 2, 4, 7, 8, 11], logs);
+    }
+
+    #[test]
+    fn test_par2_create() {
+        let files = random_files();
+        let set = create(&files, 8);
+
+        assert_eq!(md5(&set.main_body()), set.id());
+        let ids: Vec<[u8; 16]> = set.files().iter().map(|file| file.id).collect();
+        let mut sorted = ids.clone();
+        sorted.sort_by(|a, b| a.iter().rev().cmp(b.iter().rev()));
+        assert_eq!(sorted, ids);
+        assert_eq!((0..8).collect::<Vec<u32>>(), set.recovery_exponents());
+
+        // the recovery slice with exponent 0 is the XOR of all input slices
+        let mut xor = vec![0u8; 512];
+        for data in ordered(&set, &files).iter() {
+            for chunk in data.chunks(512) {
+                for (x, byte) in xor.iter_mut().zip(chunk) {
+                    *x ^= byte;
+                }
+            }
+        }
+        assert_eq!(xor, set.recovery[0].1);
+
+        let empty: [(&str, &[u8]); 0] = [];
+        assert_eq!(
+            Par2Error::InvalidSliceSize,
+            RecoverySet::create(510, &empty, 1).unwrap_err()
+        );
+        assert_eq!(
+            Par2Error::TooManySlices,
+            RecoverySet::create(4, &[("big", vec![0u8; 4 * 32769])], 1).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_par2_write_parse() {
+        let files = random_files();
+        let set = create(&files, 4);
+
+        let mut par2 = Vec::new();
+        set.write(&mut par2).unwrap();
+        assert_eq!(&PACKET_MAGIC[..], &par2[..8]);
+        assert_eq!(0, par2.len() % 4);
+        assert_eq!(set, RecoverySet::parse(&[&par2]).unwrap());
+
+        // damaged packets are skipped, and the packets of several volumes are merged
+        let mut damaged = par2.clone();
+        let len = damaged.len();
+        damaged[len - 10] ^= 1;
+        let parsed = RecoverySet::parse(&[&b"junk"[..], &damaged]).unwrap();
+        assert_eq!(vec![0, 1, 2], parsed.recovery_exponents());
+        assert_eq!(set.files(), parsed.files());
+
+        let (first, second) = par2.split_at(par2.len() - (PACKET_HEADER_LEN + 4 + 512));
+        let mut merged = RecoverySet::parse(&[&damaged[..], second]).unwrap();
+        assert_eq!(set, merged);
+        merged = RecoverySet::parse(&[first]).unwrap();
+        assert_eq!(vec![0, 1, 2], merged.recovery_exponents());
+
+        damaged = par2.clone();
+        damaged[80] ^= 1;
+        assert_eq!(
+            Par2Error::MissingPackets,
+            RecoverySet::parse(&[&damaged]).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_par2_empty_file() {
+        let files = random_files();
+        let set = create(&files, 2);
+        let i_empty = set
+            .files()
+            .iter()
+            .position(|file| file.name == "empty")
+            .unwrap();
+        let empty = &set.files()[i_empty];
+        assert_eq!(0, empty.len);
+        assert!(empty.slices.is_empty());
+        assert_eq!(md5(b""), empty.md5);
+
+        // the empty file has a description and a slice checksum packet
+        // without entries
+        let mut par2 = Vec::new();
+        set.write(&mut par2).unwrap();
+        let mut ifsc_pos = None;
+        let mut descs = 0;
+        let mut pos = 0;
+        while pos < par2.len() {
+            let len = u64_at(&par2, pos + 8) as usize;
+            let kind = id_at(&par2, pos + 48);
+            if id_at(&par2, pos + PACKET_HEADER_LEN) == empty.id {
+                if kind == TYPE_FILE_DESC {
+                    descs += 1;
+                } else if kind == TYPE_IFSC {
+                    assert_eq!(PACKET_HEADER_LEN + 16, len);
+                    ifsc_pos = Some(pos);
+                }
+            }
+            pos += len;
+        }
+        assert_eq!(1, descs);
+        let ifsc_pos = ifsc_pos.unwrap();
+        assert_eq!(set, RecoverySet::parse(&[&par2]).unwrap());
+
+        // and parses without its slice checksum packet
+        par2.drain(ifsc_pos..ifsc_pos + PACKET_HEADER_LEN + 16);
+        assert_eq!(set, RecoverySet::parse(&[&par2]).unwrap());
+
+        let mut damaged = ordered(&set, &files);
+        damaged[i_empty].extend_from_slice(b"junk");
+        assert_eq!(Vec::<bool>::new(), set.verify(&damaged).unwrap()[i_empty]);
+        assert_eq!(0, set.repair(&mut damaged).unwrap());
+        assert_eq!(ordered(&set, &files), damaged);
+    }
+
+    #[test]
+    fn test_par2_verify_repair() {
+        let files = random_files();
+        let set = create(&files, 6);
+        let expected = ordered(&set, &files);
+
+        let mut damaged = expected.clone();
+        assert_eq!(0, set.repair(&mut damaged).unwrap());
+        assert_eq!(expected, damaged);
+
+        let i_a = set
+            .files()
+            .iter()
+            .position(|file| file.name == "a")
+            .unwrap();
+        let i_b = set
+            .files()
+            .iter()
+            .position(|file| file.name == "dir/b")
+            .unwrap();
+        let i_c = set
+            .files()
+            .iter()
+            .position(|file| file.name == "c")
+            .unwrap();
+        damaged[i_a][900] ^= 1;
+        damaged[i_b].clear();
+        damaged[i_c][0] ^= 1;
+        damaged[i_c][10000] ^= 1;
+        damaged[i_c].truncate(19990);
+
+        let status = set.verify(&damaged).unwrap();
+        assert_eq!(vec![true, false], status[i_a]);
+        assert_eq!(vec![false], status[i_b]);
+        assert_eq!(40, status[i_c].len());
+        assert_eq!(3, status[i_c].iter().filter(|&&ok| !ok).count());
+
+        assert_eq!(5, set.repair(&mut damaged).unwrap());
+        assert_eq!(expected, damaged);
+
+        // repair from any recovery slices, after a round trip through the packets
+        let mut par2 = Vec::new();
+        set.write(&mut par2).unwrap();
+        let mut parsed = RecoverySet::parse(&[&par2]).unwrap();
+        parsed.recovery.remove(1);
+        parsed.recovery.remove(2);
+        damaged[i_a].clear();
+        damaged[i_c].extend_from_slice(b"trailing");
+        assert_eq!(2, parsed.repair(&mut damaged).unwrap());
+        assert_eq!(expected, damaged);
+
+        // skip recovery slices giving a singular matrix
+        let mut repeated = set.clone();
+        repeated.recovery[1] = repeated.recovery[0].clone();
+        damaged[i_a].clear();
+        damaged[i_b].clear();
+        assert_eq!(3, repeated.repair(&mut damaged).unwrap());
+        assert_eq!(expected, damaged);
+        repeated.recovery.truncate(3);
+        repeated.recovery[2] = repeated.recovery[0].clone();
+        damaged[i_a].clear();
+        damaged[i_b].clear();
+        assert_eq!(
+            Par2Error::SingularMatrix,
+            repeated.repair(&mut damaged).unwrap_err()
+        );
+
+        for data in damaged.iter_mut() {
+            data.clear();
+        }
+        assert_eq!(
+            Par2Error::TooFewRecoverySlices,
+            set.repair(&mut damaged).unwrap_err()
+        );
+        assert_eq!(
+            Par2Error::FileCountMismatch,
+            set.verify(&damaged[1..]).unwrap_err()
+        );
+    }
+
+    #[test]
+    #[ignore = "needs par2cmdline"]
+    fn test_par2cmdline_reads_ours() {
+        let (dir, files) = scratch_files();
+        let set = create(&files, 4);
+        let mut par2 = Vec::new();
+        set.write(&mut par2).unwrap();
+        fs::write(dir.path("set.par2"), &par2).unwrap();
+
+        assert!(par2cmdline(&dir, &["verify", "-q", "set.par2"]));
+
+        let mut damaged = files[0].1.clone();
+        damaged[10] ^= 1;
+        fs::write(dir.path(&files[0].0), &damaged).unwrap();
+        fs::remove_file(dir.path(&files[1].0)).unwrap();
+        assert!(!par2cmdline(&dir, &["verify", "-q", "set.par2"]));
+        assert!(par2cmdline(&dir, &["repair", "-q", "set.par2"]));
+        for (name, data) in files.iter() {
+            assert_eq!(*data, fs::read(dir.path(name)).unwrap());
+        }
+    }
+
+    #[test]
+    #[ignore = "needs par2cmdline"]
+    fn test_par2cmdline_set_parses() {
+        let (dir, files) = scratch_files();
+        let mut args = vec!["create", "-q", "-s512", "-c6", "-n1", "theirs.par2"];
+        args.extend(files.iter().map(|(name, _)| name.as_str()));
+        assert!(par2cmdline(&dir, &args));
+
+        let volumes: Vec<Vec<u8>> = fs::read_dir(&dir.0)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "par2"))
+            .map(|path| fs::read(path).unwrap())
+            .collect();
+        let set = RecoverySet::parse(&volumes).unwrap();
+        assert_eq!(512, set.slice_size());
+        assert_eq!((0..6).collect::<Vec<u32>>(), set.recovery_exponents());
+
+        // par2cmdline may leave the empty file out of the recovery set
+        let ours = create(&files, 0);
+        for file in ours.files().iter().filter(|file| file.len > 0) {
+            assert!(set.files().contains(file), "{}", file.name);
+        }
+        for file in set.files().iter() {
+            assert!(ours.files().contains(file), "{}", file.name);
+        }
+
+        let expected = ordered(&set, &files);
+        let mut damaged = expected.clone();
+        let mut non_empty = damaged.iter_mut().filter(|data| !data.is_empty());
+        non_empty.next().unwrap().clear();
+        non_empty.next().unwrap()[0] ^= 1;
+        set.repair(&mut damaged).unwrap();
+        assert_eq!(expected, damaged);
+    }
+}
@@ -0,0 +1,519 @@
+//! Encoding matrices of other erasure coding libraries and formats.
+//!
+//! `ReedSolomon::new` uses the matrix of Backblaze's JavaReedSolomon and
+//! klauspost/reedsolomon. Other systems produce different parity for the same
+//! data, so reading and repairing their data needs a codec with their matrix,
+//! built with `ReedSolomon::with_profile`:
+//!
+//! ```
+//! # use reed_solomon_erasure::galois_8::ReedSolomon;
+//! # use reed_solomon_erasure::profile::MatrixProfile;
+//! let r = ReedSolomon::with_profile(MatrixProfile::HdfsRs, 6, 3).unwrap();
+//! ```
+//!
+//! All profiles work over GF(2^8) with the polynomial of `galois_8`,
+//! `x^8 + x^4 + x^3 + x^2 + 1`, which is the one these systems use.
+//!
+//! The matrices of `MatrixProfile::IsaLVandermonde` and `MatrixProfile::Par1`
+//! are not invertible for every selection of shards, exactly like in their
+//! reference implementations. Reconstruction then fails with
+//! `Error::SingularMatrix`.
+
+use crate::errors::Error;
+use crate::galois_8::{self, ReedSolomon};
+use crate::matrix::Matrix;
+use crate::Field;
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+/// Generator matrix conventions, each producing parity bit-identical to its
+/// reference implementation.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum MatrixProfile {
+    /// The matrix of `ReedSolomon::new`: a Vandermonde matrix with evaluation
+    /// points `0, 1, 2, ...`, made systematic.
+    Default,
+    /// ISA-L's `gf_gen_rs_matrix`: parity row `r` is `1, g, g^2, ...` with
+    /// `g = 2^r`.
+    IsaLVandermonde,
+    /// ISA-L's `gf_gen_cauchy1_matrix`: the entry for shard `i` and data
+    /// shard `j` of the parity rows is `1 / (i ^ j)`.
+    IsaLCauchy,
+    /// Jerasure's `reed_sol_vandermonde_coding_matrix` with `w = 8`: an
+    /// extended Vandermonde matrix made systematic, with its first parity row
+    /// and first column scaled to ones.
+    JerasureVandermonde,
+    /// The `RS` codec of the Hadoop HDFS erasure coding policies, such as
+    /// RS-6-3-1024k. It uses the same Cauchy matrix as `IsaLCauchy`.
+    HdfsRs,
+    /// PAR1: parity row `r` is `1^r, 2^r, 3^r, ...`.
+    Par1,
+    /// zfec: a Vandermonde matrix with evaluation points
+    /// `0, 1, 2, 2^2, ...`, made systematic. See also the `zfec` module.
+    Zfec,
+    /// RAID-6 P+Q, as in Linux md and ISA-L's `gf_gen_rs_matrix` with two
+    /// parity shards: P is the exclusive or of the data shards, and Q the sum
+    /// of data shard `c` multiplied by `2^c`. With one parity shard, this is
+    /// RAID-5. At most two parity shards are allowed.
+    Raid6,
+}
+
+// Stacks the identity matrix on top of the parity rows.
+fn systematic(data_shards: usize, parity_rows: Vec<Vec<u8>>) -> Matrix<galois_8::Field> {
+    let mut rows: Vec<Vec<u8>> = (0..data_shards)
+        .map(|r| (0..data_shards).map(|c| (r == c) as u8).collect())
+        .collect();
+    rows.extend(parity_rows);
+    Matrix::new_with_data(rows)
+}
+
+// Multiplies a matrix by the inverse of its top square, making it systematic.
+fn normalize(matrix: Matrix<galois_8::Field>, data_shards: usize) -> Matrix<galois_8::Field> {
+    let top = matrix.sub_matrix(0, 0, data_shards, data_shards);
+    matrix.multiply(
+        &top.invert()
+            .expect("top rows are linearly independent; qed"),
+    )
+}
+
+fn vandermonde(points: impl Iterator<Item = u8>, data_shards: usize) -> Vec<Vec<u8>> {
+    points
+        .map(|point| {
+            (0..data_shards)
+                .map(|col| galois_8::Field::exp(point, col))
+                .collect()
+        })
+        .collect()
+}
+
+impl MatrixProfile {
+    /// The polynomial of GF(2^8) expected by the profile, including the `x^8` term.
+    pub fn polynomial(self) -> u16 {
+        galois_8::DEFAULT_POLYNOMIAL
+    }
+
+    fn matrix(self, data_shards: usize, parity_shards: usize) -> Matrix<galois_8::Field> {
+        let total_shards = data_shards + parity_shards;
+        match self {
+            MatrixProfile::Default => ReedSolomon::build_matrix(data_shards, total_shards),
+            MatrixProfile::IsaLVandermonde => systematic(
+                data_shards,
+                (0..parity_shards)
+                    .map(|r| {
+                        let gen = galois_8::exp(2, r);
+                        (0..data_shards).map(|c| galois_8::exp(gen, c)).collect()
+                    })
+                    .collect(),
+            ),
+            MatrixProfile::IsaLCauchy | MatrixProfile::HdfsRs => systematic(
+                data_shards,
+                (data_shards..total_shards)
+                    .map(|i| {
+                        (0..data_shards)
+                            .map(|j| galois_8::div(1, (i ^ j) as u8))
+                            .collect()
+                    })
+                    .collect(),
+            ),
+            MatrixProfile::JerasureVandermonde => {
+                // evaluation points 0, 1, 2, ..., and a last row for the point at infinity
+                let mut rows = vandermonde((0..total_shards - 1).map(|p| p as u8), data_shards);
+                rows.push(
+                    (0..data_shards)
+                        .map(|c| (c == data_shards - 1) as u8)
+                        .collect(),
+                );
+                let mut matrix = normalize(Matrix::new_with_data(rows), data_shards);
+
+                for c in 0..data_shards {
+                    let scale = galois_8::div(1, matrix.get(data_shards, c));
+                    for r in data_shards..total_shards {
+                        matrix.set(r, c, galois_8::mul(scale, matrix.get(r, c)));
+                    }
+                }
+                for r in data_shards + 1..total_shards {
+                    let scale = galois_8::div(1, matrix.get(r, 0));
+                    for c in 0..data_shards {
+                        matrix.set(r, c, galois_8::mul(scale, matrix.get(r, c)));
+                    }
+                }
+                matrix
+            }
+            MatrixProfile::Par1 => systematic(
+                data_shards,
+                (0..parity_shards)
+                    .map(|r| {
+                        (0..data_shards)
+                            .map(|c| galois_8::exp((c + 1) as u8, r))
+                            .collect()
+                    })
+                    .collect(),
+            ),
+            MatrixProfile::Raid6 => systematic(
+                data_shards,
+                (0..parity_shards)
+                    .map(|r| {
+                        (0..data_shards)
+                            .map(|c| galois_8::exp(galois_8::exp(2, c), r))
+                            .collect()
+                    })
+                    .collect(),
+            ),
+            MatrixProfile::Zfec => {
+                let points = (0..total_shards).map(|row| match row {
+                    0 => 0,
+                    _ => galois_8::exp(2, row - 1),
+                });
+                normalize(
+                    Matrix::new_with_data(vandermonde(points, data_shards)),
+                    data_shards,
+                )
+            }
+        }
+    }
+}
+
+impl ReedSolomon {
+    /// Creates a new instance of Reed-Solomon erasure code encoder/decoder
+    /// using the encoding matrix of `profile`.
+    ///
+    /// Returns the same errors as `new`, and `Error::TooManyParityShards` if
+    /// `profile` is `MatrixProfile::Raid6` and `parity_shards > 2`.
+    pub fn with_profile(
+        profile: MatrixProfile,
+        data_shards: usize,
+        parity_shards: usize,
+    ) -> Result<ReedSolomon, Error> {
+        Self::check_shard_counts(data_shards, parity_shards)?;
+        if profile == MatrixProfile::Raid6 && parity_shards > 2 {
+            return Err(Error::TooManyParityShards);
+        }
+
+        Self::with_matrix(
+            data_shards,
+            parity_shards,
+            profile.matrix(data_shards, parity_shards),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fill_random;
+
+    extern crate alloc;
+    use alloc::vec;
+    #[cfg(feature = "std")]
+    use std::process::Command;
+
+    const PROFILES: [MatrixProfile; 7] = [
+        MatrixProfile::Default,
+        MatrixProfile::IsaLVandermonde,
+        MatrixProfile::IsaLCauchy,
+        MatrixProfile::JerasureVandermonde,
+        MatrixProfile::HdfsRs,
+        MatrixProfile::Par1,
+        MatrixProfile::Zfec,
+    ];
+
+    fn parity_rows(
+        profile: MatrixProfile,
+        data_shards: usize,
+        parity_shards: usize,
+    ) -> Vec<Vec<u8>> {
+        let r = ReedSolomon::with_profile(profile, data_shards, parity_shards).unwrap();
+        (data_shards..data_shards + parity_shards)
+            .map(|i| r.matrix().get_row(i).to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_profile_reference_matrices() {
+        // regression values, the parity of the reference libraries
+        // themselves is checked by the interop tests below
+        assert_eq!(
+            vec![vec![1, 1, 1, 1], vec![1, 2, 4, 8], vec![1, 4, 16, 64]],
+            parity_rows(MatrixProfile::IsaLVandermonde, 4, 3)
+        );
+        assert_eq!(
+            vec![
+                vec![1, 1, 1, 1, 1, 1],
+                vec![1, 2, 4, 8, 16, 32],
+                vec![1, 4, 16, 64, 29, 116]
+            ],
+            parity_rows(MatrixProfile::IsaLVandermonde, 6, 3)
+        );
+        assert_eq!(
+            vec![
+                vec![71, 167, 122, 186],
+                vec![167, 71, 186, 122],
+                vec![122, 186, 71, 167]
+            ],
+            parity_rows(MatrixProfile::IsaLCauchy, 4, 3)
+        );
+        assert_eq!(
+            vec![
+                vec![122, 186, 71, 167, 142, 244],
+                vec![186, 122, 167, 71, 244, 142],
+                vec![173, 157, 221, 152, 61, 170]
+            ],
+            parity_rows(MatrixProfile::HdfsRs, 6, 3)
+        );
+        assert_eq!(
+            vec![vec![1, 1, 1], vec![1, 245, 244]],
+            parity_rows(MatrixProfile::JerasureVandermonde, 3, 2)
+        );
+        assert_eq!(
+            vec![
+                vec![1, 1, 1, 1, 1, 1],
+                vec![1, 225, 151, 172, 82, 200],
+                vec![1, 123, 245, 143, 244, 142]
+            ],
+            parity_rows(MatrixProfile::JerasureVandermonde, 6, 3)
+        );
+        assert_eq!(
+            vec![vec![1, 1, 1, 1], vec![1, 2, 3, 4], vec![1, 4, 5, 16]],
+            parity_rows(MatrixProfile::Par1, 4, 3)
+        );
+        assert_eq!(
+            vec![vec![3, 2], vec![5, 4]],
+            parity_rows(MatrixProfile::Zfec, 2, 2)
+        );
+        assert_eq!(
+            vec![vec![1, 1, 1, 1, 1, 1], vec![1, 2, 4, 8, 16, 32]],
+            parity_rows(MatrixProfile::Raid6, 6, 2)
+        );
+        assert_eq!(vec![vec![1, 1, 1]], parity_rows(MatrixProfile::Raid6, 3, 1));
+
+        assert_eq!(
+            ReedSolomon::new(5, 3).unwrap(),
+            ReedSolomon::with_profile(MatrixProfile::Default, 5, 3).unwrap()
+        );
+        for &profile in PROFILES.iter() {
+            assert_eq!(0x11D, profile.polynomial());
+        }
+    }
+
+    #[test]
+    fn test_profile_encode_reconstruct() {
+        for &profile in PROFILES.iter() {
+            let r = ReedSolomon::with_profile(profile, 6, 3).unwrap();
+            assert_eq!(r, r.clone());
+
+            let mut shards = vec![vec![0u8; 64]; 9];
+            for shard in shards[..6].iter_mut() {
+                fill_random(shard);
+            }
+            r.encode(&mut shards).unwrap();
+            assert!(r.verify(&shards).unwrap());
+
+            let mut some: Vec<_> = shards.iter().cloned().map(Some).collect();
+            some[0] = None;
+            some[4] = None;
+            some[7] = None;
+            r.reconstruct(&mut some).unwrap();
+            let some: Vec<_> = some.into_iter().map(Option::unwrap).collect();
+            assert_eq!(shards, some);
+        }
+
+        assert_eq!(
+            Err(Error::TooFewParityShards),
+            ReedSolomon::with_profile(MatrixProfile::IsaLCauchy, 3, 0)
+        );
+        assert_eq!(
+            Err(Error::TooManyShards),
+            ReedSolomon::with_profile(MatrixProfile::Par1, 200, 57)
+        );
+    }
+
+    #[test]
+    fn test_raid6_every_pair_of_missing_shards() {
+        let r = ReedSolomon::with_profile(MatrixProfile::Raid6, 10, 2).unwrap();
+
+        let mut shards = vec![vec![0u8; 1000]; 12];
+        for shard in shards[..10].iter_mut() {
+            fill_random(shard);
+        }
+        r.encode(&mut shards).unwrap();
+        let p = shards[..10].iter().fold(vec![0u8; 1000], |mut p, shard| {
+            p.iter_mut().zip(shard.iter()).for_each(|(p, x)| *p ^= x);
+            p
+        });
+        assert_eq!(p, shards[10]);
+
+        for i in 0..12 {
+            for j in i + 1..12 {
+                let mut some: Vec<_> = shards.iter().cloned().map(Some).collect();
+                some[i] = None;
+                some[j] = None;
+                r.reconstruct(&mut some).unwrap();
+                let some: Vec<_> = some.into_iter().map(Option::unwrap).collect();
+                assert_eq!(shards, some);
+            }
+        }
+
+        assert_eq!(
+            Err(Error::TooManyParityShards),
+            ReedSolomon::with_profile(MatrixProfile::Raid6, 10, 3)
+        );
+    }
+
+    #[test]
+    fn test_profile_singular_matrix() {
+        // parity rows 0, 1 and 3 of PAR1 with 3 data shards are linearly dependent
+        let r = ReedSolomon::with_profile(MatrixProfile::Par1, 3, 4).unwrap();
+        let mut shards: Vec<Option<Vec<u8>>> = vec![None; 7];
+        for &i in [3, 4, 6].iter() {
+            shards[i] = Some(vec![1, 2, 3]);
+        }
+
+        assert_eq!(Err(Error::SingularMatrix), r.reconstruct(&mut shards));
+        assert!(shards[..3].iter().all(Option::is_none));
+        assert!(shards[5].is_none());
+
+        // other selections of shards work
+        shards[5] = Some(vec![4, 5, 6]);
+        shards[6] = None;
+        r.reconstruct(&mut shards).unwrap();
+    }
+
+    // Encodes random data shards with `profile` and with `reference`, a
+    // program of the `interop` directory which reads the data shards from
+    // its standard input and writes the parity shards to its standard output.
+    #[cfg(feature = "std")]
+    fn check_reference(
+        profile: MatrixProfile,
+        data_shards: usize,
+        parity_shards: usize,
+        reference: &mut Command,
+    ) {
+        use std::io::Write;
+        use std::process::Stdio;
+
+        let len = 1024;
+        let r = ReedSolomon::with_profile(profile, data_shards, parity_shards).unwrap();
+        let mut shards = vec![vec![0u8; len]; data_shards + parity_shards];
+        for shard in shards[..data_shards].iter_mut() {
+            fill_random(shard);
+        }
+        r.encode(&mut shards).unwrap();
+
+        let mut child = reference
+            .args(&[
+                data_shards.to_string(),
+                parity_shards.to_string(),
+                len.to_string(),
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("failed to run the reference program");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(&shards[..data_shards].concat())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            shards[data_shards..].concat(),
+            output.stdout,
+            "{:?} {}+{}",
+            profile,
+            data_shards,
+            parity_shards
+        );
+    }
+
+    // Compiles a C program of the `interop` directory.
+    #[cfg(feature = "std")]
+    fn build_reference(source: &str, flags: &[&str]) -> std::path::PathBuf {
+        let binary = std::env::temp_dir().join(format!(
+            "rse-{}-{:016x}",
+            source.trim_end_matches(".c"),
+            rand::random::<u64>()
+        ));
+        let status = Command::new("cc")
+            .arg("-O2")
+            .arg("-o")
+            .arg(&binary)
+            .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/interop/").to_string() + source)
+            .args(flags)
+            .status()
+            .expect("failed to run cc");
+        assert!(status.success(), "failed to build {}", source);
+        binary
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[ignore = "needs ISA-L"]
+    fn test_profile_isal_parity() {
+        let binary = build_reference("profile_isal.c", &["-lisal"]);
+        for &(k, m) in [(4, 3), (6, 3), (10, 4), (12, 4)].iter() {
+            for &(kind, profile) in [
+                ("rs", MatrixProfile::IsaLVandermonde),
+                ("cauchy", MatrixProfile::IsaLCauchy),
+            ]
+            .iter()
+            {
+                check_reference(profile, k, m, Command::new(&binary).arg(kind));
+            }
+        }
+        // RAID-6 is ISA-L's Vandermonde matrix with two parity shards
+        check_reference(MatrixProfile::Raid6, 8, 2, Command::new(&binary).arg("rs"));
+        std::fs::remove_file(binary).unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[ignore = "needs Jerasure"]
+    fn test_profile_jerasure_parity() {
+        let binary = build_reference(
+            "profile_jerasure.c",
+            &[
+                "-I/usr/include/jerasure",
+                "-I/usr/local/include/jerasure",
+                "-lJerasure",
+                "-lgf_complete",
+            ],
+        );
+        for &(k, m) in [(3, 2), (4, 3), (6, 3), (10, 4)].iter() {
+            check_reference(
+                MatrixProfile::JerasureVandermonde,
+                k,
+                m,
+                &mut Command::new(&binary),
+            );
+        }
+        std::fs::remove_file(binary).unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[ignore = "needs Hadoop"]
+    fn test_profile_hdfs_parity() {
+        let classpath = Command::new("hadoop")
+            .arg("classpath")
+            .output()
+            .expect("failed to run hadoop");
+        let classpath = String::from_utf8(classpath.stdout).unwrap();
+        for &(k, m) in [(3, 2), (6, 3), (10, 4)].iter() {
+            check_reference(
+                MatrixProfile::HdfsRs,
+                k,
+                m,
+                Command::new("java")
+                    .arg("-cp")
+                    .arg(classpath.trim())
+                    .arg(concat!(
+                        env!("CARGO_MANIFEST_DIR"),
+                        "/interop/ProfileHdfs.java"
+                    )),
+            );
+        }
+    }
+}
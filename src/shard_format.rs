@@ -0,0 +1,704 @@
+//! Self-describing shard frames for storage and transport.
+//!
+//! A frame carries a single shard of a stripe together with everything needed
+//! to put it back in place: the codec parameters, the index of the shard,
+//! the length of the original object, the stripe it belongs to, a generation
+//! number, and a fingerprint of the generator matrix. A checksum protects the
+//! whole frame.
+//!
+//! # Layout
+//!
+//! All integers are little-endian.
+//!
+//! | Offset | Size | Field |
+//! | --- | --- | --- |
+//! | 0  | 4 | magic bytes `RSEF` |
+//! | 4  | 1 | format version, currently `1` |
+//! | 5  | 1 | field size in bits, always `8` |
+//! | 6  | 1 | checksum kind, see `Checksum::ID` |
+//! | 7  | 1 | checksum length in bytes |
+//! | 8  | 4 | data shard count |
+//! | 12 | 4 | parity shard count |
+//! | 16 | 4 | shard index |
+//! | 20 | 4 | reserved, zero |
+//! | 24 | 8 | payload length in bytes |
+//! | 32 | 8 | object length in bytes |
+//! | 40 | 8 | stripe ID |
+//! | 48 | 8 | generation |
+//! | 56 | 8 | generator matrix fingerprint |
+//! | 64 | checksum length | checksum over the header and the payload |
+//! | 64 + checksum length | payload length | payload |
+//!
+//! # Example
+//!
+//! ```
+//! # use reed_solomon_erasure::galois_8::ReedSolomon;
+//! # use reed_solomon_erasure::shard_format::{decode_stripe, encode_stripe, Crc32c};
+//! let r = ReedSolomon::new(3, 2).unwrap();
+//!
+//! let object = b"hello, world";
+//! let mut frames = encode_stripe::<Crc32c>(&r, 42, 1, object).unwrap();
+//!
+//! // lose one frame and corrupt another
+//! frames.remove(0);
+//! frames[1][70] ^= 1;
+//!
+//! assert_eq!(&object[..], &decode_stripe::<Crc32c, _>(&r, 42, &frames).unwrap()[..]);
+//! ```
+
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::errors::{Error, FormatError};
+use crate::galois_8::ReedSolomon;
+
+/// Magic bytes at the start of every frame.
+pub const MAGIC: [u8; 4] = *b"RSEF";
+
+/// Current version of the frame format.
+pub const VERSION: u8 = 1;
+
+/// Length of the fixed part of the frame header.
+pub const HEADER_LEN: usize = 64;
+
+const FIELD_BITS: u8 = 8;
+
+/// Checksum algorithm protecting a frame.
+pub trait Checksum: Sized {
+    /// Identifier of the algorithm, stored in the frame header.
+    const ID: u8;
+
+    /// Length of the checksum in bytes, at most 255.
+    const LEN: usize;
+
+    /// Starts a new checksum computation.
+    fn new() -> Self;
+
+    /// Feeds data into the checksum.
+    fn update(&mut self, data: &[u8]);
+
+    /// Writes the checksum into `out`, which is `LEN` bytes long.
+    fn finish(self, out: &mut [u8]);
+}
+
+const fn crc32c_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82F6_3B78
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+static CRC32C_TABLE: [u32; 256] = crc32c_table();
+
+/// CRC-32C (Castagnoli), with ID `1`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Crc32c(u32);
+
+impl Checksum for Crc32c {
+    const ID: u8 = 1;
+    const LEN: usize = 4;
+
+    fn new() -> Crc32c {
+        Crc32c(!0)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for &b in data.iter() {
+            self.0 = CRC32C_TABLE[((self.0 ^ b as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    fn finish(self, out: &mut [u8]) {
+        out.copy_from_slice(&(!self.0).to_le_bytes());
+    }
+}
+
+/// XXH64 with seed 0, with ID `2`.
+///
+/// Only available with the `xxhash` feature enabled.
+#[cfg(feature = "xxhash")]
+#[derive(Clone)]
+pub struct XxHash64(xxhash_rust::xxh64::Xxh64);
+
+#[cfg(feature = "xxhash")]
+impl Checksum for XxHash64 {
+    const ID: u8 = 2;
+    const LEN: usize = 8;
+
+    fn new() -> XxHash64 {
+        XxHash64(xxhash_rust::xxh64::Xxh64::new(0))
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finish(self, out: &mut [u8]) {
+        out.copy_from_slice(&self.0.digest().to_le_bytes());
+    }
+}
+
+/// BLAKE3 with a 32 byte output, with ID `3`.
+///
+/// Only available with the `blake3` feature enabled.
+#[cfg(feature = "blake3")]
+#[derive(Clone)]
+pub struct Blake3(blake3::Hasher);
+
+#[cfg(feature = "blake3")]
+impl Checksum for Blake3 {
+    const ID: u8 = 3;
+    const LEN: usize = 32;
+
+    fn new() -> Blake3 {
+        Blake3(blake3::Hasher::new())
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finish(self, out: &mut [u8]) {
+        out.copy_from_slice(self.0.finalize().as_bytes());
+    }
+}
+
+/// Computes the fingerprint of the generator matrix of a codec.
+///
+/// This is the 64-bit FNV-1a hash of the shard counts followed by
+/// the rows of the generator matrix.
+pub fn matrix_fingerprint(codec: &ReedSolomon) -> u64 {
+    let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for &b in bytes.iter() {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+        }
+    };
+
+    feed(&(codec.data_shard_count() as u32).to_le_bytes());
+    feed(&(codec.parity_shard_count() as u32).to_le_bytes());
+    for i in 0..codec.total_shard_count() {
+        feed(codec.matrix().get_row(i));
+    }
+
+    hash
+}
+
+/// Header of a shard frame.
+///
+/// Frames only carry shards of `galois_8` codecs: `field_bits` is always `8`,
+/// and headers with any other value never match a codec.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ShardHeader {
+    /// Size of the field in bits, `8` for the GF(2^8) of `galois_8`.
+    pub field_bits: u8,
+    /// Number of data shards of the codec.
+    pub data_shards: u32,
+    /// Number of parity shards of the codec.
+    pub parity_shards: u32,
+    /// Index of the shard in the stripe.
+    pub index: u32,
+    /// Length of the shard in bytes.
+    pub payload_len: u64,
+    /// Length of the object encoded in the stripe, excluding padding.
+    pub object_len: u64,
+    /// Identifier of the stripe.
+    pub stripe_id: u64,
+    /// Generation of the stripe, incremented on every rewrite.
+    pub generation: u64,
+    /// Fingerprint of the generator matrix, see `matrix_fingerprint`.
+    pub matrix_fingerprint: u64,
+}
+
+impl ShardHeader {
+    /// Creates the header of the shard at `index` of a stripe encoded with `codec`.
+    pub fn new(
+        codec: &ReedSolomon,
+        index: usize,
+        payload_len: usize,
+        object_len: u64,
+        stripe_id: u64,
+        generation: u64,
+    ) -> ShardHeader {
+        ShardHeader {
+            field_bits: FIELD_BITS,
+            data_shards: codec.data_shard_count() as u32,
+            parity_shards: codec.parity_shard_count() as u32,
+            index: index as u32,
+            payload_len: payload_len as u64,
+            object_len,
+            stripe_id,
+            generation,
+            matrix_fingerprint: matrix_fingerprint(codec),
+        }
+    }
+
+    /// Checks that the header describes a shard which can be decoded with `codec`.
+    pub fn matches(&self, codec: &ReedSolomon) -> bool {
+        self.matches_fingerprint(codec, matrix_fingerprint(codec))
+    }
+
+    /// Same as `matches`, with `fingerprint` the result of `matrix_fingerprint`
+    /// for `codec`, to be computed once when checking many headers.
+    pub fn matches_fingerprint(&self, codec: &ReedSolomon, fingerprint: u64) -> bool {
+        self.field_bits == FIELD_BITS
+            && self.data_shards as usize == codec.data_shard_count()
+            && self.parity_shards as usize == codec.parity_shard_count()
+            && (self.index as usize) < codec.total_shard_count()
+            && self.object_len <= self.payload_len.saturating_mul(self.data_shards as u64)
+            && self.matrix_fingerprint == fingerprint
+    }
+
+    fn write(&self, checksum_id: u8, checksum_len: usize, out: &mut [u8]) {
+        out[0..4].copy_from_slice(&MAGIC);
+        out[4] = VERSION;
+        out[5] = self.field_bits;
+        out[6] = checksum_id;
+        out[7] = checksum_len as u8;
+        out[8..12].copy_from_slice(&self.data_shards.to_le_bytes());
+        out[12..16].copy_from_slice(&self.parity_shards.to_le_bytes());
+        out[16..20].copy_from_slice(&self.index.to_le_bytes());
+        out[20..24].copy_from_slice(&[0; 4]);
+        out[24..32].copy_from_slice(&self.payload_len.to_le_bytes());
+        out[32..40].copy_from_slice(&self.object_len.to_le_bytes());
+        out[40..48].copy_from_slice(&self.stripe_id.to_le_bytes());
+        out[48..56].copy_from_slice(&self.generation.to_le_bytes());
+        out[56..64].copy_from_slice(&self.matrix_fingerprint.to_le_bytes());
+    }
+
+    fn read(bytes: &[u8]) -> Result<(ShardHeader, u8, usize), FormatError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(FormatError::TooShort);
+        }
+        if bytes[0..4] != MAGIC {
+            return Err(FormatError::InvalidMagic);
+        }
+        if bytes[4] != VERSION {
+            return Err(FormatError::UnsupportedVersion);
+        }
+
+        let u32_at = |i: usize| {
+            let mut buf = [0; 4];
+            buf.copy_from_slice(&bytes[i..i + 4]);
+            u32::from_le_bytes(buf)
+        };
+        let u64_at = |i: usize| {
+            let mut buf = [0; 8];
+            buf.copy_from_slice(&bytes[i..i + 8]);
+            u64::from_le_bytes(buf)
+        };
+
+        let header = ShardHeader {
+            field_bits: bytes[5],
+            data_shards: u32_at(8),
+            parity_shards: u32_at(12),
+            index: u32_at(16),
+            payload_len: u64_at(24),
+            object_len: u64_at(32),
+            stripe_id: u64_at(40),
+            generation: u64_at(48),
+            matrix_fingerprint: u64_at(56),
+        };
+
+        Ok((header, bytes[6], bytes[7] as usize))
+    }
+}
+
+/// Length of a frame holding a payload of `payload_len` bytes.
+pub fn frame_len<C: Checksum>(payload_len: usize) -> usize {
+    HEADER_LEN + C::LEN + payload_len
+}
+
+/// Length of the frame starting with `header`, which must hold at least
+/// `HEADER_LEN` bytes.
+///
+/// Nothing beyond the header is checked, this is meant for reading
+/// frames one after another from a stream before parsing them.
+pub fn peek_frame_len(header: &[u8]) -> Result<usize, FormatError> {
+    let (header, _, checksum_len) = ShardHeader::read(header)?;
+
+    if header.payload_len > (usize::MAX - HEADER_LEN - checksum_len) as u64 {
+        return Err(FormatError::TooShort);
+    }
+
+    Ok(HEADER_LEN + checksum_len + header.payload_len as usize)
+}
+
+/// Appends the frame holding `payload` to `out`.
+///
+/// Returns `Error::IncorrectShardSize` if the payload length
+/// does not match `header.payload_len`.
+pub fn write_frame<C: Checksum>(
+    header: &ShardHeader,
+    payload: &[u8],
+    out: &mut Vec<u8>,
+) -> Result<(), Error> {
+    if payload.len() as u64 != header.payload_len {
+        return Err(Error::IncorrectShardSize);
+    }
+
+    let start = out.len();
+    out.resize(start + HEADER_LEN + C::LEN, 0);
+
+    let (header_bytes, checksum_bytes) = out[start..].split_at_mut(HEADER_LEN);
+    header.write(C::ID, C::LEN, header_bytes);
+
+    let mut checksum = C::new();
+    checksum.update(header_bytes);
+    checksum.update(payload);
+    checksum.finish(checksum_bytes);
+
+    out.extend_from_slice(payload);
+
+    Ok(())
+}
+
+/// Parses a frame, checking its checksum.
+///
+/// Returns the header and the payload. Bytes following the frame are ignored,
+/// the length of the frame is `frame_len::<C>(payload.len())`.
+pub fn parse_frame<C: Checksum>(frame: &[u8]) -> Result<(ShardHeader, &[u8]), FormatError> {
+    let (header, checksum_id, checksum_len) = ShardHeader::read(frame)?;
+    if checksum_id != C::ID || checksum_len != C::LEN {
+        return Err(FormatError::ChecksumKindMismatch);
+    }
+
+    let payload_start = HEADER_LEN + C::LEN;
+    let payload_len = header.payload_len;
+    if ((frame.len() - payload_start.min(frame.len())) as u64) < payload_len {
+        return Err(FormatError::TooShort);
+    }
+    let payload = &frame[payload_start..payload_start + payload_len as usize];
+
+    let mut expected = [0u8; 255];
+    let mut checksum = C::new();
+    checksum.update(&frame[..HEADER_LEN]);
+    checksum.update(payload);
+    checksum.finish(&mut expected[..C::LEN]);
+
+    if expected[..C::LEN] != frame[HEADER_LEN..payload_start] {
+        return Err(FormatError::ChecksumMismatch);
+    }
+
+    Ok((header, payload))
+}
+
+/// Splits `object` into data shards, padding the last one with zeros,
+/// encodes them and frames all shards of the stripe.
+///
+/// Returns `Error::EmptyShard` if `object` is empty.
+pub fn encode_stripe<C: Checksum>(
+    codec: &ReedSolomon,
+    stripe_id: u64,
+    generation: u64,
+    object: &[u8],
+) -> Result<Vec<Vec<u8>>, Error> {
+    if object.is_empty() {
+        return Err(Error::EmptyShard);
+    }
+
+    let data_shards = codec.data_shard_count();
+    let shard_len = object.len().div_ceil(data_shards);
+
+    let mut shards = vec![vec![0u8; shard_len]; codec.total_shard_count()];
+    for (shard, chunk) in shards.iter_mut().zip(object.chunks(shard_len)) {
+        shard[..chunk.len()].copy_from_slice(chunk);
+    }
+    codec.encode(&mut shards)?;
+
+    let mut header = ShardHeader::new(
+        codec,
+        0,
+        shard_len,
+        object.len() as u64,
+        stripe_id,
+        generation,
+    );
+    let mut frames = Vec::with_capacity(shards.len());
+    for (index, shard) in shards.iter().enumerate() {
+        header.index = index as u32;
+        let mut frame = Vec::with_capacity(frame_len::<C>(shard_len));
+        write_frame::<C>(&header, shard, &mut frame)?;
+        frames.push(frame);
+    }
+
+    Ok(frames)
+}
+
+/// Recovers the object stored in the stripe `stripe_id` from a set of frames.
+///
+/// Frames which fail to parse, fail their checksum, do not match the codec,
+/// or belong to a different stripe are discarded. If frames of several
+/// generations of the stripe are present, only the latest generation is used.
+/// Duplicate frames of the same shard are ignored.
+///
+/// Returns `FormatError::CodecMismatch` if frames of the stripe are present
+/// but none of them matches the codec.
+///
+/// Returns `FormatError::RSError(Error::TooFewShardsPresent)` if the remaining
+/// frames are not enough to reconstruct the object.
+pub fn decode_stripe<C: Checksum, T: AsRef<[u8]>>(
+    codec: &ReedSolomon,
+    stripe_id: u64,
+    frames: &[T],
+) -> Result<Vec<u8>, FormatError> {
+    let fingerprint = matrix_fingerprint(codec);
+    let (parsed, mismatched): (Vec<(ShardHeader, &[u8])>, Vec<_>) = frames
+        .iter()
+        .filter_map(|frame| parse_frame::<C>(frame.as_ref()).ok())
+        .filter(|(header, _)| header.stripe_id == stripe_id)
+        .partition(|(header, _)| header.matches_fingerprint(codec, fingerprint));
+
+    if parsed.is_empty() && !mismatched.is_empty() {
+        return Err(FormatError::CodecMismatch);
+    }
+
+    let latest = match parsed.iter().map(|(header, _)| header.generation).max() {
+        Some(generation) => {
+            parsed
+                .iter()
+                .find(|(header, _)| header.generation == generation)
+                .expect("generation taken from a parsed frame; qed")
+                .0
+        }
+        None => return Err(FormatError::RSError(Error::TooFewShardsPresent)),
+    };
+
+    let mut shards: Vec<Option<Vec<u8>>> = vec![None; codec.total_shard_count()];
+    for (header, payload) in parsed.iter() {
+        if header.generation == latest.generation
+            && header.payload_len == latest.payload_len
+            && header.object_len == latest.object_len
+        {
+            let slot = &mut shards[header.index as usize];
+            if slot.is_none() {
+                *slot = Some(payload.to_vec());
+            }
+        }
+    }
+
+    codec
+        .reconstruct_data(&mut shards)
+        .map_err(FormatError::RSError)?;
+
+    let mut object = Vec::with_capacity(latest.object_len as usize);
+    for shard in shards[..codec.data_shard_count()].iter() {
+        object.extend_from_slice(shard.as_ref().expect("data shards reconstructed; qed"));
+    }
+    object.truncate(latest.object_len as usize);
+
+    Ok(object)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::tests::fill_random;
+
+    #[test]
+    fn test_crc32c_check_value() {
+        let mut checksum = Crc32c::new();
+        checksum.update(b"123456789");
+        let mut out = [0; 4];
+        checksum.finish(&mut out);
+        assert_eq!(0xE306_9283, u32::from_le_bytes(out));
+    }
+
+    #[test]
+    fn test_frame_round_trip() {
+        let r = ReedSolomon::new(4, 2).unwrap();
+        let header = ShardHeader::new(&r, 5, 10, 33, 7, 2);
+
+        let mut frame = Vec::new();
+        write_frame::<Crc32c>(&header, &[9; 10], &mut frame).unwrap();
+        assert_eq!(frame_len::<Crc32c>(10), frame.len());
+        assert_eq!(Ok(frame.len()), peek_frame_len(&frame[..HEADER_LEN]));
+        assert_eq!(Err(FormatError::TooShort), peek_frame_len(&frame[..10]));
+
+        // trailing bytes are ignored
+        frame.extend_from_slice(&[1, 2, 3]);
+
+        let (parsed, payload) = parse_frame::<Crc32c>(&frame).unwrap();
+        assert_eq!(header, parsed);
+        assert_eq!(&[9; 10], payload);
+        assert!(parsed.matches(&r));
+        assert!(!parsed.matches(&ReedSolomon::new(4, 3).unwrap()));
+        assert!(parsed.matches_fingerprint(&r, matrix_fingerprint(&r)));
+        assert!(!parsed.matches_fingerprint(&r, matrix_fingerprint(&r) ^ 1));
+
+        let mut other_field = parsed;
+        other_field.field_bits = 16;
+        assert!(!other_field.matches(&r));
+    }
+
+    #[test]
+    fn test_frame_error_handling() {
+        let r = ReedSolomon::new(4, 2).unwrap();
+        let header = ShardHeader::new(&r, 0, 10, 33, 7, 2);
+
+        let mut frame = Vec::new();
+        assert_eq!(
+            Error::IncorrectShardSize,
+            write_frame::<Crc32c>(&header, &[9; 11], &mut frame).unwrap_err()
+        );
+        write_frame::<Crc32c>(&header, &[9; 10], &mut frame).unwrap();
+
+        assert_eq!(
+            FormatError::TooShort,
+            parse_frame::<Crc32c>(&frame[..HEADER_LEN - 1]).unwrap_err()
+        );
+        assert_eq!(
+            FormatError::TooShort,
+            parse_frame::<Crc32c>(&frame[..frame.len() - 1]).unwrap_err()
+        );
+
+        let mut bad = frame.clone();
+        bad[0] = b'X';
+        assert_eq!(
+            FormatError::InvalidMagic,
+            parse_frame::<Crc32c>(&bad).unwrap_err()
+        );
+
+        let mut bad = frame.clone();
+        bad[4] = VERSION + 1;
+        assert_eq!(
+            FormatError::UnsupportedVersion,
+            parse_frame::<Crc32c>(&bad).unwrap_err()
+        );
+
+        let mut bad = frame.clone();
+        bad[6] = 0xFF;
+        assert_eq!(
+            FormatError::ChecksumKindMismatch,
+            parse_frame::<Crc32c>(&bad).unwrap_err()
+        );
+
+        // every byte but the magic, version, checksum kind and payload length
+        for i in (8..24)
+            .chain(32..HEADER_LEN)
+            .chain(HEADER_LEN + 4..frame.len())
+        {
+            let mut bad = frame.clone();
+            bad[i] ^= 0x10;
+            assert_eq!(
+                FormatError::ChecksumMismatch,
+                parse_frame::<Crc32c>(&bad).unwrap_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_stripe_discards_bad_frames() {
+        let r = ReedSolomon::new(5, 3).unwrap();
+
+        let mut object = vec![0u8; 1234];
+        fill_random(&mut object);
+
+        let old = encode_stripe::<Crc32c>(&r, 1, 1, &object[..1000]).unwrap();
+        let mut frames = encode_stripe::<Crc32c>(&r, 1, 2, &object).unwrap();
+        let other_stripe = encode_stripe::<Crc32c>(&r, 2, 3, &object).unwrap();
+        let other_codec =
+            encode_stripe::<Crc32c>(&ReedSolomon::new(4, 4).unwrap(), 1, 3, &object).unwrap();
+
+        // corrupt two frames, drop one
+        frames[0][HEADER_LEN + 10] ^= 1;
+        frames[6][20] ^= 1;
+        frames.remove(3);
+
+        frames.extend(old);
+        frames.extend(other_stripe);
+        frames.extend(other_codec);
+        frames.push(frames[1].clone());
+
+        assert_eq!(object, decode_stripe::<Crc32c, _>(&r, 1, &frames).unwrap());
+
+        frames.retain(|frame| parse_frame::<Crc32c>(frame).map(|(h, _)| h.generation) == Ok(2));
+        frames.truncate(4);
+        assert_eq!(
+            FormatError::RSError(Error::TooFewShardsPresent),
+            decode_stripe::<Crc32c, _>(&r, 1, &frames).unwrap_err()
+        );
+        assert_eq!(
+            FormatError::RSError(Error::TooFewShardsPresent),
+            decode_stripe::<Crc32c, _>(&r, 1, &Vec::<Vec<u8>>::new()).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_decode_stripe_codec_mismatch() {
+        let r = ReedSolomon::new(5, 3).unwrap();
+
+        let mut object = vec![0u8; 1234];
+        fill_random(&mut object);
+
+        let frames = encode_stripe::<Crc32c>(&r, 1, 1, &object).unwrap();
+
+        for other in [
+            ReedSolomon::new(4, 3).unwrap(),
+            ReedSolomon::new(5, 2).unwrap(),
+            ReedSolomon::new(5, 4).unwrap(),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                FormatError::CodecMismatch,
+                decode_stripe::<Crc32c, _>(other, 1, &frames).unwrap_err()
+            );
+        }
+
+        // frames of other stripes do not count as a mismatch
+        assert_eq!(
+            FormatError::RSError(Error::TooFewShardsPresent),
+            decode_stripe::<Crc32c, _>(&ReedSolomon::new(4, 3).unwrap(), 2, &frames).unwrap_err()
+        );
+    }
+
+    #[cfg(feature = "xxhash")]
+    #[test]
+    fn test_xxhash64_round_trip() {
+        let r = ReedSolomon::new(3, 2).unwrap();
+        let frames = encode_stripe::<XxHash64>(&r, 1, 1, b"hello, world").unwrap();
+
+        assert_eq!(
+            FormatError::ChecksumKindMismatch,
+            parse_frame::<Crc32c>(&frames[0]).unwrap_err()
+        );
+        assert_eq!(
+            &b"hello, world"[..],
+            &decode_stripe::<XxHash64, _>(&r, 1, &frames[2..]).unwrap()[..]
+        );
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_blake3_round_trip() {
+        let r = ReedSolomon::new(3, 2).unwrap();
+        let frames = encode_stripe::<Blake3>(&r, 1, 1, b"hello, world").unwrap();
+
+        assert_eq!(
+            &b"hello, world"[..],
+            &decode_stripe::<Blake3, _>(&r, 1, &frames[2..]).unwrap()[..]
+        );
+    }
+}
@@ -0,0 +1,643 @@
+//! Streaming encoding of arbitrary-length data.
+//!
+//! `StreamEncoder` cuts its input into stripes of `data_shard_count` shards of
+//! a fixed shard size, encodes every stripe, and appends each shard to its own
+//! output stream. The last stripe is padded with zeros. `StreamDecoder` reads
+//! the shard streams back stripe by stripe, reconstructing the shards of
+//! missing or failed streams.
+//!
+//! Each shard stream is therefore a sequence of shard-sized chunks, one per
+//! stripe, followed by a trailer of `TRAILER_LEN` bytes:
+//!
+//! | Offset | Size | Field |
+//! | --- | --- | --- |
+//! | 0 | 4 | magic bytes `RSET` |
+//! | 4 | 4 | reserved, zero |
+//! | 8 | 8 | total length of the input in bytes, little-endian |
+//!
+//! This module is only available with the `std` feature enabled.
+
+use std::io::{self, Read, Write};
+
+use crate::errors::Error;
+use crate::galois_8::ReedSolomon;
+
+/// Magic bytes at the start of the trailer of every shard stream.
+pub const TRAILER_MAGIC: [u8; 4] = *b"RSET";
+
+/// Length of the trailer of every shard stream.
+pub const TRAILER_LEN: usize = 16;
+
+pub(crate) fn invalid_input(e: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e)
+}
+
+pub(crate) fn invalid_data(e: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+pub(crate) fn make_trailer(total_len: u64) -> [u8; TRAILER_LEN] {
+    let mut trailer = [0; TRAILER_LEN];
+    trailer[0..4].copy_from_slice(&TRAILER_MAGIC);
+    trailer[8..16].copy_from_slice(&total_len.to_le_bytes());
+    trailer
+}
+
+fn parse_trailer(trailer: &[u8]) -> io::Result<u64> {
+    if trailer[0..4] != TRAILER_MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid shard stream trailer",
+        ));
+    }
+
+    let mut total_len = [0; 8];
+    total_len.copy_from_slice(&trailer[8..16]);
+    Ok(u64::from_le_bytes(total_len))
+}
+
+// Reads until `buf` is full or the end of the stream is reached,
+// returns the number of bytes read.
+fn read_full<R: Read>(input: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Encoder from a single `Read` source into one `Write` sink per shard.
+///
+/// Memory use is bounded by a single stripe, i.e.
+/// `total_shard_count * shard_size` bytes.
+///
+/// # Example
+///
+/// ```
+/// # use reed_solomon_erasure::galois_8::ReedSolomon;
+/// # use reed_solomon_erasure::stream::{StreamEncoder, TRAILER_LEN};
+/// let r = ReedSolomon::new(3, 2).unwrap();
+/// let encoder = StreamEncoder::new(&r, 4).unwrap();
+///
+/// let input = b"a stream of bytes";
+/// let mut outputs = vec![Vec::new(); 5];
+/// assert_eq!(17, encoder.encode(&input[..], &mut outputs).unwrap());
+///
+/// // 17 bytes make 2 stripes of 3 * 4 bytes
+/// for output in outputs.iter() {
+///     assert_eq!(2 * 4 + TRAILER_LEN, output.len());
+/// }
+/// ```
+#[derive(PartialEq, Debug)]
+pub struct StreamEncoder<'a> {
+    codec: &'a ReedSolomon,
+    shard_size: usize,
+}
+
+impl<'a> StreamEncoder<'a> {
+    /// Creates a new stream encoder cutting the input into shards of `shard_size` bytes.
+    ///
+    /// Returns `Error::EmptyShard` if `shard_size == 0`.
+    pub fn new(codec: &'a ReedSolomon, shard_size: usize) -> Result<StreamEncoder<'a>, Error> {
+        if shard_size == 0 {
+            return Err(Error::EmptyShard);
+        }
+
+        Ok(StreamEncoder { codec, shard_size })
+    }
+
+    /// The size of a shard of a stripe in bytes.
+    pub fn shard_size(&self) -> usize {
+        self.shard_size
+    }
+
+    /// Encodes all of `input`, writing the shard streams into `outputs`.
+    ///
+    /// Returns the total number of bytes read from `input`.
+    ///
+    /// Returns an error of kind `InvalidInput` wrapping `Error::TooFewShards`
+    /// or `Error::TooManyShards` if the number of outputs does not match the
+    /// total shard count of the codec. I/O errors of the input and outputs are
+    /// passed through.
+    pub fn encode<R: Read, W: Write>(&self, mut input: R, outputs: &mut [W]) -> io::Result<u64> {
+        if outputs.len() < self.codec.total_shard_count() {
+            return Err(invalid_input(Error::TooFewShards));
+        }
+        if outputs.len() > self.codec.total_shard_count() {
+            return Err(invalid_input(Error::TooManyShards));
+        }
+
+        let data_shards = self.codec.data_shard_count();
+
+        let mut shards = vec![vec![0u8; self.shard_size]; self.codec.total_shard_count()];
+        let mut total_len: u64 = 0;
+
+        loop {
+            let mut stripe_len = 0;
+            for shard in shards[..data_shards].iter_mut() {
+                let n = read_full(&mut input, shard)?;
+                // pad the rest of the stripe
+                for b in shard[n..].iter_mut() {
+                    *b = 0;
+                }
+                stripe_len += n;
+            }
+
+            if stripe_len == 0 {
+                break;
+            }
+            total_len += stripe_len as u64;
+
+            {
+                let (data, parity) = shards.split_at_mut(data_shards);
+                self.codec.encode_sep(data, parity).map_err(invalid_input)?;
+            }
+
+            for (shard, output) in shards.iter().zip(outputs.iter_mut()) {
+                output.write_all(shard)?;
+            }
+
+            if stripe_len < data_shards * self.shard_size {
+                break;
+            }
+        }
+
+        let trailer = make_trailer(total_len);
+        for output in outputs.iter_mut() {
+            output.write_all(&trailer)?;
+            output.flush()?;
+        }
+
+        Ok(total_len)
+    }
+}
+
+pub(crate) enum Chunk {
+    Shard,
+    Trailer(u64),
+}
+
+// Read-ahead buffer of a shard stream. A chunk is only known to be a shard
+// once at least `TRAILER_LEN` more bytes follow it.
+pub(crate) struct Lookahead {
+    buf: Vec<u8>,
+    filled: usize,
+}
+
+impl Lookahead {
+    pub(crate) fn new(shard_size: usize) -> Lookahead {
+        Lookahead {
+            buf: vec![0; shard_size + TRAILER_LEN],
+            filled: 0,
+        }
+    }
+
+    // The part of the buffer to read into, to be filled as far as possible
+    // before calling `take_chunk`.
+    pub(crate) fn unfilled(&mut self) -> &mut [u8] {
+        &mut self.buf[self.filled..]
+    }
+
+    // Takes the next chunk after `n_read` more bytes were read into the buffer,
+    // copying it into `shard` if it is a shard.
+    pub(crate) fn take_chunk(&mut self, n_read: usize, shard: &mut [u8]) -> io::Result<Chunk> {
+        self.filled += n_read;
+
+        if self.filled == self.buf.len() {
+            let shard_size = shard.len();
+            shard.copy_from_slice(&self.buf[..shard_size]);
+            self.buf.copy_within(shard_size.., 0);
+            self.filled = TRAILER_LEN;
+            Ok(Chunk::Shard)
+        } else if self.filled == TRAILER_LEN {
+            parse_trailer(&self.buf[..TRAILER_LEN]).map(Chunk::Trailer)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated shard stream",
+            ))
+        }
+    }
+
+    fn next_chunk<R: Read>(&mut self, reader: &mut R, shard: &mut [u8]) -> io::Result<Chunk> {
+        let n_read = read_full(reader, self.unfilled())?;
+        self.take_chunk(n_read, shard)
+    }
+}
+
+// The data of the last decoded stripe is only written once it is known
+// whether the stripe is the last one, which needs trimming.
+pub(crate) struct HeldStripe {
+    data: Vec<u8>,
+    has_stripe: bool,
+    written: u64,
+}
+
+impl HeldStripe {
+    pub(crate) fn new(stripe_len: usize) -> HeldStripe {
+        HeldStripe {
+            data: Vec::with_capacity(stripe_len),
+            has_stripe: false,
+            written: 0,
+        }
+    }
+
+    // The data of the held stripe, which is not the last one once a new
+    // stripe has been decoded.
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    // Replaces the held stripe, after its data has been written, with the
+    // data shards of a new stripe.
+    pub(crate) fn replace(&mut self, data_shards: &[(Vec<u8>, bool)]) {
+        self.written += self.data.len() as u64;
+
+        self.data.clear();
+        for (shard, _) in data_shards.iter() {
+            self.data.extend_from_slice(shard);
+        }
+        self.has_stripe = true;
+    }
+
+    // The data of the held stripe as the last one, trimmed to the total length.
+    pub(crate) fn last(&self, total_len: u64) -> io::Result<&[u8]> {
+        // the last stripe must contain between 1 and `stripe_len` bytes
+        // of the input, and there is no stripe at all for an empty input
+        let valid = if self.has_stripe {
+            self.written < total_len && total_len <= self.written + self.data.len() as u64
+        } else {
+            total_len == 0
+        };
+        if !valid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "total length in shard stream trailer does not match the shard streams",
+            ));
+        }
+
+        Ok(&self.data[..(total_len - self.written) as usize])
+    }
+}
+
+// Picks the total length recorded by most of the trailers, and marks the
+// streams which disagree as missing.
+pub(crate) fn vote_total_len<R>(
+    inputs: &mut [Option<R>],
+    trailers: &[(usize, u64)],
+) -> io::Result<u64> {
+    let total_len = match trailers
+        .iter()
+        .map(|&(_, a)| (trailers.iter().filter(|&&(_, b)| a == b).count(), a))
+        .max()
+    {
+        Some((_, total_len)) => total_len,
+        None => return Err(invalid_data(Error::TooFewShardsPresent)),
+    };
+
+    for &(i_input, len) in trailers.iter() {
+        if len != total_len {
+            inputs[i_input] = None;
+        }
+    }
+
+    Ok(total_len)
+}
+
+/// Decoder from the shard streams written by `StreamEncoder` back into the
+/// original bytes.
+///
+/// Memory use is bounded by about two stripes.
+///
+/// # Example
+///
+/// ```
+/// # use reed_solomon_erasure::galois_8::ReedSolomon;
+/// # use reed_solomon_erasure::stream::{StreamDecoder, StreamEncoder};
+/// let r = ReedSolomon::new(3, 2).unwrap();
+///
+/// let input = b"a stream of bytes";
+/// let mut outputs = vec![Vec::new(); 5];
+/// StreamEncoder::new(&r, 4).unwrap().encode(&input[..], &mut outputs).unwrap();
+///
+/// // lose two of the shard streams
+/// let mut inputs: Vec<_> = outputs.iter().map(|o| Some(&o[..])).collect();
+/// inputs[0] = None;
+/// inputs[3] = None;
+///
+/// let mut decoded = Vec::new();
+/// StreamDecoder::new(&r, 4).unwrap().decode(&mut inputs, &mut decoded).unwrap();
+/// assert_eq!(&input[..], &decoded[..]);
+/// ```
+#[derive(PartialEq, Debug)]
+pub struct StreamDecoder<'a> {
+    codec: &'a ReedSolomon,
+    shard_size: usize,
+}
+
+impl<'a> StreamDecoder<'a> {
+    /// Creates a new stream decoder for shard streams written with
+    /// shards of `shard_size` bytes.
+    ///
+    /// Returns `Error::EmptyShard` if `shard_size == 0`.
+    pub fn new(codec: &'a ReedSolomon, shard_size: usize) -> Result<StreamDecoder<'a>, Error> {
+        if shard_size == 0 {
+            return Err(Error::EmptyShard);
+        }
+
+        Ok(StreamDecoder { codec, shard_size })
+    }
+
+    /// The size of a shard of a stripe in bytes.
+    pub fn shard_size(&self) -> usize {
+        self.shard_size
+    }
+
+    /// Decodes the shard streams in `inputs`, writing the original bytes into `output`.
+    ///
+    /// Missing shard streams are marked by `None`. A stream which fails with
+    /// an I/O error, ends early or has a malformed trailer is set to `None`
+    /// and treated as missing for the rest of the decoding, so `inputs`
+    /// tells which streams were usable afterwards.
+    ///
+    /// Returns the total number of bytes written to `output`, as recorded
+    /// in the trailers.
+    ///
+    /// Returns an error of kind `InvalidInput` wrapping `Error::TooFewShards`
+    /// or `Error::TooManyShards` if the number of inputs does not match the
+    /// total shard count of the codec, and an error of kind `InvalidData`
+    /// if fewer than `data_shard_count` streams remain for a stripe or the
+    /// trailers are inconsistent with the decoded data. I/O errors of the
+    /// output are passed through.
+    pub fn decode<R: Read, W: Write>(
+        &self,
+        inputs: &mut [Option<R>],
+        mut output: W,
+    ) -> io::Result<u64> {
+        if inputs.len() < self.codec.total_shard_count() {
+            return Err(invalid_input(Error::TooFewShards));
+        }
+        if inputs.len() > self.codec.total_shard_count() {
+            return Err(invalid_input(Error::TooManyShards));
+        }
+
+        let data_shards = self.codec.data_shard_count();
+
+        let mut lookaheads: Vec<Lookahead> = inputs
+            .iter()
+            .map(|_| Lookahead::new(self.shard_size))
+            .collect();
+        let mut shards = vec![(vec![0u8; self.shard_size], false); inputs.len()];
+        let mut held = HeldStripe::new(data_shards * self.shard_size);
+
+        loop {
+            let mut trailers = Vec::new();
+            for (i_input, input) in inputs.iter_mut().enumerate() {
+                let (shard, present) = &mut shards[i_input];
+                *present = false;
+
+                let result = match input.as_mut() {
+                    Some(reader) => lookaheads[i_input].next_chunk(reader, shard),
+                    None => continue,
+                };
+
+                match result {
+                    Ok(Chunk::Shard) => *present = true,
+                    Ok(Chunk::Trailer(total_len)) => trailers.push((i_input, total_len)),
+                    Err(_) => *input = None,
+                }
+            }
+
+            if shards.iter().any(|&(_, present)| present) {
+                // streams which ended before the others are missing
+                for &(i_input, _) in trailers.iter() {
+                    inputs[i_input] = None;
+                }
+
+                self.codec
+                    .reconstruct_data(&mut shards)
+                    .map_err(invalid_data)?;
+
+                output.write_all(held.data())?;
+                held.replace(&shards[..data_shards]);
+
+                continue;
+            }
+
+            // all remaining streams are at their trailer
+            let total_len = vote_total_len(inputs, &trailers)?;
+
+            output.write_all(held.last(total_len)?)?;
+            output.flush()?;
+
+            return Ok(total_len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fill_random;
+
+    #[test]
+    fn test_stream_encode_layout() {
+        let r = ReedSolomon::new(4, 2).unwrap();
+        let encoder = StreamEncoder::new(&r, 100).unwrap();
+
+        for &len in [0, 1, 399, 400, 401, 1234].iter() {
+            let mut input = vec![0u8; len];
+            fill_random(&mut input);
+
+            let mut outputs = vec![Vec::new(); 6];
+            assert_eq!(
+                len as u64,
+                encoder.encode(&input[..], &mut outputs).unwrap()
+            );
+
+            let stripes = len.div_ceil(400);
+            for output in outputs.iter() {
+                assert_eq!(stripes * 100 + TRAILER_LEN, output.len());
+                assert_eq!(&make_trailer(len as u64)[..], &output[stripes * 100..]);
+            }
+
+            for stripe in 0..stripes {
+                let shards: Vec<&[u8]> = outputs
+                    .iter()
+                    .map(|o| &o[stripe * 100..(stripe + 1) * 100])
+                    .collect();
+                assert!(r.verify(&shards).unwrap());
+
+                let data: Vec<u8> = shards[..4].concat();
+                let end = len.min((stripe + 1) * 400);
+                assert_eq!(&input[stripe * 400..end], &data[..end - stripe * 400]);
+                assert!(data[end - stripe * 400..].iter().all(|&b| b == 0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_encode_error_handling() {
+        let r = ReedSolomon::new(4, 2).unwrap();
+        assert_eq!(Error::EmptyShard, StreamEncoder::new(&r, 0).unwrap_err());
+
+        let encoder = StreamEncoder::new(&r, 100).unwrap();
+        let mut outputs = vec![Vec::new(); 5];
+        let e = encoder.encode(&[0u8; 10][..], &mut outputs).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+
+        let mut outputs = vec![Vec::new(); 7];
+        let e = encoder.encode(&[0u8; 10][..], &mut outputs).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+    }
+
+    // Reader failing with an I/O error after `remaining` bytes,
+    // unless it reaches the end of `inner` first.
+    struct FailingReader<'a> {
+        inner: &'a [u8],
+        remaining: usize,
+    }
+
+    impl<'a> Read for FailingReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.remaining == 0 && !self.inner.is_empty() {
+                return Err(io::Error::other("failed"));
+            }
+            let n = buf.len().min(self.remaining);
+            let n = self.inner.read(&mut buf[..n])?;
+            self.remaining -= n;
+            Ok(n)
+        }
+    }
+
+    fn encode_random(r: &ReedSolomon, shard_size: usize, len: usize) -> (Vec<u8>, Vec<Vec<u8>>) {
+        let mut input = vec![0u8; len];
+        fill_random(&mut input);
+
+        let mut outputs = vec![Vec::new(); r.total_shard_count()];
+        StreamEncoder::new(r, shard_size)
+            .unwrap()
+            .encode(&input[..], &mut outputs)
+            .unwrap();
+
+        (input, outputs)
+    }
+
+    #[test]
+    fn test_stream_decode_round_trip() {
+        let r = ReedSolomon::new(4, 2).unwrap();
+        let decoder = StreamDecoder::new(&r, 100).unwrap();
+
+        for &len in [0, 1, 399, 400, 401, 1234].iter() {
+            let (input, outputs) = encode_random(&r, 100, len);
+
+            for &(a, b) in [(0, 0), (0, 1), (1, 5), (4, 5), (2, 2)].iter() {
+                let mut inputs: Vec<_> = outputs.iter().map(|o| Some(&o[..])).collect();
+                inputs[a] = None;
+                inputs[b] = None;
+
+                let mut decoded = Vec::new();
+                assert_eq!(
+                    len as u64,
+                    decoder.decode(&mut inputs, &mut decoded).unwrap()
+                );
+                assert_eq!(input, decoded);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_decode_failing_streams() {
+        let r = ReedSolomon::new(4, 2).unwrap();
+        let decoder = StreamDecoder::new(&r, 100).unwrap();
+        let (input, outputs) = encode_random(&r, 100, 1234);
+
+        // one stream fails with an I/O error, one is truncated
+        let mut inputs: Vec<_> = outputs
+            .iter()
+            .map(|o| {
+                Some(FailingReader {
+                    inner: &o[..],
+                    remaining: o.len(),
+                })
+            })
+            .collect();
+        inputs[1].as_mut().unwrap().remaining = 250;
+        let truncated = &outputs[4][..outputs[4].len() - 1];
+        inputs[4].as_mut().unwrap().inner = truncated;
+        inputs[4].as_mut().unwrap().remaining = truncated.len();
+
+        let mut decoded = Vec::new();
+        assert_eq!(1234, decoder.decode(&mut inputs, &mut decoded).unwrap());
+        assert_eq!(input, decoded);
+        assert!(inputs[1].is_none());
+        assert!(inputs[4].is_none());
+        assert_eq!(4, inputs.iter().filter(|i| i.is_some()).count());
+
+        // a third failure leaves too few streams
+        let mut inputs: Vec<_> = outputs.iter().map(|o| Some(&o[..])).collect();
+        inputs[0] = None;
+        inputs[1] = None;
+        inputs[2] = Some(&outputs[2][..150]);
+        let e = decoder.decode(&mut inputs, &mut Vec::new()).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+    }
+
+    #[test]
+    fn test_stream_decode_trailer_verification() {
+        let r = ReedSolomon::new(4, 2).unwrap();
+        let decoder = StreamDecoder::new(&r, 100).unwrap();
+        let (input, mut outputs) = encode_random(&r, 100, 1234);
+
+        // a single bad trailer is outvoted
+        let end = outputs[3].len();
+        outputs[3][end - 8..].copy_from_slice(&1000u64.to_le_bytes());
+        let mut inputs: Vec<_> = outputs.iter().map(|o| Some(&o[..])).collect();
+        let mut decoded = Vec::new();
+        assert_eq!(1234, decoder.decode(&mut inputs, &mut decoded).unwrap());
+        assert_eq!(input, decoded);
+        assert!(inputs[3].is_none());
+
+        // a bad magic marks the stream as failed
+        outputs[3][end - TRAILER_LEN] ^= 1;
+        let mut inputs: Vec<_> = outputs.iter().map(|o| Some(&o[..])).collect();
+        let mut decoded = Vec::new();
+        assert_eq!(1234, decoder.decode(&mut inputs, &mut decoded).unwrap());
+        assert_eq!(input, decoded);
+        assert!(inputs[3].is_none());
+
+        // total lengths which do not fit the last stripe are rejected
+        for &total_len in [0u64, 1200, 1601].iter() {
+            for output in outputs.iter_mut() {
+                output[end - 8..].copy_from_slice(&total_len.to_le_bytes());
+            }
+            let mut inputs: Vec<_> = outputs.iter().map(|o| Some(&o[..])).collect();
+            let e = decoder.decode(&mut inputs, &mut Vec::new()).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, e.kind());
+        }
+    }
+
+    #[test]
+    fn test_stream_decode_error_handling() {
+        let r = ReedSolomon::new(4, 2).unwrap();
+        assert_eq!(Error::EmptyShard, StreamDecoder::new(&r, 0).unwrap_err());
+
+        let decoder = StreamDecoder::new(&r, 100).unwrap();
+        let mut inputs: Vec<Option<&[u8]>> = vec![None; 5];
+        let e = decoder.decode(&mut inputs, &mut Vec::new()).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+
+        let mut inputs: Vec<Option<&[u8]>> = vec![None; 7];
+        let e = decoder.decode(&mut inputs, &mut Vec::new()).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, e.kind());
+
+        let mut inputs: Vec<Option<&[u8]>> = vec![None; 6];
+        let e = decoder.decode(&mut inputs, &mut Vec::new()).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+    }
+}
@@ -50,7 +50,7 @@ quickcheck! {
         for _ in 0..corrupt {
             let mut pos = rand::random::<usize>() % (data + parity);
 
-            while let Some(_) = corrupt_pos_s.iter().find(|&&x| x == pos) {
+            while corrupt_pos_s.contains(&pos) {
                 pos = rand::random::<usize>() % (data + parity);
             }
 
@@ -125,7 +125,7 @@ quickcheck! {
         for _ in 0..corrupt {
             let mut pos = rand::random::<usize>() % (data + parity);
 
-            while let Some(_) = corrupt_pos_s.iter().find(|&&x| x == pos) {
+            while corrupt_pos_s.contains(&pos) {
                 pos = rand::random::<usize>() % (data + parity);
             }
 
@@ -174,7 +174,7 @@ quickcheck! {
         for _ in 0..corrupt {
             let mut pos = rand::random::<usize>() % (data + parity);
 
-            while let Some(_) = corrupt_pos_s.iter().find(|&&x| x == pos) {
+            while corrupt_pos_s.contains(&pos) {
                 pos = rand::random::<usize>() % (data + parity);
             }
 
@@ -237,7 +237,7 @@ quickcheck! {
         for _ in 0..corrupt {
             let mut pos = rand::random::<usize>() % (data + parity);
 
-            while let Some(_) = corrupt_pos_s.iter().find(|&&x| x == pos) {
+            while corrupt_pos_s.contains(&pos) {
                 pos = rand::random::<usize>() % (data + parity);
             }
 
@@ -487,3 +487,41 @@ quickcheck! {
         expect == shards
     }
 }
+
+#[test]
+fn test_partial_parity_merge_same_as_encode() {
+    let r = ReedSolomon::new(5, 3).unwrap();
+
+    let mut expect = make_random_shards!(100, 8);
+    r.encode(&mut expect).unwrap();
+
+    let mut parity = vec![vec![[0; 2]; 100]; 3];
+    let mut partial = vec![vec![[0; 2]; 100]; 3];
+    for i in [3, 0, 4, 2, 1].iter().cloned() {
+        r.partial_parity(i, &expect[i], &mut partial).unwrap();
+        r.merge_parity(&partial, &mut parity).unwrap();
+    }
+
+    assert_eq!(&expect[5..], &parity[..]);
+}
+
+#[test]
+fn test_sum_slices_same_as_add_slice() {
+    use crate::Field;
+
+    let inputs = make_random_shards!(1001, 5);
+    let input_refs: Vec<&[[u8; 2]]> = inputs.iter().map(|x| x.as_slice()).collect();
+
+    let mut expect = inputs[0].clone();
+    for input in inputs[1..].iter() {
+        crate::galois_16::Field::add_slice(input, &mut expect);
+    }
+
+    let mut out = vec![[0; 2]; 1001];
+    crate::galois_16::Field::sum_slices(&input_refs, &mut out);
+    assert_eq!(expect, out);
+
+    let mut out = vec![[0; 2]; 1001];
+    crate::galois_16_native::Field::sum_slices(&input_refs, &mut out);
+    assert_eq!(expect, out);
+}
@@ -1,9 +1,11 @@
 #![allow(dead_code)]
+#![allow(clippy::needless_range_loop)]
 
 extern crate alloc;
 
 use alloc::vec;
 use alloc::vec::Vec;
+use core::mem::MaybeUninit;
 
 use super::{galois_8, Error, SBSError};
 use rand::{self, thread_rng, Rng};
@@ -157,7 +159,7 @@ fn test_encoding() {
     );
 
     let mut bad_shards = make_random_shards!(per_shard, 13);
-    bad_shards[0] = vec![0 as u8];
+    bad_shards[0] = vec![0_u8];
     assert_eq!(
         Error::IncorrectShardSize,
         r.encode(&mut bad_shards).unwrap_err()
@@ -369,7 +371,7 @@ quickcheck! {
         for _ in 0..corrupt {
             let mut pos = rand::random::<usize>() % (data + parity);
 
-            while let Some(_) = corrupt_pos_s.iter().find(|&&x| x == pos) {
+            while corrupt_pos_s.contains(&pos) {
                 pos = rand::random::<usize>() % (data + parity);
             }
 
@@ -444,7 +446,7 @@ quickcheck! {
         for _ in 0..corrupt {
             let mut pos = rand::random::<usize>() % (data + parity);
 
-            while let Some(_) = corrupt_pos_s.iter().find(|&&x| x == pos) {
+            while corrupt_pos_s.contains(&pos) {
                 pos = rand::random::<usize>() % (data + parity);
             }
 
@@ -493,7 +495,7 @@ quickcheck! {
         for _ in 0..corrupt {
             let mut pos = rand::random::<usize>() % (data + parity);
 
-            while let Some(_) = corrupt_pos_s.iter().find(|&&x| x == pos) {
+            while corrupt_pos_s.contains(&pos) {
                 pos = rand::random::<usize>() % (data + parity);
             }
 
@@ -556,7 +558,7 @@ quickcheck! {
         for _ in 0..corrupt {
             let mut pos = rand::random::<usize>() % (data + parity);
 
-            while let Some(_) = corrupt_pos_s.iter().find(|&&x| x == pos) {
+            while corrupt_pos_s.contains(&pos) {
                 pos = rand::random::<usize>() % (data + parity);
             }
 
@@ -935,7 +937,7 @@ fn test_verify_shards_with_buffer_incorrect_buffer_sizes() {
 
         let mut buffer = vec![vec![0; 100]; 2];
 
-        assert_eq!(true, r.verify_with_buffer(&shards, &mut buffer).unwrap());
+        assert!(r.verify_with_buffer(&shards, &mut buffer).unwrap());
     }
     {
         // Test having first buffer being empty
@@ -999,7 +1001,7 @@ fn test_verify_with_buffer_gives_correct_parity_shards() {
         for slice in slices.iter_mut() {
             fill_random(slice);
         }
-        let slices_copy = slices.clone();
+        let slices_copy = slices;
 
         {
             let mut slice_refs = convert_2D_slices!(slices=>to_mut_vec &mut [u8]);
@@ -1195,7 +1197,7 @@ fn shardbyshard_encode_correctly() {
         for slice in slices.iter_mut() {
             fill_random(slice);
         }
-        let mut slices_copy = slices.clone();
+        let mut slices_copy = slices;
 
         {
             let mut slice_refs = convert_2D_slices!(slices=>to_mut_vec &mut [u8]);
@@ -1352,7 +1354,7 @@ fn shardbyshard_encode_sep_correctly() {
         for slice in slices.iter_mut() {
             fill_random(slice);
         }
-        let mut slices_copy = slices.clone();
+        let mut slices_copy = slices;
 
         {
             let (data, parity) = slices.split_at_mut(10);
@@ -1552,9 +1554,9 @@ fn shardbyshard_encode_correctly_more_rigorous() {
             for i in 0..10 {
                 assert_eq!(i, sbs.cur_input_index());
 
-                slice_copy_refs[i].clone_from_slice(&slice_refs[i]);
+                slice_copy_refs[i].clone_from_slice(slice_refs[i]);
                 sbs.encode(&mut slice_copy_refs).unwrap();
-                fill_random(&mut slice_copy_refs[i]);
+                fill_random(slice_copy_refs[i]);
             }
         }
 
@@ -2228,7 +2230,7 @@ fn test_encode_single_sep() {
         for slice in slices.iter_mut() {
             fill_random(slice);
         }
-        let mut slices_copy = slices.clone();
+        let mut slices_copy = slices;
 
         {
             let mut slice_refs = convert_2D_slices!(slices=>to_mut_vec &mut [u8]);
@@ -2241,7 +2243,7 @@ fn test_encode_single_sep() {
             r.encode(&mut slice_refs).unwrap();
 
             for i in 0..10 {
-                r.encode_single_sep(i, &data_copy_refs[i], &mut parity_copy_refs)
+                r.encode_single_sep(i, data_copy_refs[i], &mut parity_copy_refs)
                     .unwrap();
             }
         }
@@ -2277,7 +2279,7 @@ fn test_encode_sep() {
         for slice in slices.iter_mut() {
             fill_random(slice);
         }
-        let mut slices_copy = slices.clone();
+        let mut slices_copy = slices;
 
         {
             let (data_copy, parity_copy) = slices_copy.split_at_mut(10);
@@ -2366,33 +2368,33 @@ fn test_encode_single_sep_error_handling() {
             let mut parity_refs = convert_2D_slices!(parity=>to_mut_vec &mut [u8]);
 
             for i in 0..10 {
-                r.encode_single_sep(i, &data_refs[i], &mut parity_refs)
+                r.encode_single_sep(i, data_refs[i], &mut parity_refs)
                     .unwrap();
             }
 
             assert_eq!(
                 Error::InvalidIndex,
-                r.encode_single_sep(10, &data_refs[0], &mut parity_refs)
+                r.encode_single_sep(10, data_refs[0], &mut parity_refs)
                     .unwrap_err()
             );
             assert_eq!(
                 Error::InvalidIndex,
-                r.encode_single_sep(11, &data_refs[0], &mut parity_refs)
+                r.encode_single_sep(11, data_refs[0], &mut parity_refs)
                     .unwrap_err()
             );
             assert_eq!(
                 Error::InvalidIndex,
-                r.encode_single_sep(12, &data_refs[0], &mut parity_refs)
+                r.encode_single_sep(12, data_refs[0], &mut parity_refs)
                     .unwrap_err()
             );
             assert_eq!(
                 Error::InvalidIndex,
-                r.encode_single_sep(13, &data_refs[0], &mut parity_refs)
+                r.encode_single_sep(13, data_refs[0], &mut parity_refs)
                     .unwrap_err()
             );
             assert_eq!(
                 Error::InvalidIndex,
-                r.encode_single_sep(14, &data_refs[0], &mut parity_refs)
+                r.encode_single_sep(14, data_refs[0], &mut parity_refs)
                     .unwrap_err()
             );
         }
@@ -2404,7 +2406,7 @@ fn test_encode_single_sep_error_handling() {
 
             assert_eq!(
                 Error::TooFewParityShards,
-                r.encode_single_sep(0, &data_refs[0], &mut parity_refs)
+                r.encode_single_sep(0, data_refs[0], &mut parity_refs)
                     .unwrap_err()
             );
         }
@@ -2416,7 +2418,7 @@ fn test_encode_single_sep_error_handling() {
 
             assert_eq!(
                 Error::TooManyParityShards,
-                r.encode_single_sep(0, &data_refs[0], &mut parity_refs)
+                r.encode_single_sep(0, data_refs[0], &mut parity_refs)
                     .unwrap_err()
             );
         }
@@ -2617,3 +2619,323 @@ fn test_encode_single_error_handling() {
         );
     }
 }
+
+#[test]
+fn test_repair_coefficients() {
+    let r = ReedSolomon::new(4, 3).unwrap();
+
+    let mut shards = make_random_shards!(100, 7);
+    r.encode(&mut shards).unwrap();
+
+    let available_s: [&[usize]; 4] = [
+        &[0, 1, 2, 3],
+        &[3, 4, 5, 6],
+        &[6, 0, 4, 2, 5],
+        &[1, 2, 5, 6],
+    ];
+
+    for available in available_s.iter() {
+        for target in 0..7 {
+            let coefficients = r.repair_coefficients(available, target).unwrap();
+
+            let mut repaired = vec![0; 100];
+            for &(i, c) in coefficients.iter() {
+                assert!(available.contains(&i));
+                galois_8::mul_slice_xor(c, &shards[i], &mut repaired);
+            }
+
+            assert_eq!(shards[target], repaired);
+        }
+    }
+}
+
+#[test]
+fn test_repair_coefficients_error_handling() {
+    let r = ReedSolomon::new(4, 3).unwrap();
+
+    assert_eq!(
+        Error::InvalidIndex,
+        r.repair_coefficients(&[0, 1, 2, 3], 7).unwrap_err()
+    );
+    assert_eq!(
+        Error::InvalidIndex,
+        r.repair_coefficients(&[0, 1, 2, 7], 4).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.repair_coefficients(&[0, 1, 2], 4).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.repair_coefficients(&[0, 1, 2, 2], 4).unwrap_err()
+    );
+    assert_eq!(vec![(2, 1)], r.repair_coefficients(&[2], 2).unwrap());
+}
+
+#[test]
+fn test_partial_parity_merge_same_as_encode() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    let mut expect = make_random_shards!(1000, 13);
+    r.encode(&mut expect).unwrap();
+
+    let (data, _) = expect.split_at(10);
+
+    // compute contributions out of order
+    let mut partials: Vec<Vec<Vec<u8>>> = vec![vec![vec![0; 1000]; 3]; 10];
+    for i in (0..10).rev() {
+        r.partial_parity(i, &data[i], &mut partials[i]).unwrap();
+    }
+
+    // merge pairwise, then into a zeroed accumulator
+    let mut left = partials[0].clone();
+    for partial in partials[1..5].iter() {
+        r.merge_parity(partial, &mut left).unwrap();
+    }
+    let mut right = partials[9].clone();
+    for partial in partials[5..9].iter() {
+        r.merge_parity(partial, &mut right).unwrap();
+    }
+
+    let mut parity = vec![vec![0; 1000]; 3];
+    r.merge_parity(&right, &mut parity).unwrap();
+    r.merge_parity(&left, &mut parity).unwrap();
+
+    assert_eq_shards(&expect[10..], &parity);
+}
+
+#[test]
+fn test_partial_parity_merge_error_handling() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+
+    let data = vec![0; 1000];
+    let mut parity = vec![vec![0; 1000]; 3];
+
+    assert_eq!(
+        Error::InvalidIndex,
+        r.partial_parity(10, &data, &mut parity).unwrap_err()
+    );
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.partial_parity(0, &data[1..], &mut parity).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewParityShards,
+        r.partial_parity(0, &data, &mut parity[1..]).unwrap_err()
+    );
+
+    let partial = vec![vec![0; 1000]; 3];
+    assert_eq!(
+        Error::TooFewParityShards,
+        r.merge_parity(&partial[1..], &mut parity).unwrap_err()
+    );
+    assert_eq!(
+        Error::TooManyParityShards,
+        r.merge_parity(&vec![vec![0; 1000]; 4], &mut parity)
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.merge_parity(&vec![vec![0; 999]; 3], &mut parity)
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_block_len() {
+    let r = ReedSolomon::new(10, 3).unwrap();
+    assert_eq!(512 * 1024 / 13 / 64 * 64, r.block_len());
+    let r = ReedSolomon::new(200, 50).unwrap();
+    assert_eq!(4096, r.block_len());
+
+    let mut r = ReedSolomon::new(10, 3).unwrap();
+    r.set_block_len(100);
+    assert_eq!(100, r.clone().block_len());
+}
+
+#[test]
+#[should_panic]
+fn test_block_len_zero() {
+    ReedSolomon::new(10, 3).unwrap().set_block_len(0);
+}
+
+#[test]
+fn test_blocks_same_as_whole_shards() {
+    // 1024 is a multiple of the SIMD vectors, which then leave no tail
+    for &len in [1000, 1024].iter() {
+        let mut expect = make_random_shards!(len, 13);
+        {
+            let r = ReedSolomon::new(10, 3).unwrap();
+            let (data, parity) = expect.split_at_mut(10);
+            for (i, shard) in data.iter().enumerate() {
+                r.encode_single_sep(i, shard, parity).unwrap();
+            }
+        }
+
+        for &block_len in [1, 7, 64, 999, 1000, 1024, 5000].iter() {
+            let mut r = ReedSolomon::new(10, 3).unwrap();
+            r.set_block_len(block_len);
+
+            let mut shards = expect.clone();
+            for shard in shards[10..].iter_mut() {
+                shard.iter_mut().for_each(|b| *b = 0);
+            }
+            r.encode(&mut shards).unwrap();
+            assert_eq_shards(&expect, &shards);
+            assert!(r.verify(&shards).unwrap());
+
+            let mut some = shards_to_option_shards(&shards);
+            some[0] = None;
+            some[4] = None;
+            some[11] = None;
+            r.reconstruct(&mut some).unwrap();
+            assert_eq_shards(&expect, &option_shards_into_shards(some));
+        }
+    }
+}
+
+#[test]
+fn test_small_shards_same_as_shard_by_shard() {
+    let r = ReedSolomon::new(10, 4).unwrap();
+
+    let mut expect = make_random_shards!(1024, 14);
+    {
+        let (data, parity) = expect.split_at_mut(10);
+        for (i, shard) in data.iter().enumerate() {
+            r.encode_single_sep(i, shard, parity).unwrap();
+        }
+    }
+
+    let mut shards = expect.clone();
+    r.encode(&mut shards).unwrap();
+    assert_eq_shards(&expect, &shards);
+
+    // the second round takes the decode plan from the cache
+    for _ in 0..2 {
+        let mut some = shards_to_option_shards(&expect);
+        for &i in [0, 3, 9, 12].iter() {
+            some[i] = None;
+        }
+        r.reconstruct(&mut some).unwrap();
+        assert_eq_shards(&expect, &option_shards_into_shards(some));
+    }
+}
+
+// Buffers of random bytes, so that parts which are not overwritten show.
+fn make_garbage_buffers(per_shard: usize, count: usize) -> Vec<Vec<MaybeUninit<u8>>> {
+    make_random_shards!(per_shard, count)
+        .into_iter()
+        .map(|shard| shard.into_iter().map(MaybeUninit::new).collect())
+        .collect()
+}
+
+#[test]
+fn test_encode_sep_uninit_same_as_encode() {
+    let r = ReedSolomon::new(10, 4).unwrap();
+
+    for &len in [1, 5, 1000].iter() {
+        let mut expect = make_random_shards!(len, 14);
+        r.encode(&mut expect).unwrap();
+
+        let mut buffers = make_garbage_buffers(len, 4);
+        let parity = r.encode_sep_uninit(&expect[..10], &mut buffers).unwrap();
+        assert_eq_shards(&expect[10..], &parity);
+    }
+
+    let data = make_random_shards!(100, 10);
+    assert_eq!(
+        Error::TooFewParityShards,
+        r.encode_sep_uninit(&data, &mut make_garbage_buffers(100, 3))
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.encode_sep_uninit(&data, &mut make_garbage_buffers(99, 4))
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewDataShards,
+        r.encode_sep_uninit(&data[..9], &mut make_garbage_buffers(100, 4))
+            .unwrap_err()
+    );
+}
+
+#[test]
+fn test_reconstruct_uninit() {
+    let r = ReedSolomon::new(10, 4).unwrap();
+
+    let mut expect = make_random_shards!(1000, 14);
+    r.encode(&mut expect).unwrap();
+
+    for missing in [vec![1, 5, 11], vec![0, 9], vec![10, 13], vec![2, 3, 4, 12]].iter() {
+        let mut some = shards_to_option_shards(&expect);
+        for &i in missing.iter() {
+            some[i] = None;
+        }
+
+        let mut buffers = make_garbage_buffers(1000, missing.len());
+        let reconstructed = r.reconstruct_uninit(&some, &mut buffers).unwrap();
+
+        assert_eq!(missing.len(), reconstructed.len());
+        for (&i, shard) in missing.iter().zip(reconstructed.iter()) {
+            assert_eq!(expect[i], *shard);
+        }
+    }
+
+    let some = shards_to_option_shards(&expect);
+    let mut buffers = make_garbage_buffers(1000, 0);
+    assert!(r
+        .reconstruct_uninit(&some, &mut buffers)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_reconstruct_uninit_error_handling() {
+    let r = ReedSolomon::new(10, 4).unwrap();
+
+    let mut expect = make_random_shards!(100, 14);
+    r.encode(&mut expect).unwrap();
+    let mut some = shards_to_option_shards(&expect);
+    some[3] = None;
+    some[12] = None;
+
+    assert_eq!(
+        Error::TooFewBufferShards,
+        r.reconstruct_uninit(&some, &mut make_garbage_buffers(100, 1))
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooManyBufferShards,
+        r.reconstruct_uninit(&some, &mut make_garbage_buffers(100, 3))
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.reconstruct_uninit(&some, &mut make_garbage_buffers(101, 2))
+            .unwrap_err()
+    );
+    assert_eq!(
+        Error::TooFewShards,
+        r.reconstruct_uninit(&some[..13], &mut make_garbage_buffers(100, 2))
+            .unwrap_err()
+    );
+
+    let mut some = shards_to_option_shards(&expect);
+    some[0] = Some(vec![0; 99]);
+    assert_eq!(
+        Error::IncorrectShardSize,
+        r.reconstruct_uninit(&some, &mut make_garbage_buffers(100, 0))
+            .unwrap_err()
+    );
+
+    let mut some = shards_to_option_shards(&expect);
+    for shard in some[..5].iter_mut() {
+        *shard = None;
+    }
+    assert_eq!(
+        Error::TooFewShardsPresent,
+        r.reconstruct_uninit(&some, &mut make_garbage_buffers(100, 5))
+            .unwrap_err()
+    );
+}
@@ -0,0 +1,686 @@
+//! Interoperability with the share files of zfec, as used by Tahoe-LAFS.
+//!
+//! zfec works over the same GF(2^8) as `galois_8`, but builds its Vandermonde
+//! matrix from the evaluation points `0, 1, a, a^2, ...`, where `a` is the
+//! generator `2`, instead of `0, 1, 2, 3, ...`. The resulting systematic
+//! matrices agree for up to 3 shares and differ beyond that, so the codecs of
+//! this module are built with `MatrixProfile::Zfec`, see `codec`.
+//!
+//! A share file is a compact header (see `ShareHeader`) followed by the blocks
+//! of the share. The input is split into stripes of `k * CHUNK_SIZE` bytes,
+//! each share receiving one block of `CHUNK_SIZE` bytes per stripe. The last
+//! stripe is shorter: its blocks have `ceil(len / k)` bytes, and the `pad`
+//! zero bytes appended to it are recorded in the header.
+//!
+//! zfec allows as many shares as required shares, in which case there are no
+//! parity shares and no codec is needed. This is supported by `encode` and
+//! `decode`, but not by `codec`.
+//!
+//! This module is only available with the `std` feature enabled.
+//!
+//! # Example
+//!
+//! ```
+//! # use std::io::Cursor;
+//! # use reed_solomon_erasure::zfec;
+//! let data = b"zfec compatible shares".to_vec();
+//!
+//! let mut shares = vec![Vec::new(); 5];
+//! zfec::encode(3, Cursor::new(&data), &mut shares).unwrap();
+//!
+//! let mut any_three = vec![&shares[1][..], &shares[3][..], &shares[4][..]];
+//! let mut decoded = Vec::new();
+//! zfec::decode(&mut any_three, &mut decoded).unwrap();
+//! assert_eq!(data, decoded);
+//! ```
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::errors::Error;
+use crate::galois_8::ReedSolomon;
+use crate::profile::MatrixProfile;
+
+/// Size of the blocks of full stripes, as used by zfec's `filefec`.
+pub const CHUNK_SIZE: usize = 4096;
+
+/// Maximum number of shares.
+pub const MAX_SHARES: usize = 256;
+
+fn invalid_input(e: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// Number of bits needed to store the values `0..n`.
+fn bits_for(n: usize) -> usize {
+    (usize::BITS - (n - 1).leading_zeros()) as usize
+}
+
+/// Creates a codec producing the same parity as zfec, with
+/// `required_shares` data shards out of `total_shares` shards.
+///
+/// Returns the same errors as `ReedSolomon::new`, in particular
+/// `Error::TooFewParityShards` if `required_shares == total_shares`.
+pub fn codec(required_shares: usize, total_shares: usize) -> Result<ReedSolomon, Error> {
+    ReedSolomon::with_profile(
+        MatrixProfile::Zfec,
+        required_shares,
+        total_shares.saturating_sub(required_shares),
+    )
+}
+
+/// Header of a zfec share file.
+///
+/// The header packs `total_shares - 1` into 8 bits, then `required_shares - 1`,
+/// `pad` and `share_num` into as few bits as their ranges need, big-endian,
+/// and is zero-padded to 2, 3 or 4 bytes.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ShareHeader {
+    /// Total number of shares, `m` in zfec.
+    pub total_shares: usize,
+    /// Number of shares required to decode, `k` in zfec.
+    pub required_shares: usize,
+    /// Number of zero bytes appended to the input.
+    pub pad: usize,
+    /// Index of the share.
+    pub share_num: usize,
+}
+
+impl ShareHeader {
+    fn is_valid(&self) -> bool {
+        self.total_shares <= MAX_SHARES
+            && self.required_shares >= 1
+            && self.required_shares <= self.total_shares
+            && self.pad < self.required_shares
+            && self.share_num < self.total_shares
+    }
+
+    // (bits used, header length)
+    fn bit_lens(total_shares: usize, required_shares: usize) -> (usize, usize) {
+        let bits_used = 8 + 2 * bits_for(total_shares) + bits_for(required_shares);
+        let len = match bits_used {
+            0..=16 => 2,
+            17..=24 => 3,
+            _ => 4,
+        };
+        (bits_used, len)
+    }
+
+    /// Serializes the header.
+    ///
+    /// # Panics
+    /// Panics if the fields are out of range, that is unless
+    /// `1 <= required_shares <= total_shares <= MAX_SHARES`,
+    /// `pad < required_shares` and `share_num < total_shares`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        assert!(self.is_valid(), "share header fields out of range");
+
+        let k_bits = bits_for(self.total_shares);
+        let pad_bits = bits_for(self.required_shares);
+        let share_bits = bits_for(self.total_shares);
+
+        let mut val = (self.total_shares - 1) as u32;
+        val = val << k_bits | (self.required_shares - 1) as u32;
+        val = val << pad_bits | self.pad as u32;
+        val = val << share_bits | self.share_num as u32;
+
+        let (bits_used, len) = Self::bit_lens(self.total_shares, self.required_shares);
+        val <<= len * 8 - bits_used;
+        val.to_be_bytes()[4 - len..].to_vec()
+    }
+
+    /// Reads a header from the start of a share file.
+    ///
+    /// Returns an error of kind `InvalidData` if the fields are out of
+    /// range. Other I/O errors, such as `UnexpectedEof`, are passed through.
+    pub fn read<R: Read>(mut input: R) -> io::Result<ShareHeader> {
+        let mut bytes = [0u8; 4];
+        input.read_exact(&mut bytes[..2])?;
+
+        let total_shares = bytes[0] as usize + 1;
+        let k_bits = bits_for(total_shares);
+        let required_shares = ((u32::from(bytes[1]) >> (8 - k_bits)) as usize) + 1;
+        if required_shares > total_shares {
+            return Err(invalid_data("invalid zfec share header"));
+        }
+
+        let (bits_used, len) = Self::bit_lens(total_shares, required_shares);
+        input.read_exact(&mut bytes[2..len])?;
+
+        let val = u32::from_be_bytes(bytes) >> (32 - bits_used);
+        let share_bits = bits_for(total_shares);
+        let pad_bits = bits_for(required_shares);
+        let mask = |bits: usize| (1u32 << bits) - 1;
+
+        let header = ShareHeader {
+            total_shares,
+            required_shares,
+            pad: (val >> share_bits & mask(pad_bits)) as usize,
+            share_num: (val & mask(share_bits)) as usize,
+        };
+        if !header.is_valid() {
+            return Err(invalid_data("invalid zfec share header"));
+        }
+        Ok(header)
+    }
+}
+
+/// Name of the file of a share, as chosen by zfec: `<prefix>.<share_num>_<total_shares>.fec`,
+/// with both numbers zero-padded to the number of digits of `total_shares`.
+pub fn share_file_name(prefix: &str, share_num: usize, total_shares: usize) -> String {
+    let width = total_shares.to_string().len();
+    format!(
+        "{}.{:0width$}_{:0width$}.fec",
+        prefix,
+        share_num,
+        total_shares,
+        width = width
+    )
+}
+
+// Reads until `buf` is full or the end of the input, returning the bytes read.
+fn read_full<R: Read>(input: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Encodes `input`, from its current position to its end, into one zfec
+/// share per output, any `required_shares` of which suffice to decode it.
+///
+/// Returns the number of bytes encoded.
+///
+/// Returns an error of kind `InvalidInput` wrapping `Error::TooFewDataShards`
+/// if `required_shares == 0`, `Error::TooFewShards` if there are fewer
+/// outputs than `required_shares`, or `Error::TooManyShards` if there are
+/// more than `MAX_SHARES` outputs. I/O errors are passed through.
+pub fn encode<R: Read + Seek, W: Write>(
+    required_shares: usize,
+    mut input: R,
+    outputs: &mut [W],
+) -> io::Result<u64> {
+    let total_shares = outputs.len();
+    if required_shares == 0 {
+        return Err(invalid_input(Error::TooFewDataShards));
+    }
+    if total_shares < required_shares {
+        return Err(invalid_input(Error::TooFewShards));
+    }
+    if total_shares > MAX_SHARES {
+        return Err(invalid_input(Error::TooManyShards));
+    }
+    let codec = if total_shares > required_shares {
+        Some(codec(required_shares, total_shares).map_err(invalid_input)?)
+    } else {
+        None
+    };
+
+    let start = input.stream_position()?;
+    let len = input.seek(SeekFrom::End(0))? - start;
+    input.seek(SeekFrom::Start(start))?;
+
+    let rem = (len % required_shares as u64) as usize;
+    let pad = if rem == 0 { 0 } else { required_shares - rem };
+    for (share_num, output) in outputs.iter_mut().enumerate() {
+        let header = ShareHeader {
+            total_shares,
+            required_shares,
+            pad,
+            share_num,
+        };
+        output.write_all(&header.to_bytes())?;
+    }
+
+    let stripe_len = required_shares * CHUNK_SIZE;
+    let mut stripe = vec![0u8; stripe_len];
+    let mut parity = vec![vec![0u8; CHUNK_SIZE]; total_shares - required_shares];
+    let mut total_read = 0u64;
+    loop {
+        let n = read_full(&mut input, &mut stripe)?;
+        if n == 0 {
+            break;
+        }
+        total_read += n as u64;
+
+        let block_len = n.div_ceil(required_shares);
+        for byte in stripe[n..required_shares * block_len].iter_mut() {
+            *byte = 0;
+        }
+        let data: Vec<&[u8]> = stripe[..required_shares * block_len]
+            .chunks(block_len)
+            .collect();
+        let mut parity_blocks: Vec<&mut [u8]> = parity
+            .iter_mut()
+            .map(|shard| &mut shard[..block_len])
+            .collect();
+        if let Some(ref codec) = codec {
+            codec
+                .encode_sep(&data, &mut parity_blocks)
+                .map_err(invalid_input)?;
+        }
+
+        let blocks = data
+            .iter()
+            .copied()
+            .chain(parity_blocks.iter().map(|block| &**block));
+        for (output, block) in outputs.iter_mut().zip(blocks) {
+            output.write_all(block)?;
+        }
+
+        if n < stripe_len {
+            break;
+        }
+    }
+
+    for output in outputs.iter_mut() {
+        output.flush()?;
+    }
+    Ok(total_read)
+}
+
+/// Decodes zfec shares into `output`. Any `required_shares` distinct shares
+/// suffice, further shares are not read past their header.
+///
+/// Returns the number of bytes decoded.
+///
+/// Returns an error of kind `InvalidInput` wrapping `Error::TooFewShardsPresent`
+/// if there are fewer distinct shares than required, and an error of kind
+/// `InvalidData` if the headers disagree or the shares differ in length.
+/// I/O errors are passed through.
+pub fn decode<R: Read, W: Write>(inputs: &mut [R], mut output: W) -> io::Result<u64> {
+    let mut headers = Vec::with_capacity(inputs.len());
+    for input in inputs.iter_mut() {
+        headers.push(ShareHeader::read(input)?);
+    }
+    let first = match headers.first() {
+        Some(&header) => header,
+        None => return Err(invalid_input(Error::TooFewShardsPresent)),
+    };
+    let consistent = headers.iter().all(|header| {
+        header.total_shares == first.total_shares
+            && header.required_shares == first.required_shares
+            && header.pad == first.pad
+    });
+    if !consistent {
+        return Err(invalid_data("zfec share headers disagree"));
+    }
+
+    // index of the input used for each share number
+    let mut chosen: Vec<Option<usize>> = vec![None; first.total_shares];
+    let mut chosen_count = 0;
+    for (i_input, header) in headers.iter().enumerate() {
+        if chosen_count < first.required_shares && chosen[header.share_num].is_none() {
+            chosen[header.share_num] = Some(i_input);
+            chosen_count += 1;
+        }
+    }
+    if chosen_count < first.required_shares {
+        return Err(invalid_input(Error::TooFewShardsPresent));
+    }
+    let codec = if chosen[..first.required_shares].iter().all(Option::is_some) {
+        None
+    } else {
+        Some(codec(first.required_shares, first.total_shares).map_err(invalid_input)?)
+    };
+
+    let mut shards: Vec<(Vec<u8>, bool)> = vec![(vec![0u8; CHUNK_SIZE], false); first.total_shares];
+    let mut data = Vec::with_capacity(first.required_shares * CHUNK_SIZE);
+    let mut total_written = 0u64;
+    loop {
+        let mut block_len = None;
+        for (shard, i_input) in shards.iter_mut().zip(chosen.iter()) {
+            shard.0.resize(CHUNK_SIZE, 0);
+            shard.1 = false;
+            if let Some(i_input) = *i_input {
+                let n = read_full(&mut inputs[i_input], &mut shard.0)?;
+                if block_len.is_some() && block_len != Some(n) {
+                    return Err(invalid_data("zfec shares differ in length"));
+                }
+                block_len = Some(n);
+                shard.0.truncate(n);
+                shard.1 = true;
+            }
+        }
+        let block_len = block_len.expect("at least one share is chosen; qed");
+
+        data.clear();
+        if block_len > 0 {
+            if let Some(ref codec) = codec {
+                for shard in shards.iter_mut().filter(|shard| !shard.1) {
+                    shard.0.resize(block_len, 0);
+                }
+                codec.reconstruct_data(&mut shards).map_err(invalid_input)?;
+            }
+            for shard in shards[..first.required_shares].iter() {
+                data.extend_from_slice(&shard.0);
+            }
+        }
+
+        if block_len == CHUNK_SIZE {
+            output.write_all(&data)?;
+            total_written += data.len() as u64;
+            continue;
+        }
+
+        if first.pad > data.len() {
+            return Err(invalid_data("zfec padding exceeds the last stripe"));
+        }
+        let last = &data[..data.len() - first.pad];
+        output.write_all(last)?;
+        total_written += last.len() as u64;
+        break;
+    }
+
+    output.flush()?;
+    Ok(total_written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fill_random;
+    use std::fs;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    // Scratch directory removed on drop.
+    struct ScratchDir(PathBuf);
+
+    impl ScratchDir {
+        fn new() -> ScratchDir {
+            let dir =
+                std::env::temp_dir().join(format!("rse-zfec-test-{:016x}", rand::random::<u64>()));
+            fs::create_dir(&dir).unwrap();
+            ScratchDir(dir)
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn run(dir: &ScratchDir, program: &str, args: &[&str]) {
+        let status = Command::new(program)
+            .args(args)
+            .current_dir(&dir.0)
+            .status()
+            .unwrap_or_else(|_| panic!("{} not found", program));
+        assert!(status.success(), "{} failed", program);
+    }
+
+    #[test]
+    fn test_share_header() {
+        // m = 3, k = 2, pad = 1, sh = 0 packs into 13 bits: 00000010 011 0 00
+        let header = ShareHeader {
+            total_shares: 3,
+            required_shares: 2,
+            pad: 1,
+            share_num: 0,
+        };
+        assert_eq!(vec![0x02, 0x60], header.to_bytes());
+        assert_eq!(header, ShareHeader::read(&[0x02, 0x60][..]).unwrap());
+
+        for &(m, k) in [(1, 1), (2, 1), (10, 3), (17, 16), (200, 100), (256, 256)].iter() {
+            for &pad in [0, k - 1].iter() {
+                for &sh in [0, m - 1].iter() {
+                    let header = ShareHeader {
+                        total_shares: m,
+                        required_shares: k,
+                        pad,
+                        share_num: sh,
+                    };
+                    let bytes = header.to_bytes();
+                    let expected_len = match 8 + 2 * bits_for(m) + bits_for(k) {
+                        0..=16 => 2,
+                        17..=24 => 3,
+                        _ => 4,
+                    };
+                    assert_eq!(expected_len, bytes.len());
+                    assert_eq!(header, ShareHeader::read(&bytes[..]).unwrap());
+                }
+            }
+        }
+
+        // k > m, and pad >= k for m = 5, k = 3
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            ShareHeader::read(&[0x02, 0xC0][..]).unwrap_err().kind()
+        );
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            ShareHeader::read(&[0x04, 0x58][..]).unwrap_err().kind()
+        );
+        assert_eq!(
+            io::ErrorKind::UnexpectedEof,
+            ShareHeader::read(&[0x02][..]).unwrap_err().kind()
+        );
+
+        assert_eq!("f.3_5.fec", share_file_name("f", 3, 5));
+        assert_eq!("f.003_100.fec", share_file_name("f", 3, 100));
+    }
+
+    #[test]
+    fn test_codec_matrix() {
+        // points 0, 1, a, a^2 for k = 2 give parity rows [3, 2] and [5, 4]
+        let rs = codec(2, 4).unwrap();
+        assert_eq!(&[3, 2], rs.matrix().get_row(2));
+        assert_eq!(&[5, 4], rs.matrix().get_row(3));
+
+        // same matrix as `ReedSolomon::new` for up to 3 shares only
+        assert_eq!(ReedSolomon::new(2, 1).unwrap(), codec(2, 3).unwrap());
+        assert!(ReedSolomon::new(2, 2).unwrap() != codec(2, 4).unwrap());
+
+        let rs = codec(10, 256).unwrap();
+        let mut shards = vec![vec![0u8; 16]; 256];
+        for shard in shards[..10].iter_mut() {
+            fill_random(shard);
+        }
+        rs.encode(&mut shards).unwrap();
+        let mut some: Vec<_> = shards.iter().cloned().map(Some).collect();
+        for i in (0..10).chain(100..240) {
+            some[i] = None;
+        }
+        rs.reconstruct(&mut some).unwrap();
+        assert_eq!(
+            shards,
+            some.into_iter().map(Option::unwrap).collect::<Vec<_>>()
+        );
+
+        assert_eq!(Error::TooFewParityShards, codec(3, 3).unwrap_err());
+        assert_eq!(Error::TooFewDataShards, codec(0, 3).unwrap_err());
+        assert_eq!(Error::TooManyShards, codec(3, 257).unwrap_err());
+    }
+
+    #[test]
+    fn test_encode_layout() {
+        let mut data = vec![0u8; 3 * CHUNK_SIZE + 7];
+        fill_random(&mut data);
+
+        let mut shares = vec![Vec::new(); 5];
+        assert_eq!(
+            data.len() as u64,
+            encode(3, Cursor::new(&data), &mut shares).unwrap()
+        );
+
+        // one full stripe, then 7 bytes in blocks of 3 bytes with 2 bytes of padding
+        for (share_num, share) in shares.iter().enumerate() {
+            let header = ShareHeader::read(&share[..]).unwrap();
+            assert_eq!(
+                ShareHeader {
+                    total_shares: 5,
+                    required_shares: 3,
+                    pad: 2,
+                    share_num,
+                },
+                header
+            );
+            assert_eq!(header.to_bytes().len() + CHUNK_SIZE + 3, share.len());
+        }
+        let body = |i: usize| &shares[i][shares[i].len() - CHUNK_SIZE - 3..];
+        assert_eq!(&data[..CHUNK_SIZE], &body(0)[..CHUNK_SIZE]);
+        assert_eq!(
+            &data[2 * CHUNK_SIZE..3 * CHUNK_SIZE],
+            &body(2)[..CHUNK_SIZE]
+        );
+        assert_eq!(
+            &data[3 * CHUNK_SIZE + 6..],
+            &body(2)[CHUNK_SIZE..CHUNK_SIZE + 1]
+        );
+        assert_eq!(&[0, 0], &body(2)[CHUNK_SIZE + 1..]);
+
+        let codec = codec(3, 5).unwrap();
+        let stripe: Vec<&[u8]> = (0..5).map(|i| &body(i)[..CHUNK_SIZE]).collect();
+        assert!(codec.verify(&stripe).unwrap());
+
+        assert_eq!(
+            io::ErrorKind::InvalidInput,
+            encode(6, Cursor::new(&data), &mut shares)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        for &len in [
+            0,
+            1,
+            5,
+            3 * CHUNK_SIZE,
+            3 * CHUNK_SIZE + 1,
+            7 * CHUNK_SIZE + 2,
+        ]
+        .iter()
+        {
+            let mut data = vec![0u8; len];
+            fill_random(&mut data);
+
+            for &total in [3, 4, 7].iter() {
+                let mut shares = vec![Vec::new(); total];
+                encode(3, Cursor::new(&data), &mut shares).unwrap();
+
+                let selections: &[&[usize]] = &[&[0, 1, 2], &[2, 0, 1], &[0, 2, 3], &[6, 4, 5, 1]];
+                for selection in selections.iter() {
+                    if selection.iter().any(|&i| i >= total) {
+                        continue;
+                    }
+                    let mut inputs: Vec<&[u8]> =
+                        selection.iter().map(|&i| &shares[i][..]).collect();
+                    let mut decoded = Vec::new();
+                    assert_eq!(len as u64, decode(&mut inputs, &mut decoded).unwrap());
+                    assert_eq!(data, decoded);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let mut data = vec![0u8; CHUNK_SIZE + 100];
+        fill_random(&mut data);
+        let mut shares = vec![Vec::new(); 4];
+        encode(2, Cursor::new(&data), &mut shares).unwrap();
+
+        let mut duplicate = [&shares[1][..], &shares[1][..]];
+        assert_eq!(
+            io::ErrorKind::InvalidInput,
+            decode(&mut duplicate, Vec::new()).unwrap_err().kind()
+        );
+
+        let mut other = vec![Vec::new(); 5];
+        encode(2, Cursor::new(&data), &mut other).unwrap();
+        let mut mixed = [&shares[0][..], &other[1][..]];
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            decode(&mut mixed, Vec::new()).unwrap_err().kind()
+        );
+
+        let truncated = &shares[3][..shares[3].len() - 1];
+        let mut unequal = [&shares[0][..], truncated];
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            decode(&mut unequal, Vec::new()).unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    #[ignore = "needs zfec"]
+    fn test_zfec_shares_decode() {
+        for &len in [1, 5, 3 * CHUNK_SIZE, 3 * CHUNK_SIZE + 7].iter() {
+            let mut data = vec![0u8; len];
+            fill_random(&mut data);
+            let dir = ScratchDir::new();
+            fs::write(dir.path("data"), &data).unwrap();
+            run(
+                &dir,
+                "zfec",
+                &["-d", ".", "-p", "data", "-k", "3", "-m", "5", "data"],
+            );
+
+            let theirs: Vec<Vec<u8>> = (0..5)
+                .map(|i| fs::read(dir.path(&share_file_name("data", i, 5))).unwrap())
+                .collect();
+            for (share_num, share) in theirs.iter().enumerate() {
+                assert_eq!(
+                    ShareHeader {
+                        total_shares: 5,
+                        required_shares: 3,
+                        pad: (3 - len % 3) % 3,
+                        share_num,
+                    },
+                    ShareHeader::read(&share[..]).unwrap()
+                );
+            }
+
+            let mut ours = vec![Vec::new(); 5];
+            encode(3, Cursor::new(&data), &mut ours).unwrap();
+            assert_eq!(theirs, ours);
+
+            let mut inputs = [&theirs[4][..], &theirs[0][..], &theirs[3][..]];
+            let mut decoded = Vec::new();
+            decode(&mut inputs, &mut decoded).unwrap();
+            assert_eq!(data, decoded);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs zfec"]
+    fn test_zunfec_reads_ours() {
+        let mut data = vec![0u8; 7 * CHUNK_SIZE + 2];
+        fill_random(&mut data);
+        let dir = ScratchDir::new();
+
+        let mut shares = vec![Vec::new(); 7];
+        encode(3, Cursor::new(&data), &mut shares).unwrap();
+        let names: Vec<String> = [6, 1, 4]
+            .iter()
+            .map(|&i| {
+                let name = share_file_name("data", i, 7);
+                fs::write(dir.path(&name), &shares[i]).unwrap();
+                name
+            })
+            .collect();
+
+        let mut args = vec!["-o", "decoded"];
+        args.extend(names.iter().map(String::as_str));
+        run(&dir, "zunfec", &args);
+        assert_eq!(data, fs::read(dir.path("decoded")).unwrap());
+    }
+}